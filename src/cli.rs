@@ -1,20 +1,53 @@
 use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
-#[command(name = "persist-scan", version, about = "Defensive persistence + suspicious indicator scanner")]
+#[command(
+    name = "persist-scan",
+    version,
+    about = "Defensive persistence + suspicious indicator scanner",
+    after_help = "Exit status: 0 when the command completed and no --fail-on / --max-score threshold was crossed, \
+                  1 when a threshold was crossed, 2 on errors (collection, rules, options or files), \
+                  3 when collection was partial (sources that could not be read are listed in the report)"
+)]
 pub struct Args {
-    /// Path to rules directory (contains linux/ and windows/)
-    #[arg(long, default_value = "./rules")]
-    pub rules_dir: String,
+    /// Extra rules directory (contains linux/, windows/ and macos/). Its rules are added to the
+    /// built-in rule pack, replacing built-in rules with the same ID
+    #[arg(long)]
+    pub rules_dir: Option<String>,
 
     /// Output JSON (for scan command)
     #[arg(long)]
     pub json: bool,
 
-    /// OS selection: auto|linux|windows
+    /// OS selection: auto|linux|windows|macos (auto: host OS, or detected per offline root)
     #[arg(long, default_value = "auto")]
     pub os: String,
 
+    /// Offline mode: scan a mounted image / extracted filesystem rooted at this directory
+    /// instead of the live host (no host commands are executed). Repeatable; with --os auto
+    /// the OS of each root is detected, and an evidence folder holding several mounted
+    /// partitions is split into one root per partition
+    #[arg(long, global = true, conflicts_with = "from_archive")]
+    pub root: Vec<String>,
+
+    /// Offline mode: scan a triage bundle (.tar, .tar.gz/.tgz, .zip) without extracting it;
+    /// entries are treated as paths relative to the filesystem root (etc/passwd, home/...)
+    #[arg(long, global = true)]
+    pub from_archive: Option<String>,
+
+    /// Suppressions file (YAML) of known-good findings to take out of the score (scan, diff)
+    #[arg(long, global = true)]
+    pub suppressions: Option<String>,
+
+    /// Also write an ATT&CK Navigator layer (JSON) of the techniques that fired (scan, diff)
+    #[arg(long, global = true)]
+    pub attack_layer: Option<String>,
+
+    /// Scoring file (YAML): severity points, confidence multipliers, rule weights, repeat decay
+    /// and tags left out of the score (scan, diff)
+    #[arg(long, global = true)]
+    pub scoring: Option<String>,
+
     #[command(subcommand)]
     pub cmd: Command,
 }
@@ -22,7 +55,12 @@ pub struct Args {
 #[derive(Subcommand, Debug)]
 pub enum Command {
     /// Scan and print findings
-    Scan,
+    Scan {
+        #[command(flatten)]
+        filter: FilterArgs,
+        #[command(flatten)]
+        thresholds: ThresholdArgs,
+    },
 
     /// Save a baseline snapshot of collected artifacts to a JSON file
     Baseline {
@@ -38,5 +76,88 @@ pub enum Command {
         show_removed: bool,
         #[arg(long)]
         json: bool,
+        #[command(flatten)]
+        filter: FilterArgs,
+        #[command(flatten)]
+        thresholds: ThresholdArgs,
+    },
+
+    /// Work with the rule set (built-in rules plus --rules-dir)
+    Rules {
+        #[command(subcommand)]
+        cmd: RulesCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RulesCommand {
+    /// Show the effective rule set and where each rule comes from
+    List,
+
+    /// Check every rule file in --rules-dir for mistakes; exits non-zero if any errors are found
+    Lint,
+
+    /// Convert Sigma rules into persist-scan rules, written as <out>/<os>/<ID>.yml (load them with --rules-dir <out>)
+    ImportSigma {
+        /// Directory of Sigma rules (searched recursively)
+        dir: String,
+        #[arg(long, default_value = "sigma-rules")]
+        out: String,
     },
+
+    /// Run the sample artifacts in each rule's `tests:` through the matcher; exits non-zero on failures
+    Test {
+        /// Only test the rule with this ID
+        #[arg(long)]
+        rule: Option<String>,
+    },
+}
+
+/// Exit-status gates for `scan` and `diff`.
+#[derive(clap::Args, Debug)]
+pub struct ThresholdArgs {
+    /// Exit with status 1 if there is a finding of this severity or higher: low|medium|high
+    #[arg(long)]
+    pub fail_on: Option<String>,
+
+    /// Exit with status 1 if the risk score is above this
+    #[arg(long, value_parser = clap::value_parser!(i32).range(0..=100))]
+    pub max_score: Option<i32>,
+}
+
+/// Finding filters and grouping for `scan` and `diff`. Filtered-out findings are neither
+/// shown nor scored; repeated options of one kind match any of their values.
+#[derive(clap::Args, Debug)]
+pub struct FilterArgs {
+    /// Only findings of this severity or higher: low|medium|high
+    #[arg(long)]
+    pub min_severity: Option<String>,
+
+    /// Only findings of this confidence or higher: low|medium|high
+    #[arg(long)]
+    pub min_confidence: Option<String>,
+
+    /// Only findings of rules with this tag (repeatable)
+    #[arg(long)]
+    pub tag: Vec<String>,
+
+    /// Drop findings of rules with this tag, e.g. inventory (repeatable)
+    #[arg(long)]
+    pub exclude_tag: Vec<String>,
+
+    /// Only findings of this rule ID (repeatable)
+    #[arg(long)]
+    pub rule: Vec<String>,
+
+    /// Only findings on artifacts of this kind, e.g. windows_run_key (repeatable)
+    #[arg(long)]
+    pub kind: Vec<String>,
+
+    /// Only findings on artifacts owned by this user (repeatable)
+    #[arg(long)]
+    pub user: Vec<String>,
+
+    /// Group human output by rule|kind|source|path
+    #[arg(long)]
+    pub group_by: Option<String>,
 }
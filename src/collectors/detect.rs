@@ -0,0 +1,78 @@
+use anyhow::Result;
+
+use crate::engine::TargetOs;
+use crate::vfs::{find_case_insensitive, Vfs};
+
+/// Identify the OS installed at the root of an offline filesystem by probing for files
+/// only that OS has. Windows and macOS are checked first: a macOS image also has an /etc.
+pub fn detect_offline_os(fs: &dyn Vfs) -> Result<Option<TargetOs>> {
+    if find_case_insensitive(fs, "/Windows/System32/config")?.is_some() {
+        return Ok(Some(TargetOs::Windows));
+    }
+
+    if fs.read("/System/Library/CoreServices/SystemVersion.plist")?.is_some()
+        || (fs.is_dir("/Users") && fs.is_dir("/private/etc"))
+    {
+        return Ok(Some(TargetOs::MacOs));
+    }
+
+    // /etc/os-release is usually a symlink into /usr/lib; either may be missing on minimal systems
+    for probe in ["/etc/os-release", "/usr/lib/os-release", "/etc/passwd"] {
+        if fs.read(probe)?.is_some() {
+            return Ok(Some(TargetOs::Linux));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::HostFs;
+
+    #[test]
+    fn detects_each_os_by_its_files() {
+        let dir = std::env::temp_dir().join(format!("persist-scan-detect-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let files: &[(&str, &[&str])] = &[
+            ("win", &["WINDOWS/system32/CONFIG/SYSTEM"]),
+            // A macOS image also has /etc and /usr/lib
+            ("mac", &["System/Library/CoreServices/SystemVersion.plist", "private/etc/hosts", "etc/passwd"]),
+            ("mac_no_plist", &["Users/.localized", "private/etc/hosts"]),
+            ("linux", &["usr/lib/os-release"]),
+            ("linux_passwd", &["etc/passwd"]),
+            ("nothing", &["etc/hostname", "Users/x"]),
+        ];
+        for (root, paths) in files {
+            for p in *paths {
+                let path = dir.join(root).join(p);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, b"x").unwrap();
+            }
+        }
+        // Absolute link, resolved inside the root rather than on the host
+        std::fs::create_dir_all(dir.join("linux/etc")).unwrap();
+        std::os::unix::fs::symlink("/usr/lib/os-release", dir.join("linux/etc/os-release")).unwrap();
+        std::fs::create_dir_all(dir.join("dangling/etc")).unwrap();
+        std::os::unix::fs::symlink("/usr/lib/os-release", dir.join("dangling/etc/os-release")).unwrap();
+
+        let detected: Vec<(&str, Option<TargetOs>)> = ["win", "mac", "mac_no_plist", "linux", "linux_passwd", "nothing", "dangling"]
+            .into_iter()
+            .map(|root| (root, detect_offline_os(&HostFs::new(dir.join(root))).unwrap()))
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            detected,
+            [
+                ("win", Some(TargetOs::Windows)),
+                ("mac", Some(TargetOs::MacOs)),
+                ("mac_no_plist", Some(TargetOs::MacOs)),
+                ("linux", Some(TargetOs::Linux)),
+                ("linux_passwd", Some(TargetOs::Linux)),
+                ("nothing", None),
+                ("dangling", None),
+            ]
+        );
+    }
+}
@@ -1,36 +1,55 @@
-use anyhow::{Context, Result};
-use std::process::Command;
-use walkdir::WalkDir;
+use anyhow::Result;
 
 use crate::engine::Artifact;
-use crate::util::paths::{extract_executable_path_guess, expand_tilde};
+use crate::util::paths::extract_executable_path_guess;
+use crate::util::users::parse_passwd;
+use crate::vfs::{join, Vfs};
 
+/// Crontab spool locations: Debian/Ubuntu first, then RHEL/Fedora.
+const CRON_SPOOL_DIRS: &[&str] = &["/var/spool/cron/crontabs", "/var/spool/cron"];
+
+#[cfg(unix)]
 pub fn collect_linux() -> Result<Vec<Artifact>> {
+    use crate::util::paths::expand_tilde;
+    use crate::vfs::HostFs;
+
+    let fs = HostFs::live();
+    let user = std::env::var("USER").ok();
+    let dir = expand_tilde("~/.config/autostart").display().to_string();
+
     let mut out = vec![];
-    out.extend(collect_autostart()?);
-    out.extend(collect_user_crontab()?);
+    out.extend(collect_autostart(&fs, &dir, user.as_deref())?);
+    out.extend(collect_user_crontab(user.as_deref())?);
     Ok(out)
 }
 
-fn collect_autostart() -> Result<Vec<Artifact>> {
+/// Offline collection against a mounted image, extracted filesystem or archive.
+/// Users come from the image's own /etc/passwd and no host commands are executed.
+pub fn collect_linux_offline(fs: &dyn Vfs) -> Result<Vec<Artifact>> {
     let mut out = vec![];
-    let dir = expand_tilde("~/.config/autostart");
-    if !dir.exists() {
-        return Ok(out);
+
+    let users = match fs.read_to_string("/etc/passwd")? {
+        Some(content) => parse_passwd(&content),
+        None => vec![],
+    };
+    for u in &users {
+        let dir = join(&u.home, ".config/autostart");
+        out.extend(collect_autostart(fs, &dir, Some(&u.name))?);
     }
 
-    for entry in WalkDir::new(&dir).max_depth(1) {
-        let entry = entry?;
-        if !entry.file_type().is_file() {
-            continue;
-        }
-        let path = entry.path();
-        if path.extension().and_then(|e| e.to_str()) != Some("desktop") {
+    out.extend(collect_cron_spool(fs)?);
+    Ok(out)
+}
+
+fn collect_autostart(fs: &dyn Vfs, dir: &str, user: Option<&str>) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+
+    for entry in fs.read_dir(dir)? {
+        if entry.is_dir || !entry.name.ends_with(".desktop") {
             continue;
         }
-
-        let content = std::fs::read_to_string(path)
-            .with_context(|| format!("Failed reading {}", path.display()))?;
+        let path = entry.path_in(dir);
+        let Some(content) = fs.read_to_string(&path)? else { continue };
 
         let exec_line = content
             .lines()
@@ -42,32 +61,57 @@ fn collect_autostart() -> Result<Vec<Artifact>> {
 
         out.push(Artifact {
             kind: "linux_autostart".to_string(),
-            source: path.display().to_string(),
-            name: path.file_name().and_then(|n| n.to_str()).unwrap_or("unknown").to_string(),
+            source: path,
+            name: entry.name,
+            user: user.map(str::to_string),
             command: cmd,
             path: path_guess,
             raw: None,
+            attrs: Default::default(),
         });
     }
 
     Ok(out)
 }
 
-fn collect_user_crontab() -> Result<Vec<Artifact>> {
-    let mut out = vec![];
-
-    let output = Command::new("crontab").arg("-l").output();
+#[cfg(unix)]
+fn collect_user_crontab(user: Option<&str>) -> Result<Vec<Artifact>> {
+    let output = std::process::Command::new("crontab").arg("-l").output();
     let output = match output {
         Ok(o) => o,
-        Err(_) => return Ok(out),
+        Err(_) => return Ok(vec![]),
     };
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    if stdout.trim().is_empty() {
-        return Ok(out);
+    Ok(parse_crontab(&stdout, "crontab -l", user))
+}
+
+/// Read per-user crontabs straight from the spool instead of running `crontab -l`.
+/// Spool files are named after the user that owns them.
+fn collect_cron_spool(fs: &dyn Vfs) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+
+    for spool in CRON_SPOOL_DIRS {
+        for entry in fs.read_dir(spool)? {
+            if entry.is_dir {
+                continue;
+            }
+            let source = entry.path_in(spool);
+            let Some(content) = fs.read_to_string(&source)? else { continue };
+            out.extend(parse_crontab(&content, &source, Some(&entry.name)));
+        }
     }
 
-    for (idx, line) in stdout.lines().enumerate() {
+    Ok(out)
+}
+
+fn parse_crontab(content: &str, source: &str, user: Option<&str>) -> Vec<Artifact> {
+    let mut out = vec![];
+    if content.trim().is_empty() {
+        return out;
+    }
+
+    for (idx, line) in content.lines().enumerate() {
         let l = line.trim();
         if l.is_empty() || l.starts_with('#') {
             continue;
@@ -78,15 +122,17 @@ fn collect_user_crontab() -> Result<Vec<Artifact>> {
 
         out.push(Artifact {
             kind: "linux_user_crontab".to_string(),
-            source: "crontab -l".to_string(),
+            source: source.to_string(),
             name: format!("line:{}", idx + 1),
+            user: user.map(str::to_string),
             command: cmd,
             path: path_guess,
             raw: Some(l.to_string()),
+            attrs: Default::default(),
         });
     }
 
-    Ok(out)
+    out
 }
 
 fn guess_cron_command(line: &str) -> Option<String> {
@@ -0,0 +1,377 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+use crate::engine::Artifact;
+use crate::parsers::bookmark::{bookmark_path, is_bookmark};
+use crate::parsers::launchd::{parse_dictionary, parse_launchd_plist};
+use crate::vfs::{join, Vfs};
+use plist::Value;
+
+/// (directory, artifact kind, shipped by Apple on the sealed system volume)
+const LAUNCH_DIRS: &[(&str, &str, bool)] = &[
+    ("/Library/LaunchAgents", "macos_launch_agent", false),
+    ("/Library/LaunchDaemons", "macos_launch_daemon", false),
+    ("/System/Library/LaunchAgents", "macos_launch_agent", true),
+    ("/System/Library/LaunchDaemons", "macos_launch_daemon", true),
+];
+
+/// /etc and /var are symlinks into /private on macOS; images often only carry the real path.
+const PERIODIC_DIRS: &[&str] = &["/private/etc/periodic", "/etc/periodic"];
+const ROOT_HOMES: &[&str] = &["/private/var/root", "/var/root"];
+const BTM_DIRS: &[&str] = &[
+    "/private/var/db/com.apple.backgroundtaskmanagement",
+    "/var/db/com.apple.backgroundtaskmanagement",
+];
+
+/// Login/logout hooks are read from root's loginwindow defaults (older guides write them to /Library).
+const LOGINWINDOW_SYSTEM: &[&str] = &["/Library/Preferences/com.apple.loginwindow.plist"];
+const USER_BTM: &str = "Library/Application Support/com.apple.backgroundtaskmanagementagent/backgrounditems.btm";
+
+/// launchd jobs, login items and periodic scripts. Works the same on a mounted image,
+/// an archive or the live host: everything is read from files, nothing is executed.
+pub fn collect_macos_offline(fs: &dyn Vfs) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    let users = user_homes(fs)?;
+
+    for (dir, kind, apple) in LAUNCH_DIRS {
+        out.extend(collect_launchd_dir(fs, dir, kind, *apple, None)?);
+    }
+    for (user, home) in &users {
+        let dir = join(home, "Library/LaunchAgents");
+        out.extend(collect_launchd_dir(fs, &dir, "macos_launch_agent", false, Some(user))?);
+    }
+
+    for path in LOGINWINDOW_SYSTEM {
+        out.extend(collect_loginwindow(fs, path, None)?);
+    }
+    for (user, home) in &users {
+        for name in ["loginwindow.plist", "com.apple.loginwindow.plist"] {
+            let path = join(&join(home, "Library/Preferences"), name);
+            out.extend(collect_loginwindow(fs, &path, Some(user))?);
+        }
+        let path = join(home, "Library/Preferences/com.apple.loginitems.plist");
+        out.extend(collect_loginitems(fs, &path, user)?);
+        out.extend(collect_btm(fs, &join(home, USER_BTM), Some(user))?);
+    }
+    if let Some(dir) = first_dir(fs, BTM_DIRS) {
+        for entry in fs.read_dir(&dir)? {
+            if !entry.is_dir && entry.name.ends_with(".btm") {
+                out.extend(collect_btm(fs, &entry.path_in(&dir), None)?);
+            }
+        }
+    }
+
+    out.extend(collect_periodic(fs)?);
+    Ok(out)
+}
+
+/// Home directories under /Users plus root's, keyed by account name (the directory name).
+fn user_homes(fs: &dyn Vfs) -> Result<Vec<(String, String)>> {
+    let mut out = vec![];
+    for entry in fs.read_dir("/Users")? {
+        if entry.is_dir && entry.name != "Shared" && !entry.name.starts_with('.') {
+            out.push((entry.name.clone(), entry.path_in("/Users")));
+        }
+    }
+    if let Some(home) = first_dir(fs, ROOT_HOMES) {
+        out.push(("root".to_string(), home));
+    }
+    Ok(out)
+}
+
+fn first_dir(fs: &dyn Vfs, candidates: &[&str]) -> Option<String> {
+    candidates.iter().find(|d| fs.is_dir(d)).map(|d| d.to_string())
+}
+
+/// One artifact per job plist. Files launchd itself could not load (invalid plists) are skipped.
+fn collect_launchd_dir(fs: &dyn Vfs, dir: &str, kind: &str, apple: bool, user: Option<&str>) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+
+    for entry in fs.read_dir(dir)? {
+        if entry.is_dir || !entry.name.ends_with(".plist") {
+            continue;
+        }
+        let path = entry.path_in(dir);
+        let Some(bytes) = fs.read(&path)? else { continue };
+        let Ok(job) = parse_launchd_plist(&bytes) else { continue };
+
+        let mut attrs = BTreeMap::new();
+        attrs.insert("run_at_load".to_string(), job.run_at_load.to_string());
+        if let Some(label) = &job.label {
+            attrs.insert("label".to_string(), label.clone());
+        }
+        if let Some(keep_alive) = &job.keep_alive {
+            attrs.insert("keep_alive".to_string(), keep_alive.clone());
+        }
+        if !job.program_arguments.is_empty() {
+            attrs.insert("program_arguments".to_string(), serde_json::to_string(&job.program_arguments)?);
+        }
+        if let Some(user_name) = &job.user_name {
+            attrs.insert("user_name".to_string(), user_name.clone());
+        }
+        if !job.triggers.is_empty() {
+            attrs.insert("triggers".to_string(), job.triggers.join(","));
+        }
+        if job.disabled {
+            attrs.insert("disabled".to_string(), "true".to_string());
+        }
+        if apple {
+            attrs.insert("apple_system".to_string(), "true".to_string());
+        }
+
+        out.push(Artifact {
+            kind: kind.to_string(),
+            source: path,
+            name: entry.name,
+            user: user.map(str::to_string),
+            command: job.command_line(),
+            path: job.executable().map(str::to_string),
+            raw: None,
+            attrs,
+        });
+    }
+
+    Ok(out)
+}
+
+/// LoginHook/LogoutHook scripts and legacy AutoLaunchedApplicationDictionary entries.
+fn collect_loginwindow(fs: &dyn Vfs, path: &str, user: Option<&str>) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    let Some(bytes) = fs.read(path)? else { return Ok(out) };
+    let Ok(dict) = parse_dictionary(&bytes) else { return Ok(out) };
+
+    for hook in ["LoginHook", "LogoutHook"] {
+        if let Some(script) = dict.get(hook).and_then(Value::as_string) {
+            out.push(login_artifact("macos_login_hook", path, hook, user, script));
+        }
+    }
+
+    let apps = dict.get("AutoLaunchedApplicationDictionary").and_then(Value::as_array);
+    for app in apps.into_iter().flatten() {
+        let Some(app_path) = app.as_dictionary().and_then(|d| d.get("Path")).and_then(Value::as_string) else {
+            continue;
+        };
+        out.push(login_artifact("macos_login_item", path, &file_name(app_path), user, app_path));
+    }
+
+    Ok(out)
+}
+
+/// Shared file list login items (macOS 10.12 and earlier); the target is kept as an Alias/bookmark.
+fn collect_loginitems(fs: &dyn Vfs, path: &str, user: &str) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    let Some(bytes) = fs.read(path)? else { return Ok(out) };
+    let Ok(dict) = parse_dictionary(&bytes) else { return Ok(out) };
+
+    let items = dict
+        .get("SessionItems")
+        .and_then(Value::as_dictionary)
+        .and_then(|d| d.get("CustomListItems"))
+        .and_then(Value::as_array);
+    for item in items.into_iter().flatten() {
+        let Some(item) = item.as_dictionary() else { continue };
+        let target = item
+            .get("Alias")
+            .and_then(Value::as_data)
+            .filter(|d| is_bookmark(d))
+            .and_then(|d| bookmark_path(d).ok().flatten());
+        let name = item.get("Name").and_then(Value::as_string).map(str::to_string);
+        let Some(name) = name.or_else(|| target.as_deref().map(file_name)) else { continue };
+
+        let mut artifact = login_artifact("macos_login_item", path, &name, Some(user), "");
+        artifact.command = target.clone();
+        artifact.path = target;
+        out.push(artifact);
+    }
+
+    Ok(out)
+}
+
+/// Background task management stores (backgrounditems.btm, BackgroundItems-v*.btm).
+/// These are keyed archives; item targets are kept as bookmarks or file:// URLs.
+fn collect_btm(fs: &dyn Vfs, path: &str, user: Option<&str>) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    let Some(bytes) = fs.read(path)? else { return Ok(out) };
+    let Ok(dict) = parse_dictionary(&bytes) else { return Ok(out) };
+    let Some(objects) = dict.get("$objects").and_then(Value::as_array) else { return Ok(out) };
+
+    let mut targets = vec![];
+    for obj in objects {
+        let target = match obj {
+            Value::Data(d) if is_bookmark(d) => bookmark_path(d).ok().flatten(),
+            Value::String(s) => s.strip_prefix("file://").map(percent_decode),
+            _ => None,
+        };
+        if let Some(t) = target.map(|t| t.trim_end_matches('/').to_string()) {
+            if !t.is_empty() && !targets.contains(&t) {
+                targets.push(t);
+            }
+        }
+    }
+
+    for target in targets {
+        out.push(login_artifact("macos_login_item", path, &file_name(&target), user, &target));
+    }
+    Ok(out)
+}
+
+fn login_artifact(kind: &str, source: &str, name: &str, user: Option<&str>, target: &str) -> Artifact {
+    Artifact {
+        kind: kind.to_string(),
+        source: source.to_string(),
+        name: name.to_string(),
+        user: user.map(str::to_string),
+        command: Some(target.to_string()).filter(|t| !t.is_empty()),
+        path: Some(target.to_string()).filter(|t| !t.is_empty()),
+        raw: None,
+        attrs: BTreeMap::new(),
+    }
+}
+
+/// Scripts run by periodic(8) from launchd (daily/weekly/monthly).
+fn collect_periodic(fs: &dyn Vfs) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    let Some(base) = first_dir(fs, PERIODIC_DIRS) else { return Ok(out) };
+
+    for entry in fs.read_dir(&base)? {
+        if !entry.is_dir {
+            continue;
+        }
+        let dir = entry.path_in(&base);
+        for script in fs.read_dir(&dir)? {
+            if script.is_dir {
+                continue;
+            }
+            let path = script.path_in(&dir);
+            let mut attrs = BTreeMap::new();
+            attrs.insert("schedule".to_string(), entry.name.clone());
+            out.push(Artifact {
+                kind: "macos_periodic".to_string(),
+                source: dir.clone(),
+                name: script.name,
+                user: None,
+                command: Some(path.clone()),
+                path: Some(path),
+                raw: None,
+                attrs,
+            });
+        }
+    }
+
+    Ok(out)
+}
+
+fn file_name(path: &str) -> String {
+    path.rsplit('/').find(|p| !p.is_empty()).unwrap_or(path).to_string()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(b) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::HostFs;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/macos");
+
+    #[test]
+    fn launchd_jobs_login_items_and_periodic_scripts() {
+        let dir = std::env::temp_dir().join(format!("persist-scan-macos-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let place = |fixture: &str, at: &str| {
+            let to = dir.join(at);
+            std::fs::create_dir_all(to.parent().unwrap()).unwrap();
+            std::fs::copy(format!("{}/{}", FIXTURES, fixture), to).unwrap();
+        };
+        place("launchd/daemon_binary.plist", "Library/LaunchDaemons/com.example.helper.plist");
+        place("launchd/invalid.plist", "Library/LaunchAgents/broken.plist");
+        place("launchd/agent_xml.plist", "System/Library/LaunchAgents/com.apple.fake.plist");
+        place("launchd/agent_xml.plist", "Users/bob/Library/LaunchAgents/com.example.agent.plist");
+        place("loginwindow.plist", "Library/Preferences/com.apple.loginwindow.plist");
+        place("loginitems.plist", "Users/bob/Library/Preferences/com.apple.loginitems.plist");
+        place("backgrounditems.btm", &format!("Users/bob/{}", USER_BTM));
+        std::fs::create_dir_all(dir.join("Users/Shared/Library/LaunchAgents")).unwrap();
+        std::fs::create_dir_all(dir.join("private/etc/periodic/daily/skipped-dir")).unwrap();
+        std::fs::create_dir_all(dir.join("private/etc/periodic/weekly")).unwrap();
+        std::fs::write(dir.join("private/etc/periodic/daily/110.clean-tmps"), "#!/bin/sh\n").unwrap();
+        std::fs::write(dir.join("private/etc/periodic/weekly/999.local"), "#!/bin/sh\n").unwrap();
+
+        let arts = collect_macos_offline(&HostFs::new(&dir)).unwrap();
+        let found: Vec<(&str, &str, Option<&str>, Option<&str>)> = arts
+            .iter()
+            .map(|a| (a.kind.as_str(), a.name.as_str(), a.user.as_deref(), a.command.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("macos_launch_daemon", "com.example.helper.plist", None, Some("/usr/local/libexec/helper -v")),
+                (
+                    "macos_launch_agent",
+                    "com.apple.fake.plist",
+                    None,
+                    Some(r#"/Users/bob/Library/.hidden/agent --daemon "two words""#)
+                ),
+                (
+                    "macos_launch_agent",
+                    "com.example.agent.plist",
+                    Some("bob"),
+                    Some(r#"/Users/bob/Library/.hidden/agent --daemon "two words""#)
+                ),
+                ("macos_login_hook", "LoginHook", None, Some("/Library/Scripts/login.sh")),
+                ("macos_login_item", "Legacy.app", None, Some("/Applications/Legacy.app")),
+                ("macos_login_item", "Evil Updater", Some("bob"), Some("/Applications/Evil Updater.app")),
+                ("macos_login_item", "Notes Helper.app", Some("bob"), Some("/Applications/Notes Helper.app")),
+                // Its alias is not a bookmark, so only the name is known
+                ("macos_login_item", "Broken", Some("bob"), None),
+                // The .btm lists the bookmarked app once, though it also appears as a file:// URL
+                ("macos_login_item", "Evil Updater.app", Some("bob"), Some("/Applications/Evil Updater.app")),
+                (
+                    "macos_login_item",
+                    "Sync Agent.app",
+                    Some("bob"),
+                    Some("/Users/bob/Library/Application Support/Sync/Sync Agent.app")
+                ),
+                ("macos_periodic", "110.clean-tmps", None, Some("/private/etc/periodic/daily/110.clean-tmps")),
+                ("macos_periodic", "999.local", None, Some("/private/etc/periodic/weekly/999.local")),
+            ]
+        );
+
+        let helper = &arts[0];
+        assert_eq!(helper.source, "/Library/LaunchDaemons/com.example.helper.plist");
+        assert_eq!(helper.path.as_deref(), Some("/usr/local/libexec/helper"));
+        assert_eq!(helper.attrs["keep_alive"], "true");
+        assert_eq!(helper.attrs["user_name"], "root");
+        assert_eq!(helper.attrs["disabled"], "true");
+        assert_eq!(helper.attrs["program_arguments"], r#"["helper","-v"]"#);
+        assert!(!helper.attrs.contains_key("apple_system"));
+
+        let agent = &arts[2];
+        assert_eq!(agent.attrs["run_at_load"], "true");
+        assert_eq!(agent.attrs["keep_alive"], "NetworkState,SuccessfulExit");
+        assert_eq!(agent.attrs["triggers"], "StartInterval");
+        assert_eq!(agent.attrs["label"], "com.example.agent");
+        assert_eq!(arts[1].attrs["apple_system"], "true");
+
+        assert_eq!(arts[11].attrs["schedule"], "weekly");
+        assert_eq!(arts[11].source, "/private/etc/periodic/weekly");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,25 +1,250 @@
 use anyhow::Result;
+use std::path::PathBuf;
 
-use crate::engine::{Artifact, TargetOs};
+use crate::engine::content::FileSource;
+use crate::engine::{host_os, Artifact, Origin, TargetOs};
+use crate::util::skipped;
+use crate::vfs::{find_case_insensitive, ArchiveFs, HostFs, Vfs};
 
-#[cfg(unix)]
+use detect::detect_offline_os;
+
+mod detect;
 mod linux;
-#[cfg(windows)]
+mod macos;
 mod windows;
 
-pub fn collect(os: TargetOs) -> Result<Vec<Artifact>> {
+/// Where collectors read from. With neither roots nor an archive, the live host is inventoried.
+#[derive(Debug, Clone, Default)]
+pub struct CollectOptions {
+    /// Alternate filesystem roots (mounted images / extracted filesystems) for offline scans
+    pub roots: Vec<PathBuf>,
+    /// Triage bundle (.tar, .tar.gz, .zip) read in place for offline scans
+    pub archive: Option<PathBuf>,
+}
+
+/// One filesystem to inventory, with the OS whose collectors and rules apply to it.
+pub struct ScanTarget {
+    pub os: TargetOs,
+    /// None means the live host
+    pub fs: Option<Box<dyn Vfs>>,
+}
+
+impl ScanTarget {
+    /// The OS and offline root recorded on findings from this target.
+    pub fn origin(&self) -> Origin {
+        Origin {
+            os: self.os,
+            root: self.fs.as_ref().map(|fs| fs.describe()),
+        }
+    }
+
+    /// Where `path` lives in the image. Drive-letter paths ("C:\x\y.exe") are looked up
+    /// from the image root, ignoring case.
+    fn image_path(fs: &dyn Vfs, path: &str) -> Option<String> {
+        let bytes = path.as_bytes();
+        if bytes.len() > 2 && bytes[1] == b':' && bytes[0].is_ascii_alphabetic() {
+            find_case_insensitive(fs, &path[2..]).ok().flatten()
+        } else {
+            Some(path.to_string())
+        }
+    }
+}
+
+/// Files an artifact refers to (e.g. its executable) are read from the scanned system:
+/// the image for offline targets, the host otherwise.
+impl FileSource for ScanTarget {
+    fn file_size(&self, path: &str) -> Option<u64> {
+        let Some(fs) = &self.fs else { return std::fs::metadata(path).ok().filter(|m| m.is_file()).map(|m| m.len()) };
+        fs.size(&Self::image_path(fs.as_ref(), path)?).ok().flatten()
+    }
+
+    fn read_file(&self, path: &str) -> Option<Vec<u8>> {
+        let Some(fs) = &self.fs else { return std::fs::read(path).ok() };
+        fs.read(&Self::image_path(fs.as_ref(), path)?).ok().flatten()
+    }
+}
+
+impl CollectOptions {
+    /// Filesystems to scan. `os` is the requested OS, or None for auto-detection: the live
+    /// host's own OS, or for offline scans the OS found in each root. A root that holds no
+    /// recognisable OS itself is treated as an evidence folder and its subdirectories
+    /// (mounted partitions) are probed instead.
+    pub fn targets(&self, os: Option<TargetOs>) -> Result<Vec<ScanTarget>> {
+        if !self.roots.is_empty() && self.archive.is_some() {
+            anyhow::bail!("--root and --from-archive are mutually exclusive");
+        }
+
+        if let Some(archive) = &self.archive {
+            let fs: Box<dyn Vfs> = Box::new(ArchiveFs::open(archive)?);
+            let os = match os {
+                Some(os) => os,
+                None => detect_offline_os(fs.as_ref())?.ok_or_else(|| {
+                    anyhow::anyhow!("Could not detect the OS in {} (use --os)", archive.display())
+                })?,
+            };
+            return Ok(vec![ScanTarget { os, fs: Some(fs) }]);
+        }
+
+        if self.roots.is_empty() {
+            let os = match os {
+                Some(os) => os,
+                None => host_os()?,
+            };
+            return Ok(vec![ScanTarget { os, fs: None }]);
+        }
+
+        let mut out = vec![];
+        for root in &self.roots {
+            if !root.is_dir() {
+                anyhow::bail!("--root is not a directory: {}", root.display());
+            }
+            let fs = HostFs::new(root);
+            if let Some(os) = os {
+                out.push(ScanTarget { os, fs: Some(Box::new(fs)) });
+                continue;
+            }
+            if let Some(os) = detect_offline_os(&fs)? {
+                out.push(ScanTarget { os, fs: Some(Box::new(fs)) });
+                continue;
+            }
+
+            let before = out.len();
+            for entry in fs.read_dir("/")? {
+                if !entry.is_dir {
+                    continue;
+                }
+                let sub = HostFs::new(root.join(&entry.name));
+                if let Some(os) = detect_offline_os(&sub)? {
+                    out.push(ScanTarget { os, fs: Some(Box::new(sub)) });
+                }
+            }
+            if out.len() == before {
+                anyhow::bail!("Could not detect an OS in {} or its subdirectories (use --os)", root.display());
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Artifact kinds each OS's collectors produce, i.e. what a rule's `check.kind` can refer to.
+pub fn artifact_kinds(os: TargetOs) -> &'static [&'static str] {
+    match os {
+        TargetOs::Linux => &["linux_autostart", "linux_user_crontab"],
+        TargetOs::Windows => &[
+            "windows_run_key",
+            "windows_startup_folder",
+            "windows_service",
+            "windows_scheduled_task",
+            "windows_winlogon",
+            "windows_ifeo",
+            "windows_appinit",
+            "windows_appcert",
+            "windows_lsa_package",
+            "windows_print_monitor",
+            "windows_com_hijack",
+            "windows_wmi_subscription",
+        ],
+        TargetOs::MacOs => &[
+            "macos_launch_agent",
+            "macos_launch_daemon",
+            "macos_login_item",
+            "macos_login_hook",
+            "macos_periodic",
+        ],
+    }
+}
+
+/// What one target's collectors found, and the sources they had to skip (unparsable hives,
+/// corrupt keys, unreadable folders). A non-empty `skipped` means the collection is partial.
+pub struct Collected {
+    pub artifacts: Vec<Artifact>,
+    pub skipped: Vec<String>,
+}
+
+/// Collect from an offline filesystem if given, else from the live host.
+pub fn collect(os: TargetOs, offline: Option<&dyn Vfs>) -> Result<Collected> {
+    let artifacts = collect_artifacts(os, offline)?;
+    Ok(Collected { artifacts, skipped: skipped::take() })
+}
+
+fn collect_artifacts(os: TargetOs, offline: Option<&dyn Vfs>) -> Result<Vec<Artifact>> {
     match os {
         TargetOs::Linux => {
+            if let Some(fs) = offline {
+                return linux::collect_linux_offline(fs);
+            }
             #[cfg(unix)]
             { linux::collect_linux() }
             #[cfg(not(unix))]
-            { anyhow::bail!("Linux collectors not available on this host") }
+            { anyhow::bail!("Linux collectors not available on this host (use --root for offline scans)") }
         }
         TargetOs::Windows => {
+            if let Some(fs) = offline {
+                return windows::collect_windows_offline(fs);
+            }
             #[cfg(windows)]
             { windows::collect_windows() }
             #[cfg(not(windows))]
-            { anyhow::bail!("Windows collectors not available on this host") }
+            { anyhow::bail!("Windows collectors not available on this host (use --root for offline scans)") }
+        }
+        TargetOs::MacOs => {
+            if let Some(fs) = offline {
+                return macos::collect_macos_offline(fs);
+            }
+            #[cfg(target_os = "macos")]
+            { macos::collect_macos_offline(&HostFs::live()) }
+            #[cfg(not(target_os = "macos"))]
+            { anyhow::bail!("macOS collectors not available on this host (use --root for offline scans)") }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(path: PathBuf) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, b"x").unwrap();
+    }
+
+    fn found(targets: &[ScanTarget]) -> Vec<(TargetOs, String)> {
+        targets.iter().map(|t| (t.os, t.fs.as_ref().unwrap().describe())).collect()
+    }
+
+    #[test]
+    fn evidence_folder_is_split_per_partition() {
+        let dir = std::env::temp_dir().join(format!("persist-scan-targets-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        touch(dir.join("case/p1_linux/etc/passwd"));
+        touch(dir.join("case/p2_windows/Windows/System32/config/SOFTWARE"));
+        touch(dir.join("case/p3_macos/System/Library/CoreServices/SystemVersion.plist"));
+        touch(dir.join("case/p4_data/notes.txt"));
+        touch(dir.join("case/readme.txt"));
+        touch(dir.join("empty/notes.txt"));
+        let case = dir.join("case");
+        let sub = |name: &str| case.join(name).display().to_string();
+
+        let opts = CollectOptions { roots: vec![case.clone(), dir.join("case/p2_windows")], archive: None };
+        assert_eq!(
+            found(&opts.targets(None).unwrap()),
+            [
+                (TargetOs::Linux, sub("p1_linux")),
+                (TargetOs::Windows, sub("p2_windows")),
+                (TargetOs::MacOs, sub("p3_macos")),
+                (TargetOs::Windows, sub("p2_windows")),
+            ]
+        );
+
+        // A requested OS applies to the root as given, without probing
+        assert_eq!(found(&opts.targets(Some(TargetOs::Linux)).unwrap())[0], (TargetOs::Linux, case.display().to_string()));
+
+        let empty = CollectOptions { roots: vec![dir.join("empty")], archive: None };
+        let err = empty.targets(None).err().unwrap().to_string();
+        assert!(err.starts_with("Could not detect an OS in"), "{}", err);
+        let missing = CollectOptions { roots: vec![dir.join("missing")], archive: None };
+        assert!(missing.targets(None).err().unwrap().to_string().starts_with("--root is not a directory"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,250 @@
+//! COM hijacking: per-user CLSIDs that shadow machine-wide registrations, and COM servers
+//! that load from user-writable locations.
+
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+
+use super::hives::OfflineHives;
+use super::or_skip;
+use super::registry::RegistryKey;
+use crate::engine::Artifact;
+use crate::util::paths::normalize_windows_image_path;
+
+const SERVERS: &[&str] = &["InprocServer32", "LocalServer32"];
+
+/// Path fragments (lowercase) of locations a standard user can write to.
+const USER_WRITABLE: &[&str] = &[
+    "\\users\\",
+    "\\programdata\\",
+    "\\windows\\temp\\",
+    "%appdata%",
+    "%localappdata%",
+    "%temp%",
+    "%tmp%",
+    "%userprofile%",
+    "%public%",
+];
+
+/// Exceptions to USER_WRITABLE: users may create folders under ProgramData, but not under
+/// ProgramData\Microsoft (where Defender and other stock components register their servers).
+const NOT_USER_WRITABLE: &[&str] = &["\\programdata\\microsoft\\"];
+
+/// CLSID trees relative to the SOFTWARE hive root, as (key, 32-bit view).
+const MACHINE_TREES: &[(&str, bool)] = &[("Classes\\CLSID", false), ("Classes\\Wow6432Node\\CLSID", true)];
+
+/// Per-user CLSID trees relative to NTUSER.DAT and UsrClass.dat (which backs HKCU\Software\Classes).
+const NTUSER_TREES: &[(&str, bool)] =
+    &[("Software\\Classes\\CLSID", false), ("Software\\Classes\\Wow6432Node\\CLSID", true)];
+const USRCLASS_TREES: &[(&str, bool)] = &[("CLSID", false), ("Wow6432Node\\CLSID", true)];
+
+/// A CLSID tree in the 64-bit view or the 32-bit (Wow6432Node) one.
+pub struct ClsidTree<K> {
+    /// None for the machine-wide registrations (HKLM\Software\Classes)
+    pub user: Option<String>,
+    pub source: String,
+    pub wow64: bool,
+    pub key: K,
+}
+
+/// COM hijacks from the offline SOFTWARE hive and each user's NTUSER.DAT and UsrClass.dat.
+pub fn collect_offline(hives: &OfflineHives) -> Result<Vec<Artifact>> {
+    let mut machine = vec![];
+    if let Some(software) = &hives.software {
+        for (path, wow64) in MACHINE_TREES {
+            if let Some(key) = software.open(path)? {
+                machine.push(ClsidTree { user: None, source: format!("HKLM\\Software\\{}", path), wow64: *wow64, key });
+            }
+        }
+    }
+
+    let mut users = vec![];
+    for u in &hives.users {
+        for (path, wow64) in NTUSER_TREES {
+            if let Some(key) = u.ntuser.open(path)? {
+                users.push(user_tree(&u.user, path, *wow64, key));
+            }
+        }
+        if let Some(usrclass) = &u.usrclass {
+            for (path, wow64) in USRCLASS_TREES {
+                if let Some(key) = usrclass.open(path)? {
+                    users.push(user_tree(&u.user, &format!("Software\\Classes\\{}", path), *wow64, key));
+                }
+            }
+        }
+    }
+
+    Ok(collect(&machine, &users))
+}
+
+/// `path` is relative to HKCU.
+pub fn user_tree<K>(user: &str, path: &str, wow64: bool, key: K) -> ClsidTree<K> {
+    ClsidTree { user: Some(user.to_string()), source: format!("HKU\\{}\\{}", user, path), wow64, key }
+}
+
+/// A user CLSID shadows the machine one when the machine registers the same CLSID in the same
+/// view: the merged HKCR view then takes the user's server, whatever kind the machine's is.
+/// A CLSID that fails to read is skipped on its own.
+pub fn collect<K: RegistryKey>(machine: &[ClsidTree<K>], users: &[ClsidTree<K>]) -> Vec<Artifact> {
+    let mut out = vec![];
+    let mut machine_servers: HashMap<(bool, String), Vec<(&str, String)>> = HashMap::new();
+
+    for tree in machine {
+        for clsid in or_skip(&tree.source, tree.key.subkeys()) {
+            let servers = or_skip(format_args!("{}\\{}", tree.source, clsid.name()), servers(&clsid));
+            for (server, value) in &servers {
+                if is_user_writable(value) {
+                    out.push(com_artifact(&tree.source, clsid.name(), server, None, value, vec!["user_writable_path"], None));
+                }
+            }
+            if !servers.is_empty() {
+                machine_servers.insert((tree.wow64, clsid.name().to_ascii_lowercase()), servers);
+            }
+        }
+    }
+
+    for tree in users {
+        for clsid in or_skip(&tree.source, tree.key.subkeys()) {
+            let registered = machine_servers.get(&(tree.wow64, clsid.name().to_ascii_lowercase()));
+            for (server, value) in or_skip(format_args!("{}\\{}", tree.source, clsid.name()), servers(&clsid)) {
+                let mut reasons = vec![];
+                // Compared against the machine server of the same kind when there is one
+                let shadowed = registered.and_then(|m| match m.iter().find(|(s, _)| *s == server) {
+                    Some((_, same_kind)) => Some(same_kind).filter(|v| !v.eq_ignore_ascii_case(&value)),
+                    None => m.first().map(|(_, v)| v),
+                });
+                if shadowed.is_some() {
+                    reasons.push("shadows_machine_clsid");
+                }
+                if is_user_writable(&value) {
+                    reasons.push("user_writable_path");
+                }
+                if reasons.is_empty() {
+                    continue;
+                }
+                out.push(com_artifact(&tree.source, clsid.name(), server, tree.user.as_deref(), &value, reasons, shadowed));
+            }
+        }
+    }
+
+    out
+}
+
+/// (server kind, default value) for each COM server registered under a CLSID.
+fn servers<K: RegistryKey>(clsid: &K) -> Result<Vec<(&'static str, String)>> {
+    let mut out = vec![];
+    for server in SERVERS {
+        if let Some(key) = clsid.open(server)? {
+            if let Some(value) = key.string_value("")? {
+                out.push((*server, value));
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn com_artifact(
+    source: &str,
+    clsid: &str,
+    server: &str,
+    user: Option<&str>,
+    value: &str,
+    reasons: Vec<&str>,
+    machine_value: Option<&String>,
+) -> Artifact {
+    let mut attrs = BTreeMap::new();
+    attrs.insert("clsid".to_string(), clsid.to_string());
+    attrs.insert("server".to_string(), server.to_string());
+    attrs.insert("reason".to_string(), reasons.join(","));
+    if let Some(m) = machine_value {
+        attrs.insert("machine_server".to_string(), m.clone());
+    }
+
+    Artifact {
+        kind: "windows_com_hijack".to_string(),
+        source: source.to_string(),
+        name: format!("{}\\{}", clsid, server),
+        user: user.map(str::to_string),
+        command: Some(value.to_string()),
+        path: normalize_windows_image_path(value),
+        raw: None,
+        attrs,
+    }
+}
+
+fn is_user_writable(value: &str) -> bool {
+    let v = value.to_ascii_lowercase();
+    USER_WRITABLE.iter().any(|frag| v.contains(frag)) && !NOT_USER_WRITABLE.iter().any(|frag| v.contains(frag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::windows::hives::UserHive;
+    use crate::parsers::regf::Hive;
+
+    fn hive(bytes: &[u8]) -> Hive {
+        Hive::parse(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn shadowed_and_user_writable_servers() {
+        let hives = OfflineHives {
+            software: Some(hive(include_bytes!("../../../tests/fixtures/regf/software_com.dat"))),
+            system: None,
+            users: vec![UserHive {
+                user: "bob".to_string(),
+                ntuser: hive(include_bytes!("../../../tests/fixtures/regf/ntuser_run.dat")),
+                usrclass: Some(hive(include_bytes!("../../../tests/fixtures/regf/usrclass_com.dat"))),
+            }],
+        };
+        let arts = collect_offline(&hives).unwrap();
+        let found: Vec<(&str, &str, &str, Option<&str>)> = arts
+            .iter()
+            .map(|a| {
+                (a.source.as_str(), a.name.as_str(), a.attrs["reason"].as_str(), a.attrs.get("machine_server").map(String::as_str))
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    r"HKLM\Software\Classes\CLSID",
+                    r"{00000003-0000-0000-0000-000000000003}\LocalServer32",
+                    "user_writable_path",
+                    None
+                ),
+                (
+                    r"HKU\bob\Software\Classes\CLSID",
+                    r"{00000001-0000-0000-0000-000000000001}\InprocServer32",
+                    "shadows_machine_clsid,user_writable_path",
+                    Some(r"%SystemRoot%\System32\shell32.dll")
+                ),
+                // The machine only has a LocalServer32, which the user's InprocServer32 still shadows
+                (
+                    r"HKU\bob\Software\Classes\CLSID",
+                    r"{00000004-0000-0000-0000-000000000004}\InprocServer32",
+                    "shadows_machine_clsid",
+                    Some(r#""C:\Program Files\App\app.exe" -Embedding"#)
+                ),
+                // Compared within the 32-bit view only
+                (
+                    r"HKU\bob\Software\Classes\Wow6432Node\CLSID",
+                    r"{00000006-0000-0000-0000-000000000006}\InprocServer32",
+                    "shadows_machine_clsid",
+                    Some(r"C:\Windows\SysWOW64\wow.dll")
+                ),
+            ]
+        );
+        assert!(arts.iter().all(|a| a.kind == "windows_com_hijack"));
+        assert_eq!(arts[1].user.as_deref(), Some("bob"));
+        assert_eq!(arts[1].path.as_deref(), Some(r"C:\Users\bob\AppData\Roaming\evil.dll"));
+    }
+
+    #[test]
+    fn programdata_microsoft_is_not_user_writable() {
+        assert!(!is_user_writable(r"C:\ProgramData\Microsoft\Windows Defender\Platform\4.18.2\MpOav.dll"));
+        assert!(is_user_writable(r"C:\ProgramData\Vendor\agent.exe"));
+        assert!(is_user_writable(r"%APPDATA%\x.dll"));
+        assert!(!is_user_writable(r"C:\Windows\System32\x.dll"));
+    }
+}
@@ -0,0 +1,97 @@
+use anyhow::Result;
+
+use super::registry::RegistryKey;
+use super::user_profiles;
+use crate::parsers::regf::{Hive, Key};
+use crate::util::skipped;
+use crate::vfs::{find_case_insensitive, join, Vfs};
+
+const CONFIG_DIR: &str = "/Windows/System32/config";
+const USRCLASS: &str = "AppData/Local/Microsoft/Windows/UsrClass.dat";
+
+/// Registry hives found in an offline image. Missing hives are simply absent.
+pub struct OfflineHives {
+    pub software: Option<Hive>,
+    pub system: Option<Hive>,
+    pub users: Vec<UserHive>,
+}
+
+pub struct UserHive {
+    pub user: String,
+    pub ntuser: Hive,
+    /// Per-user class registrations (HKCU\Software\Classes)
+    pub usrclass: Option<Hive>,
+}
+
+impl OfflineHives {
+    pub fn load(fs: &dyn Vfs) -> Result<Self> {
+        let software = load_hive(fs, &join(CONFIG_DIR, "SOFTWARE"))?;
+        let system = load_hive(fs, &join(CONFIG_DIR, "SYSTEM"))?;
+
+        let mut users = vec![];
+        for (user, profile) in user_profiles(fs)? {
+            if let Some(ntuser) = load_hive(fs, &join(&profile, "NTUSER.DAT"))? {
+                let usrclass = load_hive(fs, &join(&profile, USRCLASS))?;
+                users.push(UserHive { user, ntuser, usrclass });
+            }
+        }
+
+        Ok(Self { software, system, users })
+    }
+
+    /// SYSTEM\CurrentControlSet as it was when the image was taken (Select\Current -> ControlSet00N).
+    pub fn current_control_set(&self) -> Result<Option<Key<'_>>> {
+        let Some(system) = &self.system else { return Ok(None) };
+        let root = system.root()?;
+
+        let current = match root.open("Select")? {
+            Some(select) => select.value("Current")?.and_then(|v| v.as_u32()),
+            None => None,
+        };
+        root.open(&format!("ControlSet{:03}", current.unwrap_or(1)))
+    }
+}
+
+/// A hive that does not parse (dirty or truncated copies are common in triage images) is
+/// skipped and noted for the report rather than failing the whole scan.
+fn load_hive(fs: &dyn Vfs, path: &str) -> Result<Option<Hive>> {
+    let Some(found) = find_case_insensitive(fs, path)? else { return Ok(None) };
+    let Some(bytes) = fs.read(&found)? else { return Ok(None) };
+    match Hive::parse(bytes) {
+        Ok(hive) => Ok(Some(hive)),
+        Err(e) => {
+            skipped::record(format!("registry hive {}: {:#}", found, e));
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::HostFs;
+
+    #[test]
+    fn unparsable_hives_are_skipped() {
+        let dir = std::env::temp_dir().join(format!("persist-scan-hives-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("Windows/System32/config")).unwrap();
+        std::fs::create_dir_all(dir.join("Users/alice")).unwrap();
+        std::fs::create_dir_all(dir.join("Users/bob")).unwrap();
+        let good = include_bytes!("../../../tests/fixtures/regf/ntuser_run.dat");
+        let truncated = include_bytes!("../../../tests/fixtures/regf/ntuser_truncated.dat");
+        std::fs::write(dir.join("Windows/System32/config/SOFTWARE"), truncated).unwrap();
+        std::fs::write(dir.join("Users/alice/NTUSER.DAT"), good).unwrap();
+        std::fs::write(dir.join("Users/bob/NTUSER.DAT"), truncated).unwrap();
+
+        let hives = OfflineHives::load(&HostFs::new(&dir)).unwrap();
+        assert!(hives.software.is_none());
+        let users: Vec<&str> = hives.users.iter().map(|u| u.user.as_str()).collect();
+        assert_eq!(users, ["alice"]);
+        let skipped = skipped::take();
+        assert_eq!(skipped.len(), 2);
+        assert!(skipped[0].starts_with("registry hive ") && skipped[0].contains("SOFTWARE"), "{}", skipped[0]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
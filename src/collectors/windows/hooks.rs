@@ -0,0 +1,333 @@
+//! Registry extension points that load code into logon, LSA, spooler or every process:
+//! Winlogon, IFEO / SilentProcessExit, AppInit_DLLs, AppCertDlls, LSA packages, print monitors.
+//!
+//! Only values that differ from a stock Windows install are reported, so each kind
+//! is a finding on its own (see the matching rules in rules/windows/).
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+use super::or_skip;
+use super::registry::RegistryKey;
+use crate::engine::Artifact;
+use crate::util::paths::normalize_windows_image_path;
+
+const WINLOGON: &str = "Microsoft\\Windows NT\\CurrentVersion\\Winlogon";
+const IFEO: &str = "Microsoft\\Windows NT\\CurrentVersion\\Image File Execution Options";
+const SILENT_PROCESS_EXIT: &str = "Microsoft\\Windows NT\\CurrentVersion\\SilentProcessExit";
+const WINDOWS: &str = "Microsoft\\Windows NT\\CurrentVersion\\Windows";
+const APPCERT: &str = "Control\\Session Manager\\AppCertDlls";
+const LSA: &str = "Control\\Lsa";
+const PRINT_MONITORS: &str = "Control\\Print\\Monitors";
+
+const DEFAULT_USERINIT: &[&str] = &["c:\\windows\\system32\\userinit.exe", "userinit.exe", "userinit"];
+const DEFAULT_SHELL: &[&str] = &["explorer.exe", "explorer", "c:\\windows\\explorer.exe"];
+/// Winlogon\Notify packages shipped with Windows XP/2003.
+const DEFAULT_NOTIFY: &[&str] = &[
+    "crypt32chain", "cryptnet", "cscdll", "dimsntfy", "sclgntfy", "senslogn", "termsrv", "wlballoon", "wzcnotif",
+];
+const DEFAULT_AUTH_PACKAGES: &[&str] = &["msv1_0"];
+const DEFAULT_NOTIFICATION_PACKAGES: &[&str] = &["scecli", "rassfm"];
+const DEFAULT_SECURITY_PACKAGES: &[&str] =
+    &["kerberos", "msv1_0", "schannel", "wdigest", "tspkg", "pku2u", "cloudap", "negoexts", "livessp", "\"\""];
+const DEFAULT_PRINT_MONITORS: &[&str] =
+    &["localspl.dll", "tcpmon.dll", "usbmon.dll", "wsdmon.dll", "appmon.dll", "fxsmon.dll", "ppmon.dll"];
+
+/// `software` is HKLM\Software, `control_set` is HKLM\SYSTEM\CurrentControlSet and
+/// `users` are (account, HKCU root) pairs. Each extension point is read on its own, so one
+/// that fails to read is skipped without losing the others.
+pub fn collect<K: RegistryKey>(software: Option<&K>, control_set: Option<&K>, users: &[(String, K)]) -> Vec<Artifact> {
+    let mut out = vec![];
+
+    if let Some(sw) = software {
+        out.extend(or_skip(format_args!("HKLM\\Software\\{}", WINLOGON), winlogon(sw, "HKLM\\Software", None)));
+        for prefix in ["", "Wow6432Node\\"] {
+            out.extend(or_skip(format_args!("HKLM\\Software\\{}{}", prefix, IFEO), ifeo(sw, prefix)));
+            out.extend(or_skip(format_args!("HKLM\\Software\\{}{}", prefix, WINDOWS), appinit(sw, prefix)));
+        }
+    }
+    for (user, root) in users {
+        let scope = format!("HKU\\{}\\Software", user);
+        out.extend(or_skip(format_args!("{}\\{}", scope, WINLOGON), winlogon(root, &scope, Some(user))));
+    }
+
+    if let Some(ccs) = control_set {
+        let scope = "HKLM\\SYSTEM\\CurrentControlSet";
+        out.extend(or_skip(format_args!("{}\\{}", scope, APPCERT), appcert(ccs)));
+        out.extend(or_skip(format_args!("{}\\{}", scope, LSA), lsa_packages(ccs)));
+        out.extend(or_skip(format_args!("{}\\{}", scope, PRINT_MONITORS), print_monitors(ccs)));
+    }
+
+    out
+}
+
+fn winlogon<K: RegistryKey>(software: &K, scope: &str, user: Option<&str>) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    let path = if user.is_some() { format!("Software\\{}", WINLOGON) } else { WINLOGON.to_string() };
+    let Some(key) = software.open(&path)? else { return Ok(out) };
+    let source = format!("{}\\{}", scope, WINLOGON);
+
+    if let Some(userinit) = key.string_value("Userinit")? {
+        let extra = non_default_entries(&userinit, DEFAULT_USERINIT);
+        if !extra.is_empty() {
+            out.push(hook("windows_winlogon", &source, "Userinit", user, &userinit, path_of(extra[0]), BTreeMap::new()));
+        }
+    }
+
+    // HKLM Shell defaults to explorer.exe; a per-user Shell value does not exist by default.
+    if let Some(shell) = key.string_value("Shell")? {
+        let extra = non_default_entries(&shell, DEFAULT_SHELL);
+        if user.is_some() || !extra.is_empty() {
+            let path = path_of(extra.first().copied().unwrap_or(&shell));
+            out.push(hook("windows_winlogon", &source, "Shell", user, &shell, path, BTreeMap::new()));
+        }
+    }
+
+    if let Some(notify) = key.open("Notify")? {
+        for pkg in notify.subkeys()? {
+            if is_one_of(pkg.name(), DEFAULT_NOTIFY) {
+                continue;
+            }
+            if let Some(dll) = pkg.string_value("DllName")? {
+                let name = format!("Notify\\{}", pkg.name());
+                out.push(hook("windows_winlogon", &source, &name, user, &dll, system32_dll(&dll), BTreeMap::new()));
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+fn ifeo<K: RegistryKey>(software: &K, prefix: &str) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+
+    let ifeo_path = format!("{}{}", prefix, IFEO);
+    if let Some(ifeo) = software.open(&ifeo_path)? {
+        let source = format!("HKLM\\Software\\{}", ifeo_path);
+        for image in ifeo.subkeys()? {
+            let Some(debugger) = image.string_value("Debugger")? else { continue };
+            let mut attrs = BTreeMap::new();
+            if let Some(flags) = image.value("GlobalFlag")?.and_then(|v| v.as_u32()) {
+                attrs.insert("global_flag".to_string(), format!("{:#x}", flags));
+            }
+            let name = format!("{}\\Debugger", image.name());
+            out.push(hook("windows_ifeo", &source, &name, None, &debugger, path_of(&debugger), attrs));
+        }
+    }
+
+    let spe_path = format!("{}{}", prefix, SILENT_PROCESS_EXIT);
+    if let Some(spe) = software.open(&spe_path)? {
+        let source = format!("HKLM\\Software\\{}", spe_path);
+        for image in spe.subkeys()? {
+            let Some(monitor) = image.string_value("MonitorProcess")? else { continue };
+            let mut attrs = BTreeMap::new();
+            if let Some(mode) = image.value("ReportingMode")?.and_then(|v| v.as_u32()) {
+                attrs.insert("reporting_mode".to_string(), mode.to_string());
+            }
+            let name = format!("{}\\MonitorProcess", image.name());
+            out.push(hook("windows_ifeo", &source, &name, None, &monitor, path_of(&monitor), attrs));
+        }
+    }
+
+    Ok(out)
+}
+
+fn appinit<K: RegistryKey>(software: &K, prefix: &str) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    let path = format!("{}{}", prefix, WINDOWS);
+    let Some(key) = software.open(&path)? else { return Ok(out) };
+    let Some(dlls) = key.string_value("AppInit_DLLs")? else { return Ok(out) };
+    if dlls.trim().is_empty() {
+        return Ok(out);
+    }
+
+    let mut attrs = BTreeMap::new();
+    for (attr, value) in [("load_appinit_dlls", "LoadAppInit_DLLs"), ("require_signed", "RequireSignedAppInit_DLLs")] {
+        if let Some(v) = key.value(value)?.and_then(|v| v.as_u32()) {
+            attrs.insert(attr.to_string(), v.to_string());
+        }
+    }
+
+    let first = dlls.split([',', ' ']).find(|d| !d.is_empty()).unwrap_or("");
+    let source = format!("HKLM\\Software\\{}", path);
+    out.push(hook("windows_appinit", &source, "AppInit_DLLs", None, &dlls, path_of(first), attrs));
+    Ok(out)
+}
+
+/// AppCertDlls has no values on a stock install; every entry is loaded into CreateProcess callers.
+fn appcert<K: RegistryKey>(control_set: &K) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    let Some(key) = control_set.open(APPCERT)? else { return Ok(out) };
+    let source = format!("HKLM\\SYSTEM\\CurrentControlSet\\{}", APPCERT);
+
+    for v in key.values()? {
+        let dll = v.data.to_display_string();
+        if dll.is_empty() {
+            continue;
+        }
+        out.push(hook("windows_appcert", &source, &v.name, None, &dll, path_of(&dll), BTreeMap::new()));
+    }
+    Ok(out)
+}
+
+fn lsa_packages<K: RegistryKey>(control_set: &K) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+
+    for (key_path, value, defaults) in [
+        (LSA.to_string(), "Authentication Packages", DEFAULT_AUTH_PACKAGES),
+        (LSA.to_string(), "Notification Packages", DEFAULT_NOTIFICATION_PACKAGES),
+        (LSA.to_string(), "Security Packages", DEFAULT_SECURITY_PACKAGES),
+        (format!("{}\\OSConfig", LSA), "Security Packages", DEFAULT_SECURITY_PACKAGES),
+    ] {
+        let Some(key) = control_set.open(&key_path)? else { continue };
+        let Some(data) = key.value(value)? else { continue };
+        let source = format!("HKLM\\SYSTEM\\CurrentControlSet\\{}", key_path);
+
+        for pkg in data.as_strings() {
+            let pkg = pkg.trim();
+            if pkg.is_empty() || is_one_of(pkg, defaults) {
+                continue;
+            }
+            let mut attrs = BTreeMap::new();
+            attrs.insert("package".to_string(), pkg.to_string());
+            out.push(hook("windows_lsa_package", &source, value, None, pkg, system32_dll(pkg), attrs));
+        }
+    }
+
+    Ok(out)
+}
+
+fn print_monitors<K: RegistryKey>(control_set: &K) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    let Some(monitors) = control_set.open(PRINT_MONITORS)? else { return Ok(out) };
+    let source = format!("HKLM\\SYSTEM\\CurrentControlSet\\{}", PRINT_MONITORS);
+
+    for m in monitors.subkeys()? {
+        let Some(driver) = m.string_value("Driver")? else { continue };
+        let file = driver.rsplit('\\').next().unwrap_or(&driver);
+        if is_one_of(file, DEFAULT_PRINT_MONITORS) {
+            continue;
+        }
+        out.push(hook("windows_print_monitor", &source, m.name(), None, &driver, system32_dll(&driver), BTreeMap::new()));
+    }
+    Ok(out)
+}
+
+fn hook(
+    kind: &str,
+    source: &str,
+    name: &str,
+    user: Option<&str>,
+    command: &str,
+    path: Option<String>,
+    attrs: BTreeMap<String, String>,
+) -> Artifact {
+    Artifact {
+        kind: kind.to_string(),
+        source: source.to_string(),
+        name: name.to_string(),
+        user: user.map(str::to_string),
+        command: Some(command.to_string()),
+        path,
+        raw: None,
+        attrs,
+    }
+}
+
+/// Comma-separated entries (Userinit, Shell) that are not in the default list.
+fn non_default_entries<'a>(value: &'a str, defaults: &[&str]) -> Vec<&'a str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty() && !is_one_of(e, defaults))
+        .collect()
+}
+
+fn is_one_of(v: &str, list: &[&str]) -> bool {
+    list.iter().any(|d| d.eq_ignore_ascii_case(v))
+}
+
+fn path_of(command: &str) -> Option<String> {
+    normalize_windows_image_path(command)
+}
+
+/// Bare DLL or package names are loaded from System32 ("evil" -> C:\Windows\System32\evil.dll).
+fn system32_dll(name: &str) -> Option<String> {
+    let n = name.trim();
+    if n.is_empty() {
+        return None;
+    }
+    if n.contains('\\') || n.contains('%') {
+        return path_of(n);
+    }
+    let file = if n.to_ascii_lowercase().ends_with(".dll") { n.to_string() } else { format!("{}.dll", n) };
+    Some(format!("C:\\Windows\\System32\\{}", file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::regf::Hive;
+
+    fn hive(bytes: &[u8]) -> Hive {
+        Hive::parse(bytes.to_vec()).unwrap()
+    }
+
+    fn hooks(software: &[u8], system: &[u8], users: &[(&str, &[u8])]) -> Vec<Artifact> {
+        let software = hive(software);
+        let system = hive(system);
+        let users: Vec<(String, Hive)> = users.iter().map(|(u, b)| (u.to_string(), hive(b))).collect();
+        let user_roots: Vec<_> = users.iter().map(|(u, h)| (u.clone(), h.root().unwrap())).collect();
+        let control_set = system.root().unwrap().open("ControlSet001").unwrap().unwrap();
+        collect(Some(&software.root().unwrap()), Some(&control_set), &user_roots)
+    }
+
+    #[test]
+    fn stock_values_are_not_reported() {
+        let arts = hooks(
+            include_bytes!("../../../tests/fixtures/regf/software_hooks_stock.dat"),
+            include_bytes!("../../../tests/fixtures/regf/system_hooks_stock.dat"),
+            &[],
+        );
+        assert_eq!(arts, []);
+    }
+
+    #[test]
+    fn added_entries_are_reported_per_kind() {
+        let arts = hooks(
+            include_bytes!("../../../tests/fixtures/regf/software_hooks.dat"),
+            include_bytes!("../../../tests/fixtures/regf/system_hooks.dat"),
+            &[("bob", include_bytes!("../../../tests/fixtures/regf/ntuser_winlogon.dat"))],
+        );
+        let found: Vec<(&str, &str, Option<&str>, Option<&str>)> =
+            arts.iter().map(|a| (a.kind.as_str(), a.name.as_str(), a.user.as_deref(), a.path.as_deref())).collect();
+        assert_eq!(
+            found,
+            [
+                // Only the entry appended to the stock userinit.exe
+                ("windows_winlogon", "Userinit", None, Some(r"C:\Users\Public\u.exe")),
+                ("windows_winlogon", "Shell", None, Some(r"C:\ProgramData\sh.exe")),
+                ("windows_winlogon", r"Notify\evilnt", None, Some(r"C:\Windows\System32\evilnt.dll")),
+                // notepad.exe only has a GlobalFlag: no Debugger, nothing to report
+                ("windows_ifeo", r"sethc.exe\Debugger", None, Some(r"C:\Windows\System32\cmd.exe")),
+                ("windows_ifeo", r"notepad.exe\MonitorProcess", None, Some(r"C:\Users\Public\m.exe")),
+                ("windows_appinit", "AppInit_DLLs", None, Some(r"C:\Tools\a.dll")),
+                ("windows_ifeo", r"utilman.exe\Debugger", None, Some(r"C:\Tools\dbg.exe")),
+                // Any per-user Shell, even explorer.exe
+                ("windows_winlogon", "Shell", Some("bob"), Some("explorer.exe")),
+                ("windows_appcert", "cert", None, Some(r"C:\Tools\cert.dll")),
+                ("windows_lsa_package", "Authentication Packages", None, Some(r"C:\Tools\auth.dll")),
+                ("windows_lsa_package", "Notification Packages", None, Some(r"C:\Windows\System32\pwfilter.dll")),
+                ("windows_lsa_package", "Security Packages", None, Some(r"C:\Windows\System32\mimilib.dll")),
+                // Stock monitors match by file name, whatever the case or directory
+                ("windows_print_monitor", "Evil Port", None, Some(r"C:\Windows\System32\evilmon.dll")),
+            ]
+        );
+        assert_eq!(arts[3].attrs["global_flag"], "0x200");
+        assert_eq!(arts[4].attrs["reporting_mode"], "1");
+        assert_eq!((arts[5].attrs["load_appinit_dlls"].as_str(), arts[5].attrs["require_signed"].as_str()), ("1", "0"));
+        assert_eq!(arts[6].source, r"HKLM\Software\Wow6432Node\Microsoft\Windows NT\CurrentVersion\Image File Execution Options");
+        assert_eq!(arts[11].source, r"HKLM\SYSTEM\CurrentControlSet\Control\Lsa\OSConfig");
+        assert_eq!(arts[11].attrs["package"], "mimilib");
+    }
+}
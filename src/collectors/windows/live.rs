@@ -0,0 +1,161 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use super::registry::LiveKey;
+use super::{com, hooks, services, startup, tasks, RUN_KEYS};
+use crate::engine::Artifact;
+use crate::vfs::HostFs;
+use crate::util::paths::extract_executable_path_guess;
+
+use winreg::enums::*;
+use winreg::RegKey;
+
+pub fn collect_windows() -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    out.extend(collect_run_keys()?);
+    out.extend(collect_startup_folders()?);
+    out.extend(collect_services()?);
+    out.extend(collect_scheduled_tasks()?);
+    out.extend(collect_hooks());
+    out.extend(collect_com());
+    Ok(out)
+}
+
+fn collect_com() -> Vec<Artifact> {
+    let mut machine = vec![];
+    for (path, wow64) in [("SOFTWARE\\Classes\\CLSID", false), ("SOFTWARE\\Classes\\Wow6432Node\\CLSID", true)] {
+        if let Some(key) = LiveKey::open_predef(HKEY_LOCAL_MACHINE, path) {
+            machine.push(com::ClsidTree { user: None, source: format!("HKLM\\{}", path), wow64, key });
+        }
+    }
+    let mut users = vec![];
+    if let Ok(user) = std::env::var("USERNAME") {
+        for (path, wow64) in [("Software\\Classes\\CLSID", false), ("Software\\Classes\\Wow6432Node\\CLSID", true)] {
+            if let Some(key) = LiveKey::open_predef(HKEY_CURRENT_USER, path) {
+                users.push(com::user_tree(&user, path, wow64, key));
+            }
+        }
+    }
+    com::collect(&machine, &users)
+}
+
+fn collect_hooks() -> Vec<Artifact> {
+    let software = LiveKey::open_predef(HKEY_LOCAL_MACHINE, "SOFTWARE");
+    let control_set = LiveKey::open_predef(HKEY_LOCAL_MACHINE, "SYSTEM\\CurrentControlSet");
+    let mut users = vec![];
+    if let (Ok(user), Some(hkcu)) = (std::env::var("USERNAME"), LiveKey::open_predef(HKEY_CURRENT_USER, "")) {
+        users.push((user, hkcu));
+    }
+    hooks::collect(software.as_ref(), control_set.as_ref(), &users)
+}
+
+/// The task XML store is plain files, so the offline parser runs against the system drive.
+fn collect_scheduled_tasks() -> Result<Vec<Artifact>> {
+    let drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+    tasks::collect(&HostFs::new(format!("{}\\", drive)))
+}
+
+fn collect_services() -> Result<Vec<Artifact>> {
+    match LiveKey::open_predef(HKEY_LOCAL_MACHINE, "SYSTEM\\CurrentControlSet\\Services") {
+        Some(key) => services::from_services_key(&key),
+        None => Ok(vec![]),
+    }
+}
+
+fn collect_run_keys() -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+
+    for key in RUN_KEYS {
+        let subkey = format!("Software\\{}", key);
+        out.extend(read_run_key(HKEY_CURRENT_USER, &subkey, "HKCU")?);
+        out.extend(read_run_key(HKEY_LOCAL_MACHINE, &subkey, "HKLM")?);
+    }
+
+    Ok(out)
+}
+
+fn read_run_key(hkey: winreg::HKEY, subkey: &str, scope: &str) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    let root = RegKey::predef(hkey);
+
+    let key = match root.open_subkey(subkey) {
+        Ok(k) => k,
+        Err(_) => return Ok(out),
+    };
+
+    for item in key.enum_values() {
+        let (name, value) = item?;
+        let cmd = value.to_string();
+        let path_guess = extract_executable_path_guess(&cmd);
+
+        out.push(Artifact {
+            kind: "windows_run_key".to_string(),
+            source: format!("{}\\{}", scope, subkey),
+            name,
+            user: if scope == "HKCU" { std::env::var("USERNAME").ok() } else { None },
+            command: Some(cmd),
+            path: path_guess,
+            raw: None,
+            attrs: Default::default(),
+        });
+    }
+
+    // RunOnceEx keeps its entries one level down (RunOnceEx\0001\...)
+    if subkey.ends_with("RunOnceEx") {
+        for sub in key.enum_keys() {
+            let sub = sub?;
+            out.extend(read_run_key(hkey, &format!("{}\\{}", subkey, sub), scope)?);
+        }
+    }
+
+    Ok(out)
+}
+
+fn collect_startup_folders() -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+
+    if let Some(p) = user_startup_folder()? {
+        out.extend(list_startup_folder("UserStartup", p)?);
+    }
+
+    if let Some(p) = common_startup_folder()? {
+        out.extend(list_startup_folder("CommonStartup", p)?);
+    }
+
+    Ok(out)
+}
+
+fn list_startup_folder(label: &str, folder: PathBuf) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    if !folder.exists() {
+        return Ok(out);
+    }
+    let user = if label == "UserStartup" { std::env::var("USERNAME").ok() } else { None };
+    for entry in std::fs::read_dir(&folder)
+        .with_context(|| format!("Failed reading dir {}", folder.display()))?
+    {
+        let entry = entry?;
+        let path = entry.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("unknown").to_string();
+        let content = if startup::is_shortcut(&name) { std::fs::read(&path).ok() } else { None };
+
+        out.push(startup::startup_item(
+            format!("{}:{}", label, folder.display()),
+            name,
+            user.as_deref(),
+            path.display().to_string(),
+            content.as_deref(),
+        ));
+    }
+    Ok(out)
+}
+
+fn user_startup_folder() -> Result<Option<PathBuf>> {
+    let appdata = std::env::var_os("APPDATA").map(PathBuf::from);
+    Ok(appdata.map(|p| p.join("Microsoft\\Windows\\Start Menu\\Programs\\Startup")))
+}
+
+fn common_startup_folder() -> Result<Option<PathBuf>> {
+    let pd = std::env::var_os("ProgramData").map(PathBuf::from);
+    Ok(pd.map(|p| p.join("Microsoft\\Windows\\Start Menu\\Programs\\Startup")))
+}
@@ -0,0 +1,123 @@
+//! Windows persistence collectors.
+//! Live collection reads the running registry via winreg; offline collection parses
+//! hive files and profile folders from an image, so it runs on any host.
+
+use anyhow::Result;
+
+use crate::engine::Artifact;
+use crate::util::skipped;
+use crate::vfs::{find_case_insensitive, Vfs};
+
+mod com;
+mod hives;
+mod hooks;
+#[cfg(windows)]
+mod live;
+mod registry;
+mod run_keys;
+mod services;
+mod startup;
+mod tasks;
+mod wmi;
+
+#[cfg(windows)]
+pub use live::collect_windows;
+
+use hives::OfflineHives;
+
+/// Autostart keys, relative to HKLM\Software / HKCU\Software.
+pub(crate) const RUN_KEYS: &[&str] = &[
+    "Microsoft\\Windows\\CurrentVersion\\Run",
+    "Microsoft\\Windows\\CurrentVersion\\RunOnce",
+    "Microsoft\\Windows\\CurrentVersion\\RunOnceEx",
+    "Microsoft\\Windows\\CurrentVersion\\Policies\\Explorer\\Run",
+    "Wow6432Node\\Microsoft\\Windows\\CurrentVersion\\Run",
+    "Wow6432Node\\Microsoft\\Windows\\CurrentVersion\\RunOnce",
+    "Wow6432Node\\Microsoft\\Windows\\CurrentVersion\\RunOnceEx",
+    "Wow6432Node\\Microsoft\\Windows\\CurrentVersion\\Policies\\Explorer\\Run",
+];
+
+/// Profile folders under C:\Users that are links or templates rather than real accounts.
+const SKIP_PROFILES: &[&str] = &["All Users", "Default User", "Public"];
+
+/// Offline collection against a mounted Windows volume, extracted image or archive.
+pub fn collect_windows_offline(fs: &dyn Vfs) -> Result<Vec<Artifact>> {
+    let hives = OfflineHives::load(fs)?;
+
+    let mut out = vec![];
+    out.extend(run_keys::collect(&hives));
+    out.extend(or_skip("service keys", services::collect_offline(&hives)));
+    out.extend(or_skip("registry hook keys", collect_offline_hooks(&hives)));
+    out.extend(or_skip("COM class keys", com::collect_offline(&hives)));
+    out.extend(startup::collect(fs)?);
+    out.extend(tasks::collect(fs)?);
+    out.extend(wmi::collect(fs)?);
+    Ok(out)
+}
+
+/// Reads that fail (a corrupt cell in an offline hive, typically) are skipped and noted for
+/// the report, so a damaged cell only costs the keys behind it rather than the whole scan.
+pub(crate) fn or_skip<T: Default>(what: impl std::fmt::Display, result: Result<T>) -> T {
+    result.unwrap_or_else(|e| {
+        skipped::record(format!("{}: {:#}", what, e));
+        T::default()
+    })
+}
+
+fn collect_offline_hooks(hives: &OfflineHives) -> Result<Vec<Artifact>> {
+    let software = hives.software.as_ref().map(|h| h.root()).transpose()?;
+    let control_set = hives.current_control_set()?;
+    let mut users = vec![];
+    for u in &hives.users {
+        users.push((u.user.clone(), u.ntuser.root()?));
+    }
+    Ok(hooks::collect(software.as_ref(), control_set.as_ref(), &users))
+}
+
+/// A user profile directory in the image: (account name, image path).
+pub(crate) fn user_profiles(fs: &dyn Vfs) -> Result<Vec<(String, String)>> {
+    let Some(users_dir) = find_case_insensitive(fs, "/Users")? else { return Ok(vec![]) };
+
+    let mut out = vec![];
+    for entry in fs.read_dir(&users_dir)? {
+        if !entry.is_dir || SKIP_PROFILES.iter().any(|s| s.eq_ignore_ascii_case(&entry.name)) {
+            continue;
+        }
+        let path = entry.path_in(&users_dir);
+        out.push((entry.name, path));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::HostFs;
+
+    #[test]
+    fn corrupt_cells_only_cost_their_keys() {
+        let dir = std::env::temp_dir().join(format!("persist-scan-windows-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("Windows/System32/config")).unwrap();
+        std::fs::create_dir_all(dir.join("Users/bob")).unwrap();
+        std::fs::write(
+            dir.join("Windows/System32/config/SOFTWARE"),
+            include_bytes!("../../../tests/fixtures/regf/software_com.dat"),
+        )
+        .unwrap();
+        std::fs::write(dir.join("Users/bob/NTUSER.DAT"), include_bytes!("../../../tests/fixtures/regf/ntuser_corrupt_cell.dat"))
+            .unwrap();
+
+        let arts = collect_windows_offline(&HostFs::new(&dir)).unwrap();
+        // RunOnce holds the corrupt value cell; Run and the SOFTWARE hive's COM classes still come through
+        let run: Vec<&str> = arts.iter().filter(|a| a.kind == "windows_run_key").map(|a| a.name.as_str()).collect();
+        assert_eq!(run, ["Updater", "Expand", "Multi", "Flag", "Überwachung"]);
+        assert!(arts.iter().all(|a| !a.source.ends_with("RunOnce")));
+        assert!(arts.iter().any(|a| a.kind == "windows_com_hijack"));
+        let skipped = skipped::take();
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].starts_with(r"HKU\bob\Software\Microsoft\Windows\CurrentVersion\RunOnce: "), "{}", skipped[0]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::Result;
+
+use crate::parsers::regf::{self, Value, ValueData};
+
+/// Backend-neutral view of a registry key, so collector logic runs unchanged against
+/// the live registry (winreg) and offline hive files.
+pub trait RegistryKey: Sized {
+    fn name(&self) -> &str;
+
+    /// Open a descendant by backslash-separated path.
+    fn open(&self, path: &str) -> Result<Option<Self>>;
+
+    fn subkeys(&self) -> Result<Vec<Self>>;
+
+    fn values(&self) -> Result<Vec<Value>>;
+
+    /// Look up a value by name (case-insensitive); "" is the default value.
+    fn value(&self, name: &str) -> Result<Option<ValueData>> {
+        Ok(self
+            .values()?
+            .into_iter()
+            .find(|v| v.name.eq_ignore_ascii_case(name))
+            .map(|v| v.data))
+    }
+
+    /// String value rendered for display, or None if missing or empty.
+    fn string_value(&self, name: &str) -> Result<Option<String>> {
+        Ok(self
+            .value(name)?
+            .map(|d| d.to_display_string())
+            .filter(|s| !s.is_empty()))
+    }
+}
+
+impl<'a> RegistryKey for regf::Key<'a> {
+    fn name(&self) -> &str {
+        regf::Key::name(self)
+    }
+
+    fn open(&self, path: &str) -> Result<Option<Self>> {
+        regf::Key::open(self, path)
+    }
+
+    fn subkeys(&self) -> Result<Vec<Self>> {
+        regf::Key::subkeys(self)
+    }
+
+    fn values(&self) -> Result<Vec<Value>> {
+        regf::Key::values(self)
+    }
+}
+
+#[cfg(windows)]
+pub struct LiveKey {
+    name: String,
+    key: winreg::RegKey,
+}
+
+#[cfg(windows)]
+impl LiveKey {
+    /// Open a key under a predefined root (HKLM, HKCU, ...); None if missing or access is denied.
+    pub fn open_predef(hkey: winreg::HKEY, path: &str) -> Option<Self> {
+        let key = winreg::RegKey::predef(hkey).open_subkey(path).ok()?;
+        Some(Self { name: last_component(path), key })
+    }
+}
+
+#[cfg(windows)]
+fn last_component(path: &str) -> String {
+    path.rsplit('\\').next().unwrap_or(path).to_string()
+}
+
+#[cfg(windows)]
+impl RegistryKey for LiveKey {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn open(&self, path: &str) -> Result<Option<Self>> {
+        Ok(self
+            .key
+            .open_subkey(path)
+            .ok()
+            .map(|key| LiveKey { name: last_component(path), key }))
+    }
+
+    fn subkeys(&self) -> Result<Vec<Self>> {
+        let mut out = vec![];
+        for name in self.key.enum_keys() {
+            let name = name?;
+            if let Ok(key) = self.key.open_subkey(&name) {
+                out.push(LiveKey { name, key });
+            }
+        }
+        Ok(out)
+    }
+
+    fn values(&self) -> Result<Vec<Value>> {
+        let mut out = vec![];
+        for item in self.key.enum_values() {
+            let (name, value) = item?;
+            out.push(Value { name, data: regf::decode(value.vtype as u32, value.bytes) });
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+
+use super::hives::OfflineHives;
+use super::{or_skip, RUN_KEYS};
+use crate::engine::Artifact;
+use crate::parsers::regf::Hive;
+use crate::util::paths::extract_executable_path_guess;
+
+/// Run/RunOnce/RunOnceEx/Policies Run keys from the SOFTWARE hive and every user's NTUSER.DAT.
+/// Sources use the same "HKLM\..." layout as live collection; per-user keys appear under HKU\<user>.
+/// A key that fails to read is skipped on its own.
+pub fn collect(hives: &OfflineHives) -> Vec<Artifact> {
+    let mut out = vec![];
+
+    if let Some(software) = &hives.software {
+        for key in RUN_KEYS {
+            let source = format!("HKLM\\Software\\{}", key);
+            out.extend(or_skip(&source, read_run_key(software, key, &source, None)));
+        }
+    }
+
+    for u in &hives.users {
+        for key in RUN_KEYS {
+            let subkey = format!("Software\\{}", key);
+            let source = format!("HKU\\{}\\{}", u.user, subkey);
+            out.extend(or_skip(&source, read_run_key(&u.ntuser, &subkey, &source, Some(&u.user))));
+        }
+    }
+
+    out
+}
+
+fn read_run_key(hive: &Hive, path: &str, source: &str, user: Option<&str>) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    let Some(key) = hive.open(path)? else { return Ok(out) };
+
+    for value in key.values()? {
+        let cmd = value.data.to_display_string();
+        let path_guess = extract_executable_path_guess(&cmd);
+
+        out.push(Artifact {
+            kind: "windows_run_key".to_string(),
+            source: source.to_string(),
+            name: value.name,
+            user: user.map(str::to_string),
+            command: Some(cmd),
+            path: path_guess,
+            raw: None,
+            attrs: Default::default(),
+        });
+    }
+
+    // RunOnceEx keeps its entries one level down (RunOnceEx\0001\...)
+    if path.ends_with("RunOnceEx") {
+        for sub in key.subkeys()? {
+            let sub_path = format!("{}\\{}", path, sub.name());
+            let sub_source = format!("{}\\{}", source, sub.name());
+            out.extend(or_skip(&sub_source, read_run_key(hive, &sub_path, &sub_source, user)));
+        }
+    }
+
+    Ok(out)
+}
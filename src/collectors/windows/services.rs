@@ -0,0 +1,151 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+use super::hives::OfflineHives;
+use super::or_skip;
+use super::registry::RegistryKey;
+use crate::engine::Artifact;
+use crate::util::paths::{is_unquoted_path_with_spaces, normalize_windows_image_path};
+
+/// Services and drivers from the offline SYSTEM hive, following Select\Current
+/// to the control set that was active when the image was taken.
+pub fn collect_offline(hives: &OfflineHives) -> Result<Vec<Artifact>> {
+    let Some(control_set) = hives.current_control_set()? else { return Ok(vec![]) };
+
+    match control_set.open("Services")? {
+        Some(services) => from_services_key(&services),
+        None => Ok(vec![]),
+    }
+}
+
+/// One `windows_service` artifact per service with an ImagePath or ServiceDll.
+/// A service key that fails to read is skipped on its own.
+pub fn from_services_key<K: RegistryKey>(services: &K) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    for svc in services.subkeys()? {
+        let what = format!("service key {}", svc.name());
+        out.extend(or_skip(what, service(&svc)));
+    }
+    Ok(out)
+}
+
+/// `path` is the normalised ServiceDll when present (svchost-hosted services), else the ImagePath binary.
+fn service<K: RegistryKey>(svc: &K) -> Result<Option<Artifact>> {
+    let image_path = svc.string_value("ImagePath")?;
+    let service_dll = match svc.open("Parameters")? {
+        Some(params) => params.string_value("ServiceDll")?,
+        None => None,
+    }
+    .or(svc.string_value("ServiceDll")?);
+
+    if image_path.is_none() && service_dll.is_none() {
+        return Ok(None);
+    }
+
+    let mut attrs = BTreeMap::new();
+    if let Some(start) = svc.value("Start")?.and_then(|v| v.as_u32()) {
+        attrs.insert("start".to_string(), start_type_name(start));
+    }
+    if let Some(ty) = svc.value("Type")?.and_then(|v| v.as_u32()) {
+        attrs.insert("type".to_string(), service_type_name(ty));
+    }
+    for (attr, value) in [
+        ("object_name", "ObjectName"),
+        ("failure_command", "FailureCommand"),
+        ("display_name", "DisplayName"),
+    ] {
+        if let Some(v) = svc.string_value(value)? {
+            attrs.insert(attr.to_string(), v);
+        }
+    }
+    if let Some(ip) = &image_path {
+        attrs.insert("image_path".to_string(), ip.clone());
+        if is_unquoted_path_with_spaces(ip) {
+            attrs.insert("unquoted_path".to_string(), "true".to_string());
+        }
+    }
+    if let Some(dll) = &service_dll {
+        attrs.insert("service_dll".to_string(), dll.clone());
+    }
+
+    let path = service_dll
+        .as_deref()
+        .or(image_path.as_deref())
+        .and_then(normalize_windows_image_path);
+
+    Ok(Some(Artifact {
+        kind: "windows_service".to_string(),
+        source: format!("HKLM\\SYSTEM\\CurrentControlSet\\Services\\{}", svc.name()),
+        name: svc.name().to_string(),
+        user: None,
+        command: image_path.or(service_dll),
+        path,
+        raw: None,
+        attrs,
+    }))
+}
+
+fn start_type_name(start: u32) -> String {
+    match start {
+        0 => "boot".to_string(),
+        1 => "system".to_string(),
+        2 => "auto".to_string(),
+        3 => "demand".to_string(),
+        4 => "disabled".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn service_type_name(ty: u32) -> String {
+    match ty {
+        0x1 => "kernel_driver".to_string(),
+        0x2 => "file_system_driver".to_string(),
+        0x10 => "own_process".to_string(),
+        0x20 => "share_process".to_string(),
+        0x50 => "user_own_process".to_string(),
+        0x60 => "user_share_process".to_string(),
+        0x110 => "own_process_interactive".to_string(),
+        0x120 => "share_process_interactive".to_string(),
+        other => format!("{:#x}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::regf::Hive;
+
+    #[test]
+    fn services_of_the_current_control_set() {
+        let system = Hive::parse(include_bytes!("../../../tests/fixtures/regf/system_services.dat").to_vec()).unwrap();
+        let hives = OfflineHives { software: None, system: Some(system), users: vec![] };
+        let arts = collect_offline(&hives).unwrap();
+        // Select\Current is 2: the Stale service in ControlSet001 is not read, Empty has nothing to run
+        let found: Vec<(&str, Option<&str>, &str, &str)> = arts
+            .iter()
+            .map(|a| (a.name.as_str(), a.path.as_deref(), a.attrs["start"].as_str(), a.attrs["type"].as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("EvilSvc", Some(r"C:\ProgramData\evil.dll"), "auto", "share_process"),
+                ("MyApp", Some(r"C:\Program Files\My App\svc.exe"), "demand", "own_process"),
+                ("mydrv", Some(r"C:\Windows\System32\drivers\mydrv.sys"), "boot", "kernel_driver"),
+                ("DllOnly", Some(r"C:\Tools\only.dll"), "7", "0x400"),
+            ]
+        );
+
+        let evil = &arts[0];
+        assert_eq!(evil.source, r"HKLM\SYSTEM\CurrentControlSet\Services\EvilSvc");
+        assert_eq!(evil.command.as_deref(), Some(r"%SystemRoot%\system32\svchost.exe -k netsvcs -p"));
+        assert_eq!(evil.attrs["service_dll"], r"C:\ProgramData\evil.dll");
+        assert_eq!(evil.attrs["failure_command"], r"C:\Windows\Temp\fail.exe");
+        assert_eq!(evil.attrs["object_name"], "LocalSystem");
+        assert!(!evil.attrs.contains_key("unquoted_path"));
+
+        assert_eq!(arts[1].attrs["unquoted_path"], "true");
+        assert_eq!(arts[1].attrs["display_name"], "My App");
+        assert_eq!(arts[3].command.as_deref(), Some(r"C:\Tools\only.dll"));
+        assert!(!arts[3].attrs.contains_key("image_path"));
+    }
+}
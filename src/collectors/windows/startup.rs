@@ -0,0 +1,113 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+use super::user_profiles;
+use crate::engine::Artifact;
+use crate::parsers::lnk::parse_lnk;
+use crate::util::paths::{normalize_windows_path, to_windows_path};
+use crate::vfs::{find_case_insensitive, join, Vfs};
+
+const STARTUP_SUBDIR: &str = "Microsoft/Windows/Start Menu/Programs/Startup";
+
+/// Per-user and all-users Startup folders in an offline image.
+pub fn collect(fs: &dyn Vfs) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+
+    for (user, profile) in user_profiles(fs)? {
+        let folder = join(&join(&profile, "AppData/Roaming"), STARTUP_SUBDIR);
+        out.extend(list_startup_folder(fs, "UserStartup", &folder, Some(&user))?);
+    }
+
+    out.extend(list_startup_folder(fs, "CommonStartup", &join("/ProgramData", STARTUP_SUBDIR), None)?);
+    Ok(out)
+}
+
+fn list_startup_folder(fs: &dyn Vfs, label: &str, folder: &str, user: Option<&str>) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    let Some(folder) = find_case_insensitive(fs, folder)? else { return Ok(out) };
+
+    for entry in fs.read_dir(&folder)? {
+        let path = entry.path_in(&folder);
+        let content = if is_shortcut(&entry.name) { fs.read(&path)? } else { None };
+        out.push(startup_item(
+            format!("{}:{}", label, to_windows_path(&folder)),
+            entry.name,
+            user,
+            to_windows_path(&path),
+            content.as_deref(),
+        ));
+    }
+    Ok(out)
+}
+
+pub(crate) fn is_shortcut(name: &str) -> bool {
+    name.to_ascii_lowercase().ends_with(".lnk")
+}
+
+/// A Startup folder entry. For shortcuts (`lnk` holds the file content) command/path come
+/// from the link target, and the shortcut's own location is kept in attrs.
+pub(crate) fn startup_item(source: String, name: String, user: Option<&str>, item_path: String, lnk: Option<&[u8]>) -> Artifact {
+    let mut artifact = Artifact {
+        kind: "windows_startup_folder".to_string(),
+        source,
+        name,
+        user: user.map(str::to_string),
+        command: None,
+        path: Some(item_path.clone()),
+        raw: None,
+        attrs: BTreeMap::new(),
+    };
+
+    let Some(link) = lnk.and_then(|b| parse_lnk(b).ok()) else { return artifact };
+
+    artifact.attrs.insert("shortcut".to_string(), item_path);
+    for (k, v) in [
+        ("working_directory", &link.working_dir),
+        ("icon_location", &link.icon_location),
+        ("relative_path", &link.relative_path),
+        ("description", &link.description),
+    ] {
+        if let Some(v) = v {
+            artifact.attrs.insert(k.to_string(), v.clone());
+        }
+    }
+    artifact.command = link.command_line();
+    artifact.path = link
+        .target
+        .as_deref()
+        .or(link.relative_path.as_deref())
+        .and_then(normalize_windows_path)
+        .or(artifact.path);
+    artifact
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shortcut(name: &str, bytes: &[u8]) -> Artifact {
+        startup_item("UserStartup".to_string(), name.to_string(), Some("bob"), format!(r"C:\Startup\{}", name), Some(bytes))
+    }
+
+    #[test]
+    fn shortcut_targets_with_spaces_are_kept_whole() {
+        let item = shortcut("local_path.lnk", include_bytes!("../../../tests/fixtures/lnk/local_path.lnk"));
+        assert_eq!(item.path.as_deref(), Some(r"C:\Program Files\Contoso\start.bat"));
+        assert_eq!(item.command.as_deref(), Some(r#""C:\Program Files\Contoso\start.bat""#));
+        assert_eq!(item.attrs["shortcut"], r"C:\Startup\local_path.lnk");
+
+        let item = shortcut("relative_path.lnk", include_bytes!("../../../tests/fixtures/lnk/relative_path.lnk"));
+        assert_eq!(item.path.as_deref(), Some(r"..\..\..\..\Tools\sync tool.cmd"));
+        assert_eq!(item.attrs["working_directory"], r"C:\Tools");
+
+        let item = shortcut("environment.lnk", include_bytes!("../../../tests/fixtures/lnk/environment.lnk"));
+        assert_eq!(item.path.as_deref(), Some(r"C:\Windows\system32\cmd.exe"));
+    }
+
+    #[test]
+    fn unparsable_shortcuts_keep_their_own_path() {
+        let item = shortcut("bad_header.lnk", include_bytes!("../../../tests/fixtures/lnk/bad_header.lnk"));
+        assert_eq!(item.path.as_deref(), Some(r"C:\Startup\bad_header.lnk"));
+        assert_eq!(item.command, None);
+    }
+}
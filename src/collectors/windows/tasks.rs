@@ -0,0 +1,170 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+use crate::engine::Artifact;
+use crate::parsers::task_xml::{parse_task, TaskAction};
+use crate::util::paths::{normalize_windows_path, to_windows_path};
+use crate::util::skipped;
+use crate::vfs::{find_case_insensitive, Vfs};
+
+const TASKS_DIR: &str = "/Windows/System32/Tasks";
+const MAX_DEPTH: usize = 16;
+
+/// Scheduled tasks from the Task Scheduler XML store, one artifact per action.
+/// Files that are not valid task XML, and folders or files the scan is not allowed to open
+/// (live scans without admin rights), are skipped rather than failing the scan.
+pub fn collect(fs: &dyn Vfs) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+    if let Some(dir) = find_case_insensitive(fs, TASKS_DIR)? {
+        walk(fs, &dir, &dir, 0, &mut out)?;
+    }
+    Ok(out)
+}
+
+fn walk(fs: &dyn Vfs, base: &str, dir: &str, depth: usize, out: &mut Vec<Artifact>) -> Result<()> {
+    if depth > MAX_DEPTH {
+        return Ok(());
+    }
+    let Some(entries) = skip_denied(fs.read_dir(dir), dir)? else { return Ok(()) };
+    for entry in entries {
+        let path = entry.path_in(dir);
+        if entry.is_dir {
+            walk(fs, base, &path, depth + 1, out)?;
+            continue;
+        }
+        let Some(bytes) = skip_denied(fs.read(&path), &path)?.flatten() else { continue };
+        let Ok(task) = parse_task(&bytes) else { continue };
+
+        // Task path as shown by schtasks: "\Microsoft\Windows\Foo"
+        let name = path[base.len()..].replace('/', "\\");
+
+        let mut common = BTreeMap::new();
+        common.insert("hidden".to_string(), task.hidden.to_string());
+        common.insert("enabled".to_string(), task.enabled.to_string());
+        if !task.triggers.is_empty() {
+            common.insert("triggers".to_string(), task.triggers.join(","));
+        }
+        for (k, v) in [
+            ("author", &task.author),
+            ("user_id", &task.user_id),
+            ("group_id", &task.group_id),
+            ("logon_type", &task.logon_type),
+            ("run_level", &task.run_level),
+        ] {
+            if let Some(v) = v {
+                common.insert(k.to_string(), v.clone());
+            }
+        }
+
+        for (idx, action) in task.actions.iter().enumerate() {
+            let mut attrs = common.clone();
+            if task.actions.len() > 1 {
+                attrs.insert("action_index".to_string(), (idx + 1).to_string());
+            }
+
+            let (command, exe_path) = match action {
+                TaskAction::Exec { command, arguments, working_directory } => {
+                    attrs.insert("action".to_string(), "exec".to_string());
+                    if let Some(wd) = working_directory {
+                        attrs.insert("working_directory".to_string(), wd.clone());
+                    }
+                    // <Command> is the program path alone (spaces and all); quote it in the
+                    // command line so it reads the way Task Scheduler runs it
+                    let program = if command.contains(' ') && !command.starts_with('"') {
+                        format!("\"{}\"", command)
+                    } else {
+                        command.clone()
+                    };
+                    let full = match arguments {
+                        Some(a) => {
+                            attrs.insert("arguments".to_string(), a.clone());
+                            format!("{} {}", program, a)
+                        }
+                        None => program,
+                    };
+                    (Some(full), normalize_windows_path(command))
+                }
+                TaskAction::ComHandler { class_id, data } => {
+                    attrs.insert("action".to_string(), "com_handler".to_string());
+                    attrs.insert("class_id".to_string(), class_id.clone());
+                    if let Some(d) = data {
+                        attrs.insert("com_data".to_string(), d.clone());
+                    }
+                    (None, None)
+                }
+            };
+
+            out.push(Artifact {
+                kind: "windows_scheduled_task".to_string(),
+                source: to_windows_path(&path),
+                name: name.clone(),
+                user: task.user_id.clone(),
+                command,
+                path: exe_path,
+                raw: None,
+                attrs,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn skip_denied<T>(result: Result<T>, path: &str) -> Result<Option<T>> {
+    match result {
+        Ok(v) => Ok(Some(v)),
+        Err(e) if is_permission_denied(&e) => {
+            skipped::record(format!("{}: {:#}", to_windows_path(path), e));
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn is_permission_denied(e: &anyhow::Error) -> bool {
+    e.chain()
+        .filter_map(|c| c.downcast_ref::<std::io::Error>())
+        .any(|io| io.kind() == std::io::ErrorKind::PermissionDenied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::HostFs;
+
+    #[test]
+    fn exec_command_is_a_path_and_arguments_stay_separate() {
+        let dir = std::env::temp_dir().join(format!("persist-scan-tasks-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("Windows/System32/Tasks/Contoso")).unwrap();
+        let multi_exec = include_bytes!("../../../tests/fixtures/tasks/multi_exec.xml");
+        std::fs::write(dir.join("Windows/System32/Tasks/Contoso/Sync"), multi_exec).unwrap();
+
+        let found = collect(&HostFs::new(&dir)).unwrap();
+        assert_eq!(found.len(), 2);
+        let sync = &found[0];
+        assert_eq!(sync.name, r"\Contoso\Sync");
+        assert_eq!(sync.path.as_deref(), Some(r"C:\Program Files\Contoso\Sync\run.cmd"));
+        assert_eq!(
+            sync.command.as_deref(),
+            Some(r#""C:\Program Files\Contoso\Sync\run.cmd" --quiet --log "C:\ProgramData\Contoso\sync.log""#)
+        );
+        assert_eq!(sync.attrs["arguments"], r#"--quiet --log "C:\ProgramData\Contoso\sync.log""#);
+        assert_eq!(sync.attrs["action_index"], "1");
+
+        let helper = &found[1];
+        assert_eq!(helper.path.as_deref(), Some(r"C:\Windows\System32\rundll32.exe"));
+        assert_eq!(helper.attrs["arguments"], r"C:\ProgramData\Contoso\helper.dll,Start");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn only_permission_errors_are_skipped() {
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        let denied: Result<()> = Err(anyhow::Error::new(denied).context("Failed reading dir"));
+        assert!(matches!(skip_denied(denied, "/Windows/System32/Tasks/Microsoft"), Ok(None)));
+
+        let other: Result<()> = Err(anyhow::Error::new(std::io::Error::other("disk gone")));
+        assert!(skip_denied(other, "/Windows/System32/Tasks").is_err());
+    }
+}
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+use crate::engine::Artifact;
+use crate::parsers::wmi::carve_subscriptions;
+use crate::util::paths::{normalize_windows_image_path, to_windows_path};
+use crate::vfs::{find_case_insensitive, Vfs};
+
+/// Repository locations: Vista and later, then XP/2003.
+const REPOSITORIES: &[&str] = &[
+    "/Windows/System32/wbem/Repository/OBJECTS.DATA",
+    "/Windows/System32/wbem/Repository/FS/OBJECTS.DATA",
+];
+
+/// WMI event subscriptions with a CommandLine or ActiveScript consumer, one artifact per binding.
+pub fn collect(fs: &dyn Vfs) -> Result<Vec<Artifact>> {
+    let mut out = vec![];
+
+    for repo in REPOSITORIES {
+        let Some(path) = find_case_insensitive(fs, repo)? else { continue };
+        let Some(data) = fs.read(&path)? else { continue };
+
+        for sub in carve_subscriptions(&data) {
+            let mut attrs = BTreeMap::new();
+            attrs.insert("consumer_class".to_string(), sub.consumer_class.clone());
+            attrs.insert("consumer_name".to_string(), sub.consumer_name.clone());
+            for (k, v) in [
+                ("filter_name", &sub.filter_name),
+                ("query", &sub.query),
+                ("script_engine", &sub.script_engine),
+            ] {
+                if let Some(v) = v {
+                    attrs.insert(k.to_string(), v.clone());
+                }
+            }
+
+            // ActiveScript consumers have no command line; the script text is what runs.
+            let command = sub.command_line.clone().or(sub.script_text);
+            out.push(Artifact {
+                kind: "windows_wmi_subscription".to_string(),
+                source: to_windows_path(&path),
+                name: format!("{}.Name=\"{}\"", sub.consumer_class, sub.consumer_name),
+                user: None,
+                path: sub.command_line.as_deref().and_then(normalize_windows_image_path),
+                command,
+                raw: None,
+                attrs,
+            });
+        }
+    }
+
+    Ok(out)
+}
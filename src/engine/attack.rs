@@ -0,0 +1,320 @@
+//! MITRE ATT&CK mapping: the technique table rule `attack:` entries are validated against,
+//! the per-technique summary of a report, and ATT&CK Navigator layer export.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::report::{Finding, Report};
+use super::rule::Severity;
+
+/// One technique a rule detects, with the tactic it is used for there.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct AttackRef {
+    /// Technique or sub-technique ID, e.g. T1053 or T1053.003
+    pub technique: String,
+    /// Tactic short name as used by ATT&CK Navigator, e.g. persistence, privilege-escalation
+    pub tactic: String,
+}
+
+/// Enterprise techniques relevant to persistence hunting: (ID, name, tactics).
+/// Sub-techniques have no tactics of their own and inherit their parent's.
+const TECHNIQUES: &[(&str, &str, &[&str])] = &[
+    ("T1036", "Masquerading", &["defense-evasion"]),
+    ("T1036.005", "Match Legitimate Name or Location", &[]),
+    ("T1037", "Boot or Logon Initialization Scripts", &["persistence", "privilege-escalation"]),
+    ("T1037.001", "Logon Script (Windows)", &[]),
+    ("T1037.002", "Login Hook", &[]),
+    ("T1037.003", "Network Logon Script", &[]),
+    ("T1037.004", "RC Scripts", &[]),
+    ("T1037.005", "Startup Items", &[]),
+    ("T1053", "Scheduled Task/Job", &["execution", "persistence", "privilege-escalation"]),
+    ("T1053.002", "At", &[]),
+    ("T1053.003", "Cron", &[]),
+    ("T1053.005", "Scheduled Task", &[]),
+    ("T1053.006", "Systemd Timers", &[]),
+    ("T1059", "Command and Scripting Interpreter", &["execution"]),
+    ("T1059.001", "PowerShell", &[]),
+    ("T1059.003", "Windows Command Shell", &[]),
+    ("T1059.004", "Unix Shell", &[]),
+    ("T1078", "Valid Accounts", &["defense-evasion", "persistence", "privilege-escalation", "initial-access"]),
+    ("T1098", "Account Manipulation", &["persistence", "privilege-escalation"]),
+    ("T1098.004", "SSH Authorized Keys", &[]),
+    ("T1133", "External Remote Services", &["persistence", "initial-access"]),
+    ("T1136", "Create Account", &["persistence"]),
+    ("T1136.001", "Local Account", &[]),
+    ("T1137", "Office Application Startup", &["persistence"]),
+    ("T1176", "Browser Extensions", &["persistence"]),
+    ("T1197", "BITS Jobs", &["defense-evasion", "persistence"]),
+    ("T1505", "Server Software Component", &["persistence"]),
+    ("T1505.003", "Web Shell", &[]),
+    ("T1542", "Pre-OS Boot", &["defense-evasion", "persistence"]),
+    ("T1543", "Create or Modify System Process", &["persistence", "privilege-escalation"]),
+    ("T1543.001", "Launch Agent", &[]),
+    ("T1543.002", "Systemd Service", &[]),
+    ("T1543.003", "Windows Service", &[]),
+    ("T1543.004", "Launch Daemon", &[]),
+    ("T1546", "Event Triggered Execution", &["persistence", "privilege-escalation"]),
+    ("T1546.002", "Screensaver", &[]),
+    ("T1546.003", "Windows Management Instrumentation Event Subscription", &[]),
+    ("T1546.004", "Unix Shell Configuration Modification", &[]),
+    ("T1546.007", "Netsh Helper DLL", &[]),
+    ("T1546.008", "Accessibility Features", &[]),
+    ("T1546.009", "AppCert DLLs", &[]),
+    ("T1546.010", "AppInit DLLs", &[]),
+    ("T1546.012", "Image File Execution Options Injection", &[]),
+    ("T1546.014", "Emond", &[]),
+    ("T1546.015", "Component Object Model Hijacking", &[]),
+    ("T1547", "Boot or Logon Autostart Execution", &["persistence", "privilege-escalation"]),
+    ("T1547.001", "Registry Run Keys / Startup Folder", &[]),
+    ("T1547.002", "Authentication Package", &[]),
+    ("T1547.004", "Winlogon Helper DLL", &[]),
+    ("T1547.005", "Security Support Provider", &[]),
+    ("T1547.006", "Kernel Modules and Extensions", &[]),
+    ("T1547.009", "Shortcut Modification", &[]),
+    ("T1547.010", "Port Monitors", &[]),
+    ("T1547.011", "Plist Modification", &[]),
+    ("T1547.013", "XDG Autostart Entries", &[]),
+    ("T1547.014", "Active Setup", &[]),
+    ("T1547.015", "Login Items", &[]),
+    ("T1554", "Compromise Host Software Binary", &["persistence"]),
+    ("T1556", "Modify Authentication Process", &["credential-access", "defense-evasion", "persistence"]),
+    ("T1556.002", "Password Filter DLL", &[]),
+    ("T1564", "Hide Artifacts", &["defense-evasion"]),
+    ("T1564.001", "Hidden Files and Directories", &[]),
+    ("T1574", "Hijack Execution Flow", &["persistence", "privilege-escalation", "defense-evasion"]),
+    ("T1574.001", "DLL Search Order Hijacking", &[]),
+    ("T1574.002", "DLL Side-Loading", &[]),
+    ("T1574.011", "Services Registry Permissions Weakness", &[]),
+];
+
+/// Technique name, if the ID is in the bundled table (IDs compare ignoring case).
+pub fn technique_name(id: &str) -> Option<&'static str> {
+    TECHNIQUES.iter().find(|(tid, _, _)| tid.eq_ignore_ascii_case(id)).map(|(_, name, _)| *name)
+}
+
+/// Tactics of a technique; sub-techniques inherit their parent's.
+pub fn tactics(id: &str) -> &'static [&'static str] {
+    let parent = id.split('.').next().unwrap_or(id);
+    TECHNIQUES
+        .iter()
+        .find(|(tid, _, _)| tid.eq_ignore_ascii_case(parent))
+        .map(|(_, _, tactics)| *tactics)
+        .unwrap_or(&[])
+}
+
+/// Check a rule's `attack:` entries: known technique IDs, and a tactic the technique belongs to.
+pub fn validate(refs: &[AttackRef]) -> Result<()> {
+    for r in refs {
+        if technique_name(&r.technique).is_none() {
+            return Err(anyhow!("Unknown ATT&CK technique: {}", r.technique));
+        }
+        let allowed = tactics(&r.technique);
+        if !allowed.iter().any(|t| t.eq_ignore_ascii_case(&r.tactic)) {
+            return Err(anyhow!(
+                "ATT&CK technique {} is not part of tactic '{}' (use {})",
+                r.technique,
+                r.tactic,
+                allowed.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Findings per technique and tactic, for the report summary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TechniqueSummary {
+    pub technique: String,
+    pub name: String,
+    pub tactic: String,
+    pub findings: usize,
+    pub max_severity: Severity,
+    pub rules: Vec<String>,
+}
+
+/// Summarise the techniques behind `findings`, ordered by tactic and then technique ID.
+pub fn summarize(findings: &[Finding]) -> Vec<TechniqueSummary> {
+    let mut by_key: BTreeMap<(String, String), TechniqueSummary> = BTreeMap::new();
+    for f in findings {
+        for r in &f.attack {
+            let technique = r.technique.to_uppercase();
+            let tactic = r.tactic.to_lowercase();
+            let entry = by_key.entry((tactic.clone(), technique.clone())).or_insert_with(|| TechniqueSummary {
+                name: technique_name(&technique).unwrap_or_default().to_string(),
+                technique,
+                tactic,
+                findings: 0,
+                max_severity: f.severity,
+                rules: vec![],
+            });
+            entry.findings += 1;
+            if f.severity.points() > entry.max_severity.points() {
+                entry.max_severity = f.severity;
+            }
+            if !entry.rules.contains(&f.rule_id) {
+                entry.rules.push(f.rule_id.clone());
+            }
+        }
+    }
+    by_key.into_values().collect()
+}
+
+/// ATT&CK Navigator layer (format 4.5) with the techniques that fired: scored by finding
+/// count and coloured by their highest severity. Parents of fired sub-techniques are expanded.
+pub fn navigator_layer(report: &Report, name: &str) -> serde_json::Value {
+    let mut techniques = vec![];
+    let mut expanded: Vec<(String, String)> = vec![];
+    for t in &report.attack {
+        let color = match t.max_severity {
+            Severity::High => "#e60d0d",
+            Severity::Medium => "#fc9c3c",
+            Severity::Low => "#ffe766",
+        };
+        techniques.push(serde_json::json!({
+            "techniqueID": t.technique,
+            "tactic": t.tactic,
+            "score": t.findings,
+            "color": color,
+            "comment": format!("{} finding(s): {}", t.findings, t.rules.join(", ")),
+            "enabled": true,
+        }));
+        if let Some((parent, _)) = t.technique.split_once('.') {
+            let key = (parent.to_string(), t.tactic.clone());
+            if !expanded.contains(&key) {
+                expanded.push(key);
+            }
+        }
+    }
+    for (parent, tactic) in expanded {
+        techniques.push(serde_json::json!({
+            "techniqueID": parent,
+            "tactic": tactic,
+            "showSubtechniques": true,
+            "enabled": true,
+        }));
+    }
+
+    let max_score = report.attack.iter().map(|t| t.findings).max().unwrap_or(1);
+    serde_json::json!({
+        "name": name,
+        "versions": { "layer": "4.5", "navigator": "5.1.0" },
+        "domain": "enterprise-attack",
+        "description": format!(
+            "persist-scan findings, generated {}",
+            report.generated_at_utc.format("%Y-%m-%d %H:%M:%S UTC")
+        ),
+        "sorting": 3,
+        "hideDisabled": false,
+        "techniques": techniques,
+        "gradient": {
+            "colors": ["#ffe766", "#e60d0d"],
+            "minValue": 0,
+            "maxValue": max_score,
+        },
+        "legendItems": [
+            { "label": "High severity", "color": "#e60d0d" },
+            { "label": "Medium severity", "color": "#fc9c3c" },
+            { "label": "Low severity", "color": "#ffe766" },
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::report::tests::{finding, report};
+
+    fn attack(technique: &str, tactic: &str) -> AttackRef {
+        AttackRef { technique: technique.to_string(), tactic: tactic.to_string() }
+    }
+
+    fn with_attack(rule_id: &str, severity: Severity, refs: &[(&str, &str)]) -> Finding {
+        Finding { attack: refs.iter().map(|(t, tac)| attack(t, tac)).collect(), ..finding(rule_id, severity) }
+    }
+
+    #[test]
+    fn validate_checks_technique_and_tactic() {
+        assert!(validate(&[attack("T1053.003", "persistence"), attack("t1547", "Privilege-Escalation")]).is_ok());
+
+        let unknown = validate(&[attack("T1053.003", "persistence"), attack("T9999", "persistence")]).unwrap_err();
+        assert_eq!(unknown.to_string(), "Unknown ATT&CK technique: T9999");
+        assert!(validate(&[attack("T1053.999", "persistence")]).is_err());
+
+        let wrong = validate(&[attack("T1136", "execution")]).unwrap_err();
+        assert_eq!(wrong.to_string(), "ATT&CK technique T1136 is not part of tactic 'execution' (use persistence)");
+    }
+
+    #[test]
+    fn sub_techniques_inherit_parent_tactics() {
+        assert_eq!(tactics("T1053.005"), ["execution", "persistence", "privilege-escalation"]);
+        assert_eq!(tactics("t1546.015"), tactics("T1546"));
+        assert!(validate(&[attack("T1543.003", "privilege-escalation")]).is_ok());
+        let err = validate(&[attack("T1543.003", "execution")]).unwrap_err();
+        assert_eq!(err.to_string(), "ATT&CK technique T1543.003 is not part of tactic 'execution' (use persistence, privilege-escalation)");
+        assert_eq!(technique_name("T1547.001"), Some("Registry Run Keys / Startup Folder"));
+    }
+
+    #[test]
+    fn summary_groups_by_tactic_and_technique() {
+        let findings = [
+            with_attack("CRON_ANY", Severity::Low, &[("T1053.003", "persistence")]),
+            with_attack("CRON_SHELL", Severity::High, &[("t1053.003", "Persistence"), ("T1059.004", "execution")]),
+            with_attack("CRON_ANY", Severity::Medium, &[("T1053.003", "persistence")]),
+            with_attack("CRON_PRIV", Severity::Low, &[("T1053.003", "privilege-escalation")]),
+            finding("NO_ATTACK", Severity::High),
+        ];
+        let summary = summarize(&findings);
+        let found: Vec<_> = summary
+            .iter()
+            .map(|t| {
+                let rules = t.rules.iter().map(String::as_str).collect();
+                (t.tactic.as_str(), t.technique.as_str(), t.name.as_str(), t.findings, t.max_severity, rules)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("execution", "T1059.004", "Unix Shell", 1, Severity::High, vec!["CRON_SHELL"]),
+                // IDs and tactics are compared ignoring case; each rule is listed once
+                ("persistence", "T1053.003", "Cron", 3, Severity::High, vec!["CRON_ANY", "CRON_SHELL"]),
+                ("privilege-escalation", "T1053.003", "Cron", 1, Severity::Low, vec!["CRON_PRIV"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn navigator_layer_expands_parent_techniques() {
+        let r = report(vec![
+            with_attack("A", Severity::Medium, &[("T1053.003", "persistence"), ("T1053.005", "persistence")]),
+            with_attack("B", Severity::Low, &[("T1053.003", "persistence"), ("T1136", "persistence")]),
+            with_attack("C", Severity::High, &[("T1053.005", "execution")]),
+        ]);
+        let layer = navigator_layer(&r, "case 42");
+        assert_eq!(layer["name"], "case 42");
+        assert_eq!(layer["gradient"]["maxValue"], 2);
+
+        let techniques = layer["techniques"].as_array().unwrap();
+        let found: Vec<_> = techniques
+            .iter()
+            .map(|t| {
+                let show = t["showSubtechniques"].as_bool().unwrap_or(false);
+                (t["techniqueID"].as_str().unwrap(), t["tactic"].as_str().unwrap(), t["score"].as_u64(), t["color"].as_str(), show)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("T1053.005", "execution", Some(1), Some("#e60d0d"), false),
+                ("T1053.003", "persistence", Some(2), Some("#fc9c3c"), false),
+                ("T1053.005", "persistence", Some(1), Some("#fc9c3c"), false),
+                ("T1136", "persistence", Some(1), Some("#ffe766"), false),
+                // One expanded parent per (technique, tactic), after the scored entries
+                ("T1053", "execution", None, None, true),
+                ("T1053", "persistence", None, None, true),
+            ]
+        );
+        assert_eq!(techniques[1]["comment"], "2 finding(s): A, B");
+    }
+}
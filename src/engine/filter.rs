@@ -0,0 +1,130 @@
+//! Finding filters (`scan --min-severity high --exclude-tag inventory ...`), applied before the
+//! report is built so filtered-out findings are neither shown nor scored.
+
+use super::report::Finding;
+use super::rule::{Confidence, Severity};
+
+/// Every criterion given must hold. Tags, rule IDs, kinds and users compare ignoring case.
+#[derive(Debug, Clone, Default)]
+pub struct FindingFilter {
+    pub min_severity: Option<Severity>,
+    pub min_confidence: Option<Confidence>,
+    /// The finding's rule has at least one of these tags
+    pub tags: Vec<String>,
+    /// The finding's rule has none of these tags
+    pub exclude_tags: Vec<String>,
+    pub rules: Vec<String>,
+    pub kinds: Vec<String>,
+    /// Findings without a user never match a user filter
+    pub users: Vec<String>,
+}
+
+impl FindingFilter {
+    pub fn matches(&self, f: &Finding) -> bool {
+        let any_of = |wanted: &[String], value: &str| wanted.iter().any(|w| w.eq_ignore_ascii_case(value));
+        let has_tag = |wanted: &[String]| f.tags.iter().any(|t| any_of(wanted, t));
+
+        self.min_severity.is_none_or(|min| f.severity >= min)
+            && self.min_confidence.is_none_or(|min| f.confidence >= min)
+            && (self.tags.is_empty() || has_tag(&self.tags))
+            && !has_tag(&self.exclude_tags)
+            && (self.rules.is_empty() || any_of(&self.rules, &f.rule_id))
+            && (self.kinds.is_empty() || any_of(&self.kinds, &f.artifact_kind))
+            && (self.users.is_empty() || f.user.as_deref().is_some_and(|u| any_of(&self.users, u)))
+    }
+
+    /// Split findings into (kept, number filtered out).
+    pub fn apply(&self, findings: Vec<Finding>) -> (Vec<Finding>, usize) {
+        let before = findings.len();
+        let kept: Vec<Finding> = findings.into_iter().filter(|f| self.matches(f)).collect();
+        let dropped = before - kept.len();
+        (kept, dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::report::tests::finding;
+
+    fn tagged(rule_id: &str, tags: &[&str], kind: &str, user: Option<&str>) -> Finding {
+        Finding {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            artifact_kind: kind.to_string(),
+            user: user.map(str::to_string),
+            ..finding(rule_id, Severity::Medium)
+        }
+    }
+
+    fn strings(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// Rule IDs of the findings `filter` keeps, and how many it drops.
+    fn kept(filter: &FindingFilter, findings: &[Finding]) -> (Vec<String>, usize) {
+        let (kept, dropped) = filter.apply(findings.to_vec());
+        (kept.into_iter().map(|f| f.rule_id).collect(), dropped)
+    }
+
+    #[test]
+    fn criteria_ignore_case_and_all_must_hold() {
+        let findings = [
+            tagged("WIN_RUNKEY_ANY", &["Persistence", "inventory"], "windows_run_key", Some("Bob")),
+            tagged("WIN_RUNKEY_SUSPICIOUS_PATH", &["persistence", "suspicious-path"], "windows_run_key", None),
+            tagged("WIN_SERVICE_SUSPICIOUS_PATH", &["suspicious-path"], "windows_service", Some("alice")),
+        ];
+
+        let by_tag = FindingFilter { tags: strings(&["PERSISTENCE"]), ..Default::default() };
+        assert_eq!(kept(&by_tag, &findings), (strings(&["WIN_RUNKEY_ANY", "WIN_RUNKEY_SUSPICIOUS_PATH"]), 1));
+
+        let by_rule = FindingFilter { rules: strings(&["win_runkey_any", "WIN_SERVICE_SUSPICIOUS_PATH"]), ..Default::default() };
+        assert_eq!(kept(&by_rule, &findings), (strings(&["WIN_RUNKEY_ANY", "WIN_SERVICE_SUSPICIOUS_PATH"]), 1));
+
+        let by_kind = FindingFilter { kinds: strings(&["Windows_Service"]), ..Default::default() };
+        assert_eq!(kept(&by_kind, &findings), (strings(&["WIN_SERVICE_SUSPICIOUS_PATH"]), 2));
+
+        // A finding without a user never matches a user filter
+        let by_user = FindingFilter { users: strings(&["bob", "ALICE"]), ..Default::default() };
+        assert_eq!(kept(&by_user, &findings), (strings(&["WIN_RUNKEY_ANY", "WIN_SERVICE_SUSPICIOUS_PATH"]), 1));
+
+        let combined = FindingFilter {
+            tags: strings(&["suspicious-path"]),
+            kinds: strings(&["windows_run_key"]),
+            ..Default::default()
+        };
+        assert_eq!(kept(&combined, &findings), (strings(&["WIN_RUNKEY_SUSPICIOUS_PATH"]), 2));
+
+        assert_eq!(kept(&FindingFilter::default(), &findings).1, 0);
+    }
+
+    #[test]
+    fn excluded_tags_beat_wanted_tags() {
+        let findings = [
+            tagged("ANY", &["persistence", "Inventory"], "linux_autostart", None),
+            tagged("SUSPICIOUS", &["persistence"], "linux_autostart", None),
+            tagged("UNTAGGED", &[], "linux_autostart", None),
+        ];
+        let filter = FindingFilter {
+            tags: strings(&["persistence"]),
+            exclude_tags: strings(&["inventory"]),
+            ..Default::default()
+        };
+        assert_eq!(kept(&filter, &findings), (strings(&["SUSPICIOUS"]), 2));
+
+        let exclude_only = FindingFilter { exclude_tags: strings(&["INVENTORY"]), ..Default::default() };
+        assert_eq!(kept(&exclude_only, &findings), (strings(&["SUSPICIOUS", "UNTAGGED"]), 1));
+    }
+
+    #[test]
+    fn minimum_severity_and_confidence() {
+        let findings = [
+            Finding { confidence: Confidence::High, ..finding("LOW_HIGH", Severity::Low) },
+            Finding { confidence: Confidence::Low, ..finding("HIGH_LOW", Severity::High) },
+            finding("MEDIUM_MEDIUM", Severity::Medium),
+        ];
+        let severity = FindingFilter { min_severity: Some(Severity::Medium), ..Default::default() };
+        assert_eq!(kept(&severity, &findings), (strings(&["HIGH_LOW", "MEDIUM_MEDIUM"]), 1));
+        let confidence = FindingFilter { min_confidence: Some(Confidence::Medium), ..Default::default() };
+        assert_eq!(kept(&confidence, &findings), (strings(&["LOW_HIGH", "MEDIUM_MEDIUM"]), 1));
+    }
+}
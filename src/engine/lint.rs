@@ -0,0 +1,261 @@
+//! Static checks over a rules directory (`persist-scan rules lint`).
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use super::matcher::{CompiledRule, RuleOrigin};
+use super::rule::Match;
+use super::{is_rule_file, load_lists, parse_os, parse_rule};
+use super::TargetOs;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct LintIssue {
+    pub level: Level,
+    pub file: String,
+    pub rule_id: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        };
+        match &self.rule_id {
+            Some(id) => write!(f, "{}: {}: {}: {}", level, self.file, id, self.message),
+            None => write!(f, "{}: {}: {}", level, self.file, self.message),
+        }
+    }
+}
+
+pub struct LintReport {
+    pub files: usize,
+    pub issues: Vec<LintIssue>,
+}
+
+impl LintReport {
+    pub fn errors(&self) -> usize {
+        self.issues.iter().filter(|i| i.level == Level::Error).count()
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.issues.iter().filter(|i| i.level == Level::Warning).count()
+    }
+}
+
+/// Check every rule file under `rules_dir`. `known_kinds` gives the artifact kinds the
+/// collectors of an OS produce.
+pub fn lint_rules(rules_dir: &str, known_kinds: impl Fn(TargetOs) -> &'static [&'static str]) -> Result<LintReport> {
+    let root = Path::new(rules_dir);
+    if !root.is_dir() {
+        anyhow::bail!("Rules directory not found: {}", root.display());
+    }
+
+    let mut issues = vec![];
+    let mut files = 0;
+    let mut seen_ids: BTreeMap<String, String> = BTreeMap::new();
+
+    // Lists first, so rules can be checked for undefined references
+    let mut lists = load_lists(None)?;
+    let lists_dir = root.join("lists");
+    if lists_dir.is_dir() {
+        for entry in walkdir::WalkDir::new(&lists_dir).min_depth(1).sort_by_file_name() {
+            let entry = entry?;
+            if !entry.file_type().is_file() || !is_rule_file(entry.path()) {
+                continue;
+            }
+            let file = entry.path().display().to_string();
+            if entry.depth() > 1 {
+                let message = "nested under lists/; never loaded".to_string();
+                issues.push(LintIssue { level: Level::Warning, file, rule_id: None, message });
+                continue;
+            }
+            let bytes = std::fs::read(entry.path())?;
+            if let Err(e) = lists.add_file(&bytes, &file, false) {
+                issues.push(LintIssue { level: Level::Error, file, rule_id: None, message: e.to_string() });
+            }
+        }
+    }
+
+    for entry in walkdir::WalkDir::new(root).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path();
+        if !entry.file_type().is_file() || !is_rule_file(path) || path.starts_with(&lists_dir) {
+            continue;
+        }
+        files += 1;
+        let file = path.display().to_string();
+        let mut issue = |level, rule_id: Option<&str>, message: String| {
+            issues.push(LintIssue { level, file: file.clone(), rule_id: rule_id.map(str::to_string), message });
+        };
+
+        // load_rules reads rules/<os>/ up to one level of subdirectories
+        let rel = path.strip_prefix(root).unwrap_or(path);
+        let dir_name = rel
+            .components()
+            .next()
+            .filter(|_| rel.components().count() > 1)
+            .map(|c| c.as_os_str().to_string_lossy().to_string());
+        let dir_os = dir_name.as_deref().and_then(|d| parse_os(d).ok().flatten());
+        if dir_os.is_none() {
+            issue(Level::Warning, None, "not under an OS directory (linux/, windows/, macos/); never loaded".to_string());
+        } else if rel.components().count() > 3 {
+            issue(Level::Warning, None, "nested too deep under the OS directory; never loaded".to_string());
+        }
+
+        let bytes = std::fs::read(path)?;
+        let rule = match parse_rule(&bytes, &lists) {
+            Ok(r) => r,
+            Err(e) if e.is::<serde_yaml::Error>() => {
+                issue(Level::Error, None, format!("invalid rule YAML: {}", e));
+                continue;
+            }
+            Err(e) => {
+                issue(Level::Error, None, e.to_string());
+                continue;
+            }
+        };
+        let id = Some(rule.id.as_str());
+
+        if let Some(first) = seen_ids.get(&rule.id) {
+            issue(Level::Error, id, format!("duplicate rule id (also defined in {})", first));
+        } else {
+            seen_ids.insert(rule.id.clone(), file.clone());
+        }
+
+        if let (Some(os), Some(dir)) = (dir_os, dir_name.as_deref()) {
+            if !rule.os.eq_ignore_ascii_case(dir) {
+                issue(Level::Error, id, format!("os '{}' does not match its directory '{}/'", rule.os, dir));
+            }
+            if !known_kinds(os).contains(&rule.check.kind.as_str()) {
+                issue(
+                    Level::Error,
+                    id,
+                    format!("check.kind '{}' is not produced by any {} collector", rule.check.kind, os.as_str()),
+                );
+            }
+        }
+
+        if rule.check.scope.is_some() {
+            issue(Level::Warning, id, "check.scope is not used by the matcher and has no effect".to_string());
+        }
+
+        let mut empty = vec![];
+        empty_needles(&rule.r#match, "match", &mut empty);
+        for (at, effect) in empty {
+            issue(Level::Error, id, format!("empty needle in {} ({})", at, effect));
+        }
+
+        let origin = RuleOrigin::File { path: file.clone(), overrides: false };
+        if let Err(e) = CompiledRule::compile(rule.clone(), origin) {
+            // Skip the outer context: it repeats the rule ID and file
+            let cause: Vec<String> = e.chain().skip(1).map(|c| c.to_string()).collect();
+            issue(Level::Error, id, cause.join(": "));
+        }
+    }
+
+    Ok(LintReport { files, issues })
+}
+
+/// Locations of empty strings in needle lists and condition operands, with what they do.
+fn empty_needles(m: &Match, at: &str, out: &mut Vec<(String, &'static str)>) {
+    for (name, list, effect) in [
+        ("any_path_contains", &m.any_path_contains, "silently skipped"),
+        ("any_path_prefix", &m.any_path_prefix, "silently skipped"),
+        ("any_command_contains", &m.any_command_contains, "silently skipped"),
+        ("regex_command", &m.regex_command, "matches every artifact"),
+        ("regex_path", &m.regex_path, "matches every artifact"),
+    ] {
+        if list.iter().any(|n| n.is_empty()) {
+            out.push((format!("{}.{}", at, name), effect));
+        }
+    }
+    if m.operands().any(|o| o.is_empty()) {
+        out.push((format!("{}.value", at), "an empty operand matches trivially"));
+    }
+    for (i, child) in m.all.iter().enumerate() {
+        empty_needles(child, &format!("{}.all[{}]", at, i), out);
+    }
+    for (i, child) in m.any.iter().enumerate() {
+        empty_needles(child, &format!("{}.any[{}]", at, i), out);
+    }
+    if let Some(not) = &m.not {
+        empty_needles(not, &format!("{}.not", at), out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(id: &str, os: &str, kind: &str, extra: &str) -> String {
+        format!(
+            "id: {}\ntitle: t\nos: {}\nseverity: low\nconfidence: low\ntags: []\nrationale: r\ncheck:\n  kind: {}\n{}",
+            id, os, kind, extra
+        )
+    }
+
+    #[test]
+    fn reports_each_problem_once() {
+        let dir = std::env::temp_dir().join(format!("persist-scan-lint-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let files = [
+            ("linux/ok.yml", rule("LNX_OK", "linux", "linux_autostart", "match:\n  any_path_prefix: $my_dirs\n")),
+            ("linux/dup.yml", rule("LNX_OK", "linux", "linux_autostart", "match: {}\n")),
+            ("linux/wrong_os.yml", rule("LNX_WIN", "windows", "windows_run_key", "match: {}\n")),
+            ("linux/kind.yml", rule("LNX_KIND", "linux", "linux_systemd", "match: {}\n")),
+            ("linux/key.yml", rule("LNX_KEY", "linux", "linux_autostart", "match: {}\nseverity_note: x\n")),
+            ("linux/empty.yml", rule("LNX_EMPTY", "linux", "linux_autostart", "match:\n  any_path_contains: ['']\n  regex_path: ['']\n")),
+            ("linux/regex.yml", rule("LNX_REGEX", "linux", "linux_autostart", "match:\n  regex_command: ['(']\n")),
+            ("linux/scope.yml", rule("LNX_SCOPE", "linux", "linux_autostart", "  scope: user\nmatch: {}\n")),
+            ("linux/list.yml", rule("LNX_LIST", "linux", "linux_autostart", "match:\n  any_path_prefix: $no_such_list\n")),
+            ("linux/no_field.yml", rule("LNX_NO_FIELD", "linux", "linux_autostart", "match:\n  all:\n    - { op: equals, value: x }\n")),
+            ("linux/a/b/deep.yml", rule("LNX_DEEP", "linux", "linux_autostart", "match: {}\n")),
+            ("loose.yml", rule("LNX_LOOSE", "linux", "linux_autostart", "match: {}\n")),
+            ("lists/extra.yml", "my_dirs: [/opt/x/]\n".to_string()),
+            ("lists/nested/more.yml", "more_dirs: [/opt/y/]\n".to_string()),
+        ];
+        for (rel, text) in &files {
+            let path = dir.join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, text).unwrap();
+        }
+
+        let report = lint_rules(dir.to_str().unwrap(), crate::collectors::artifact_kinds).unwrap();
+        let prefix = format!("{}/", dir.display());
+        let found: Vec<String> =
+            report.issues.iter().map(|i| i.to_string().replace(&prefix, "").lines().next().unwrap().to_string()).collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            found,
+            [
+                "warning: lists/nested/more.yml: nested under lists/; never loaded",
+                "warning: linux/a/b/deep.yml: nested too deep under the OS directory; never loaded",
+                "error: linux/empty.yml: LNX_EMPTY: empty needle in match.any_path_contains (silently skipped)",
+                "error: linux/empty.yml: LNX_EMPTY: empty needle in match.regex_path (matches every artifact)",
+                "error: linux/key.yml: invalid rule YAML: unknown field `severity_note`, expected one of `id`, `title`, `os`, \
+                 `severity`, `confidence`, `tags`, `attack`, `rationale`, `check`, `match`, `tests` at line 11 column 1",
+                "error: linux/kind.yml: LNX_KIND: check.kind 'linux_systemd' is not produced by any linux collector",
+                "error: linux/list.yml: undefined list or variable: $no_such_list",
+                "error: linux/no_field.yml: LNX_NO_FIELD: op, value, values and case_sensitive need a field",
+                "error: linux/ok.yml: LNX_OK: duplicate rule id (also defined in linux/dup.yml)",
+                "error: linux/regex.yml: LNX_REGEX: regex_command: Invalid regex: (: regex parse error:",
+                "warning: linux/scope.yml: LNX_SCOPE: check.scope is not used by the matcher and has no effect",
+                "error: linux/wrong_os.yml: LNX_WIN: os 'windows' does not match its directory 'linux/'",
+                "error: linux/wrong_os.yml: LNX_WIN: check.kind 'windows_run_key' is not produced by any linux collector",
+                "warning: loose.yml: not under an OS directory (linux/, windows/, macos/); never loaded",
+            ]
+        );
+        assert_eq!((report.files, report.errors(), report.warnings()), (12, 10, 4));
+    }
+}
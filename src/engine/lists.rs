@@ -0,0 +1,219 @@
+//! Named lists and variables shared between rules (`rules/lists/*.yml`). A rule's match block
+//! refers to them as `$name`: a list splices into a list of needles, a variable (a single
+//! string) stands in for one value. `$$name` is the literal string `$name`.
+//!
+//! Only a value that is exactly `$name` is a reference, so `$HOME/.profile` or `C$\` are
+//! left alone. Regex fields are never substituted: there `$` is an anchor.
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+
+/// Match keys holding regexes (`content.regex` included)
+const REGEX_KEYS: &[&str] = &["regex_command", "regex_path", "regex"];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ListValue {
+    Variable(String),
+    List(Vec<String>),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Lists {
+    values: BTreeMap<String, Definition>,
+}
+
+#[derive(Debug, Clone)]
+struct Definition {
+    value: ListValue,
+    /// File it was defined in
+    origin: String,
+    built_in: bool,
+}
+
+impl Lists {
+    /// Add the definitions in one lists file. A name may be defined once per source
+    /// (`built_in` false: --rules-dir), and a --rules-dir definition replaces a built-in one.
+    pub fn add_file(&mut self, bytes: &[u8], origin: &str, built_in: bool) -> Result<()> {
+        let defs: BTreeMap<String, ListValue> = serde_yaml::from_slice(bytes).map_err(|e| {
+            anyhow!("expected `name: value` or `name: [values]` entries of strings: {}", e)
+        })?;
+        for (name, value) in defs {
+            if !is_name(&name) {
+                return Err(anyhow!("invalid list name '{}' (use letters, digits and _)", name));
+            }
+            if let Some(first) = self.values.get(&name).filter(|d| d.built_in == built_in) {
+                return Err(anyhow!("list ${} is already defined in {}", name, first.origin));
+            }
+            self.values.insert(name, Definition { value, origin: origin.to_string(), built_in });
+        }
+        Ok(())
+    }
+
+    /// Replace `$name` references in the rule's match block. Returns whether anything was
+    /// substituted; every undefined name is reported in one error.
+    pub fn resolve(&self, doc: &mut Value) -> Result<bool> {
+        let Some(m) = doc.get_mut("match") else { return Ok(false) };
+        let mut undefined = vec![];
+        let changed = self.resolve_value(m, &mut undefined);
+        if !undefined.is_empty() {
+            undefined.sort();
+            undefined.dedup();
+            let names: Vec<String> = undefined.iter().map(|n| format!("${}", n)).collect();
+            return Err(anyhow!("undefined list or variable: {}", names.join(", ")));
+        }
+        Ok(changed)
+    }
+
+    fn resolve_value(&self, v: &mut Value, undefined: &mut Vec<String>) -> bool {
+        match v {
+            Value::String(s) => match reference(s) {
+                Some(Ref::Escaped(lit)) => {
+                    *s = lit;
+                    true
+                }
+                Some(Ref::Name(name)) => match self.values.get(&name).map(|d| &d.value) {
+                    Some(ListValue::Variable(val)) => {
+                        *s = val.clone();
+                        true
+                    }
+                    Some(ListValue::List(items)) => {
+                        *v = Value::Sequence(items.iter().cloned().map(Value::String).collect());
+                        true
+                    }
+                    None => {
+                        undefined.push(name);
+                        false
+                    }
+                },
+                None => false,
+            },
+            Value::Sequence(items) => {
+                let mut changed = false;
+                let mut out = Vec::with_capacity(items.len());
+                for mut item in items.drain(..) {
+                    let was_reference = matches!(&item, Value::String(_));
+                    let item_changed = self.resolve_value(&mut item, undefined);
+                    changed |= item_changed;
+                    // A list referenced as a list item is spliced in
+                    match item {
+                        Value::Sequence(inner) if was_reference && item_changed => out.extend(inner),
+                        other => out.push(other),
+                    }
+                }
+                *items = out;
+                changed
+            }
+            Value::Mapping(map) => {
+                // `value` / `values` of an `op: regex` condition are patterns too
+                let regex_op = map.get("op").and_then(Value::as_str) == Some("regex");
+                let mut changed = false;
+                for (key, child) in map.iter_mut() {
+                    let key = key.as_str().unwrap_or("");
+                    if REGEX_KEYS.contains(&key) || (regex_op && (key == "value" || key == "values")) {
+                        continue;
+                    }
+                    changed |= self.resolve_value(child, undefined);
+                }
+                changed
+            }
+            _ => false,
+        }
+    }
+}
+
+enum Ref {
+    Name(String),
+    Escaped(String),
+}
+
+fn reference(s: &str) -> Option<Ref> {
+    if let Some(rest) = s.strip_prefix("$$") {
+        return is_name(rest).then(|| Ref::Escaped(format!("${}", rest)));
+    }
+    let name = s.strip_prefix('$')?;
+    is_name(name).then(|| Ref::Name(name.to_string()))
+}
+
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LISTS: &str = r#"
+tmp_dirs: ["/tmp/", "/dev/shm/"]
+shell: /bin/sh
+"#;
+
+    fn resolve(match_yaml: &str) -> Result<Value> {
+        let mut lists = Lists::default();
+        lists.add_file(LISTS.as_bytes(), "test.yml", true).unwrap();
+        let mut doc: Value = serde_yaml::from_str(&format!("match:\n{}", match_yaml)).unwrap();
+        lists.resolve(&mut doc)?;
+        Ok(doc["match"].clone())
+    }
+
+    fn strings(v: &Value) -> Vec<&str> {
+        v.as_sequence().unwrap().iter().map(|s| s.as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn whole_value_references_are_substituted() {
+        let m = resolve(
+            r#"
+  any_path_prefix: $tmp_dirs
+  any_command_contains: [$tmp_dirs, /var/tmp/, $shell, $$shell]
+  all:
+    - field: command
+      op: contains
+      value: $shell
+"#,
+        )
+        .unwrap();
+        assert_eq!(strings(&m["any_path_prefix"]), ["/tmp/", "/dev/shm/"]);
+        assert_eq!(strings(&m["any_command_contains"]), ["/tmp/", "/dev/shm/", "/var/tmp/", "/bin/sh", "$shell"]);
+        assert_eq!(m["all"][0]["value"].as_str(), Some("/bin/sh"));
+    }
+
+    #[test]
+    fn dollars_inside_values_are_literal() {
+        let m = resolve(r#"  any_command_contains: ["$HOME/.profile", 'C$\', "$", "cost: 5$", "$tmp_dirs/x"]"#).unwrap();
+        assert_eq!(strings(&m["any_command_contains"]), ["$HOME/.profile", r"C$\", "$", "cost: 5$", "$tmp_dirs/x"]);
+    }
+
+    #[test]
+    fn regex_fields_are_left_as_written() {
+        let m = resolve(
+            r#"
+  regex_command: ["^$shell", "$shell"]
+  regex_path: ["$tmp_dirs"]
+  any:
+    - field: name
+      op: regex
+      values: [$undefined_anchor, "^x$"]
+  content:
+    regex: ["$shell"]
+    strings: [$shell]
+"#,
+        )
+        .unwrap();
+        assert_eq!(strings(&m["regex_command"]), ["^$shell", "$shell"]);
+        assert_eq!(strings(&m["regex_path"]), ["$tmp_dirs"]);
+        assert_eq!(strings(&m["any"][0]["values"]), ["$undefined_anchor", "^x$"]);
+        assert_eq!(strings(&m["content"]["regex"]), ["$shell"]);
+        assert_eq!(strings(&m["content"]["strings"]), ["/bin/sh"]);
+    }
+
+    #[test]
+    fn undefined_names_are_reported_together() {
+        let err = resolve("  any_path_prefix: [$nope, $tmp_dirs]\n  any_command_contains: [$also_nope, $nope]").unwrap_err();
+        assert_eq!(err.to_string(), "undefined list or variable: $also_nope, $nope");
+    }
+}
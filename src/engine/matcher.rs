@@ -1,55 +1,373 @@
-use anyhow::Result;
-use regex::Regex;
+use anyhow::{anyhow, Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use regex::{RegexBuilder, RegexSet, RegexSetBuilder};
 
+use super::attack;
+use super::content::{ArtifactFiles, CompiledContent};
 use super::Artifact;
-use super::rule::Rule;
+use super::rule::{FieldOp, Match, Rule};
 
-pub fn rule_matches(rule: &Rule, art: &Artifact) -> Result<bool> {
-    if rule.check.kind != art.kind {
-        return Ok(false);
+/// A rule with its match tree compiled once at load time: regexes and globs are built
+/// into sets and `contains` needles are lowercased, so matching does no allocation per
+/// pattern. Invalid patterns are rejected here rather than on the first matching artifact.
+#[derive(Debug, Clone)]
+pub struct CompiledRule {
+    pub rule: Rule,
+    pub origin: RuleOrigin,
+    matcher: CompiledMatch,
+}
+
+/// Where a rule was loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleOrigin {
+    /// The rule pack compiled into the binary; `path` is relative to the shipped rules/ directory
+    BuiltIn { path: String },
+    /// A file under `--rules-dir`; `overrides` is set when it replaced a built-in rule with the same ID
+    File { path: String, overrides: bool },
+}
+
+impl std::fmt::Display for RuleOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleOrigin::BuiltIn { path } => write!(f, "built-in:{}", path),
+            RuleOrigin::File { path, .. } => write!(f, "{}", path),
+        }
     }
+}
 
-    let m = &rule.r#match;
-    let has_criteria = !m.any_path_contains.is_empty()
-        || !m.any_path_prefix.is_empty()
-        || !m.any_command_contains.is_empty()
-        || !m.regex_command.is_empty()
-        || !m.regex_path.is_empty();
+#[derive(Debug, Clone)]
+struct CompiledMatch {
+    /// Whether any flat list is present (the lists are ORed together)
+    flat: bool,
+    path_contains: Vec<String>,
+    path_prefix: Vec<String>,
+    command_contains: Vec<String>,
+    regex_command: Option<RegexSet>,
+    regex_path: Option<RegexSet>,
+    condition: Option<Condition>,
+    content: Option<CompiledContent>,
+    all: Vec<CompiledMatch>,
+    any: Vec<CompiledMatch>,
+    not: Option<Box<CompiledMatch>>,
+}
 
-    if !has_criteria {
-        return Ok(true);
+#[derive(Debug, Clone)]
+struct Condition {
+    field: String,
+    op: FieldOp,
+    case_sensitive: bool,
+    /// Operands, lowercased unless case-sensitive (string ops)
+    operands: Vec<String>,
+    regex: Option<RegexSet>,
+    glob: Option<GlobSet>,
+}
+
+impl CompiledRule {
+    pub fn compile(rule: Rule, origin: RuleOrigin) -> Result<Self> {
+        attack::validate(&rule.attack)
+            .with_context(|| format!("Invalid attack mapping in rule {} ({})", rule.id, origin))?;
+        let matcher = compile_block(&rule.r#match)
+            .with_context(|| format!("Invalid match criteria in rule {} ({})", rule.id, origin))?;
+        Ok(Self { rule, origin, matcher })
     }
+}
+
+/// `files` reads the files the artifact refers to, for `content:` conditions.
+pub fn rule_matches(rule: &CompiledRule, art: &Artifact, files: &ArtifactFiles) -> bool {
+    rule.rule.check.kind == art.kind && rule.matcher.matches(art, files)
+}
 
-    let path = art.path.as_deref().unwrap_or("");
-    let cmd = art.command.as_deref().unwrap_or("");
+fn compile_block(m: &Match) -> Result<CompiledMatch> {
+    let lower = |v: &[String]| v.iter().filter(|n| !n.is_empty()).map(|n| n.to_lowercase()).collect();
+
+    let condition = match &m.field {
+        Some(field) => Some(compile_condition(m, field)?),
+        // Without a field these would be dropped and the block would match everything
+        None if m.has_condition_parts() => anyhow::bail!("op, value, values and case_sensitive need a field"),
+        None => None,
+    };
+    let not = match &m.not {
+        Some(n) => Some(Box::new(compile_block(n)?)),
+        None => None,
+    };
+
+    Ok(CompiledMatch {
+        flat: m.has_flat_criteria(),
+        path_contains: lower(&m.any_path_contains),
+        path_prefix: m.any_path_prefix.iter().filter(|p| !p.is_empty()).cloned().collect(),
+        command_contains: lower(&m.any_command_contains),
+        regex_command: regex_set(&m.regex_command, false).context("regex_command")?,
+        regex_path: regex_set(&m.regex_path, false).context("regex_path")?,
+        condition,
+        content: m.content.as_ref().map(CompiledContent::compile).transpose().context("content")?,
+        all: m.all.iter().map(compile_block).collect::<Result<_>>()?,
+        any: m.any.iter().map(compile_block).collect::<Result<_>>()?,
+        not,
+    })
+}
 
-    for needle in &m.any_path_contains {
-        if !needle.is_empty() && path.to_lowercase().contains(&needle.to_lowercase()) {
-            return Ok(true);
+fn compile_condition(m: &Match, field: &str) -> Result<Condition> {
+    let op = m.op.ok_or_else(|| anyhow!("Condition on field '{}' has no op", field))?;
+    let operands: Vec<String> = m.operands().cloned().collect();
+    if op == FieldOp::Exists {
+        if !operands.is_empty() {
+            anyhow::bail!("Condition on field '{}' with op exists takes no value", field);
         }
+    } else if operands.is_empty() {
+        anyhow::bail!("Condition on field '{}' has no value or values", field);
+    } else if op == FieldOp::InList && m.value.is_some() {
+        anyhow::bail!("Condition on field '{}' with op in_list takes values, not value (use equals for one value)", field);
     }
-    for pref in &m.any_path_prefix {
-        if !pref.is_empty() && path.starts_with(pref) {
-            return Ok(true);
+
+    let mut cond = Condition {
+        field: field.to_string(),
+        op,
+        case_sensitive: m.case_sensitive,
+        operands: vec![],
+        regex: None,
+        glob: None,
+    };
+    match op {
+        FieldOp::Regex => {
+            cond.regex = regex_set(&operands, !m.case_sensitive).with_context(|| format!("field '{}'", field))?;
+        }
+        FieldOp::Glob => {
+            let mut set = GlobSetBuilder::new();
+            for pat in &operands {
+                // Backslashes are literal so Windows paths can be globbed as written
+                let glob = GlobBuilder::new(pat)
+                    .case_insensitive(!m.case_sensitive)
+                    .backslash_escape(false)
+                    .build()
+                    .with_context(|| format!("Invalid glob for field '{}': {}", field, pat))?;
+                set.add(glob);
+            }
+            cond.glob = Some(set.build()?);
         }
+        _ => {
+            cond.operands = operands
+                .into_iter()
+                .map(|o| if m.case_sensitive { o } else { o.to_lowercase() })
+                .collect();
+        }
+    }
+    Ok(cond)
+}
+
+fn regex_set(patterns: &[String], case_insensitive: bool) -> Result<Option<RegexSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
     }
-    for needle in &m.any_command_contains {
-        if !needle.is_empty() && cmd.to_lowercase().contains(&needle.to_lowercase()) {
-            return Ok(true);
+    // Built one by one first so the error names the offending pattern
+    for pat in patterns {
+        RegexBuilder::new(pat)
+            .case_insensitive(case_insensitive)
+            .build()
+            .with_context(|| format!("Invalid regex: {}", pat))?;
+    }
+    let set = RegexSetBuilder::new(patterns).case_insensitive(case_insensitive).build()?;
+    Ok(Some(set))
+}
+
+impl CompiledMatch {
+    /// A block holds when its flat lists (if any), its field condition, its content condition
+    /// and each of all/any/not hold. File contents are only read once everything else holds.
+    fn matches(&self, art: &Artifact, files: &ArtifactFiles) -> bool {
+        (!self.flat || self.flat_matches(art))
+            && self.condition.as_ref().is_none_or(|c| c.matches(art))
+            && self.all.iter().all(|b| b.matches(art, files))
+            && (self.any.is_empty() || self.any.iter().any(|b| b.matches(art, files)))
+            && self.not.as_ref().is_none_or(|b| !b.matches(art, files))
+            && self.content.as_ref().is_none_or(|c| c.matches(files))
+    }
+
+    /// The flat lists are ORed together, as they always have been.
+    fn flat_matches(&self, art: &Artifact) -> bool {
+        let path = art.path.as_deref().unwrap_or("");
+        let cmd = art.command.as_deref().unwrap_or("");
+
+        if !self.path_contains.is_empty() {
+            let path = path.to_lowercase();
+            if self.path_contains.iter().any(|n| path.contains(n.as_str())) {
+                return true;
+            }
         }
+        if self.path_prefix.iter().any(|p| path.starts_with(p.as_str())) {
+            return true;
+        }
+        if !self.command_contains.is_empty() {
+            let cmd = cmd.to_lowercase();
+            if self.command_contains.iter().any(|n| cmd.contains(n.as_str())) {
+                return true;
+            }
+        }
+        self.regex_command.as_ref().is_some_and(|s| s.is_match(cmd))
+            || self.regex_path.as_ref().is_some_and(|s| s.is_match(path))
     }
-    for pat in &m.regex_command {
-        let re = Regex::new(pat)?;
-        if re.is_match(cmd) {
-            return Ok(true);
+}
+
+impl Condition {
+    /// A field the artifact does not have never matches.
+    fn matches(&self, art: &Artifact) -> bool {
+        let Some(actual) = art.field(&self.field) else { return false };
+        if self.op == FieldOp::Exists {
+            return true;
+        }
+        if let Some(set) = &self.regex {
+            return set.is_match(&actual);
         }
+        if let Some(set) = &self.glob {
+            return set.is_match(actual.as_ref());
+        }
+
+        let actual = if self.case_sensitive { actual.to_string() } else { actual.to_lowercase() };
+        self.operands.iter().any(|o| match self.op {
+            FieldOp::Contains => actual.contains(o.as_str()),
+            FieldOp::Prefix => actual.starts_with(o.as_str()),
+            FieldOp::Suffix => actual.ends_with(o.as_str()),
+            _ => actual == *o,
+        })
     }
-    for pat in &m.regex_path {
-        let re = Regex::new(pat)?;
-        if re.is_match(path) {
-            return Ok(true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::content::FileSource;
+    use std::collections::BTreeMap;
+
+    struct NoFiles;
+
+    impl FileSource for NoFiles {
+        fn file_size(&self, _path: &str) -> Option<u64> {
+            None
+        }
+
+        fn read_file(&self, _path: &str) -> Option<Vec<u8>> {
+            None
+        }
+    }
+
+    fn compile(match_yaml: &str) -> Result<CompiledRule> {
+        let indented: String = match_yaml.lines().map(|l| format!("  {}\n", l)).collect();
+        let yaml = format!(
+            "id: TEST_RULE\ntitle: t\nos: linux\nseverity: low\nconfidence: low\ntags: []\nrationale: r\n\
+             check:\n  kind: test\nmatch:\n{}",
+            indented
+        );
+        let rule: Rule = serde_yaml::from_str(&yaml).unwrap();
+        CompiledRule::compile(rule, RuleOrigin::BuiltIn { path: "test.yml".to_string() })
+    }
+
+    fn artifact(name: &str, path: &str, attrs: &[(&str, &str)]) -> Artifact {
+        Artifact {
+            kind: "test".to_string(),
+            source: "/etc/crontab".to_string(),
+            name: name.to_string(),
+            user: None,
+            command: Some(format!("{} --run", path)),
+            path: Some(path.to_string()),
+            raw: None,
+            attrs: attrs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<BTreeMap<_, _>>(),
         }
     }
 
-    Ok(false)
+    fn matches(rule: &CompiledRule, art: &Artifact) -> bool {
+        rule_matches(rule, art, &ArtifactFiles::new(&NoFiles, art))
+    }
+
+    #[test]
+    fn not_inside_any() {
+        let rule = compile(
+            "any:\n  - field: name\n    op: equals\n    value: updater\n  - not:\n      any_path_prefix: [\"/usr/\"]",
+        )
+        .unwrap();
+        assert!(matches(&rule, &artifact("updater", "/usr/bin/updater", &[])));
+        assert!(matches(&rule, &artifact("other", "/tmp/x", &[])));
+        assert!(!matches(&rule, &artifact("other", "/usr/bin/x", &[])));
+    }
+
+    #[test]
+    fn all_with_an_exception() {
+        let rule = compile("all:\n  - any_path_prefix: [\"/home/\"]\n  - not:\n      any_path_contains: [\"/.local/bin/\"]")
+            .unwrap();
+        assert!(matches(&rule, &artifact("x", "/home/bob/.cache/x", &[])));
+        assert!(!matches(&rule, &artifact("x", "/home/bob/.local/bin/x", &[])));
+        assert!(!matches(&rule, &artifact("x", "/opt/x", &[])));
+        // The rule only applies to its check.kind
+        let mut other = artifact("x", "/home/bob/x", &[]);
+        other.kind = "other".to_string();
+        assert!(!matches(&rule, &other));
+    }
+
+    #[test]
+    fn field_ops() {
+        let art = artifact("sync-job", r"C:\Users\bob\AppData\sync.exe", &[("start", "auto")]);
+        let cases = [
+            ("field: name\nop: equals\nvalue: sync-job", true),
+            ("field: name\nop: equals\nvalue: sync", false),
+            ("field: command\nop: contains\nvalue: --run", true),
+            ("field: source\nop: prefix\nvalue: /etc/", true),
+            ("field: path\nop: suffix\nvalue: .exe", true),
+            ("field: attrs.start\nop: in_list\nvalues: [boot, auto]", true),
+            ("field: start\nop: in_list\nvalues: [boot, system]", false),
+            ("field: name\nop: regex\nvalue: '^sync-\\w+$'", true),
+            ("field: name\nop: regex\nvalues: ['^x', 'job$']", true),
+            ("field: path\nop: glob\nvalue: 'C:\\Users\\*\\AppData\\*.exe'", true),
+            ("field: path\nop: glob\nvalue: 'C:\\Windows\\*'", false),
+            ("field: value_path\nop: equals\nvalue: '/etc/crontab\\sync-job'", true),
+            ("field: attrs.start\nop: exists", true),
+            ("field: attrs.label\nop: exists", false),
+            ("field: user\nop: exists", false),
+            // A missing field never matches, whatever the op
+            ("field: user\nop: regex\nvalue: '.*'", false),
+        ];
+        for (yaml, want) in cases {
+            let rule = compile(yaml).unwrap_or_else(|e| panic!("{}: {:#}", yaml, e));
+            assert_eq!(matches(&rule, &art), want, "{}", yaml);
+        }
+    }
+
+    #[test]
+    fn conditions_ignore_case_unless_asked() {
+        let art = artifact("Updater", r"C:\Program Files\App\App.EXE", &[]);
+        let cases = [
+            ("field: name\nop: equals\nvalue: UPDATER", true),
+            ("field: path\nop: contains\nvalue: program files", true),
+            ("field: path\nop: regex\nvalue: 'app\\.exe$'", true),
+            ("field: path\nop: glob\nvalue: 'c:\\program files\\*.exe'", true),
+            ("field: name\nop: equals\nvalue: UPDATER\ncase_sensitive: true", false),
+            ("field: name\nop: equals\nvalue: Updater\ncase_sensitive: true", true),
+            ("field: path\nop: regex\nvalue: 'app\\.exe$'\ncase_sensitive: true", false),
+            ("field: path\nop: glob\nvalue: 'c:\\program files\\*.exe'\ncase_sensitive: true", false),
+            // The flat lists always ignore case for contains, never for prefix
+            ("any_path_contains: ['PROGRAM FILES']", true),
+            ("any_path_prefix: ['c:\\']", false),
+        ];
+        for (yaml, want) in cases {
+            let rule = compile(yaml).unwrap();
+            assert_eq!(matches(&rule, &art), want, "{}", yaml);
+        }
+    }
+
+    #[test]
+    fn invalid_patterns_fail_at_load_time() {
+        let cases = [
+            ("field: name\nop: regex\nvalue: '(unclosed'", "Invalid regex: (unclosed"),
+            ("regex_command: ['[a-']", "Invalid regex: [a-"),
+            ("any:\n  - field: path\n    op: glob\n    value: '{a,b'", "Invalid glob for field 'path'"),
+            ("field: name\nvalue: x", "has no op"),
+            ("field: name\nop: equals", "has no value"),
+            ("field: name\nop: exists\nvalue: x", "takes no value"),
+            ("field: name\nop: in_list\nvalue: x", "takes values, not value"),
+            ("op: equals\nvalue: x", "need a field"),
+            ("any:\n  - values: [a, b]", "need a field"),
+            ("not:\n  case_sensitive: true", "need a field"),
+        ];
+        for (yaml, want) in cases {
+            let err = format!("{:#}", compile(yaml).expect_err(yaml));
+            assert!(err.contains("rule TEST_RULE (built-in:test.yml)"), "{}", err);
+            assert!(err.contains(want), "{}: {}", yaml, err);
+        }
+    }
 }
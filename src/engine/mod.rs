@@ -1,11 +1,26 @@
 use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 
+pub mod attack;
+pub mod content;
+pub mod filter;
+pub mod lint;
+pub mod lists;
 pub mod matcher;
 pub mod report;
 pub mod rule;
+pub mod rule_tests;
+pub mod scoring;
+pub mod sigma;
+pub mod suppress;
 
-use matcher::rule_matches;
+use content::{ArtifactFiles, FileSource};
+use include_dir::{include_dir, Dir};
+use lists::Lists;
+use matcher::{rule_matches, CompiledRule, RuleOrigin};
+use rayon::prelude::*;
 use report::{Finding, Report};
 use rule::Rule;
 
@@ -13,84 +28,260 @@ use rule::Rule;
 pub enum TargetOs {
     Linux,
     Windows,
+    MacOs,
 }
 
-pub fn detect_os(arg: &str) -> Result<TargetOs> {
-    match arg.to_lowercase().as_str() {
-        "auto" => {
-            if cfg!(windows) {
-                Ok(TargetOs::Windows)
-            } else if cfg!(unix) {
-                Ok(TargetOs::Linux)
-            } else {
-                Err(anyhow!("Unsupported host OS for auto-detect"))
-            }
+impl TargetOs {
+    pub const ALL: [TargetOs; 3] = [TargetOs::Linux, TargetOs::Windows, TargetOs::MacOs];
+
+    /// Lowercase name, as used for `--os`, rule `os:` fields and rule subdirectories.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TargetOs::Linux => "linux",
+            TargetOs::Windows => "windows",
+            TargetOs::MacOs => "macos",
         }
-        "linux" => Ok(TargetOs::Linux),
-        "windows" => Ok(TargetOs::Windows),
-        _ => Err(anyhow!("Invalid --os value: {} (use auto|linux|windows)", arg)),
     }
 }
 
-pub fn load_rules(rules_dir: &str, os: TargetOs) -> Result<Vec<Rule>> {
-    let subdir = match os {
-        TargetOs::Linux => "linux",
-        TargetOs::Windows => "windows",
-    };
-    let dir = std::path::Path::new(rules_dir).join(subdir);
-    let mut out = vec![];
+/// Parse `--os`; None means auto-detect (the host OS, or per root for offline scans).
+pub fn parse_os(arg: &str) -> Result<Option<TargetOs>> {
+    match arg.to_lowercase().as_str() {
+        "auto" => Ok(None),
+        "linux" => Ok(Some(TargetOs::Linux)),
+        "windows" => Ok(Some(TargetOs::Windows)),
+        "macos" => Ok(Some(TargetOs::MacOs)),
+        _ => Err(anyhow!("Invalid --os value: {} (use auto|linux|windows|macos)", arg)),
+    }
+}
 
-    if !dir.exists() {
-        return Err(anyhow!("Rules directory not found: {}", dir.display()));
+/// Parse a severity option such as `--fail-on`.
+pub fn parse_severity(flag: &str, arg: &str) -> Result<rule::Severity> {
+    match arg.to_lowercase().as_str() {
+        "low" => Ok(rule::Severity::Low),
+        "medium" => Ok(rule::Severity::Medium),
+        "high" => Ok(rule::Severity::High),
+        _ => Err(anyhow!("Invalid {} value: {} (use low|medium|high)", flag, arg)),
     }
+}
 
-    for entry in walkdir::WalkDir::new(&dir).max_depth(2) {
-        let entry = entry?;
-        if !entry.file_type().is_file() {
-            continue;
+/// Parse a confidence option such as `--min-confidence`.
+pub fn parse_confidence(flag: &str, arg: &str) -> Result<rule::Confidence> {
+    match arg.to_lowercase().as_str() {
+        "low" => Ok(rule::Confidence::Low),
+        "medium" => Ok(rule::Confidence::Medium),
+        "high" => Ok(rule::Confidence::High),
+        _ => Err(anyhow!("Invalid {} value: {} (use low|medium|high)", flag, arg)),
+    }
+}
+
+/// Parse `--group-by`.
+pub fn parse_group_by(arg: &str) -> Result<report::GroupBy> {
+    match arg.to_lowercase().as_str() {
+        "rule" => Ok(report::GroupBy::Rule),
+        "kind" => Ok(report::GroupBy::Kind),
+        "source" => Ok(report::GroupBy::Source),
+        "path" => Ok(report::GroupBy::Path),
+        _ => Err(anyhow!("Invalid --group-by value: {} (use rule|kind|source|path)", arg)),
+    }
+}
+
+pub fn host_os() -> Result<TargetOs> {
+    if cfg!(windows) {
+        Ok(TargetOs::Windows)
+    } else if cfg!(target_os = "macos") {
+        Ok(TargetOs::MacOs)
+    } else if cfg!(unix) {
+        Ok(TargetOs::Linux)
+    } else {
+        Err(anyhow!("Unsupported host OS for auto-detect"))
+    }
+}
+
+/// The shipped rules/ directory, compiled into the binary so scans work from any directory.
+static BUILTIN_RULES: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/rules");
+
+/// Load and compile every rule for `os`: the built-in pack, then the rules under
+/// `<rules_dir>/<os>/` (if given), which are added or replace built-in rules with the same ID.
+/// Invalid YAML or patterns fail here, naming the file and rule.
+pub fn load_rules(rules_dir: Option<&str>, os: TargetOs) -> Result<Vec<CompiledRule>> {
+    let lists = load_lists(rules_dir)?;
+    let mut by_id: BTreeMap<String, CompiledRule> = BTreeMap::new();
+
+    for (path, bytes) in builtin_files(os.as_str(), 2) {
+        let rule = parse_rule(&bytes, &lists).with_context(|| format!("Failed parsing YAML built-in:{}", path))?;
+        if let Some(first) = by_id.get(&rule.id) {
+            return Err(anyhow!("Duplicate rule id {} in {} and built-in:{}", rule.id, first.origin, path));
         }
+        let compiled = CompiledRule::compile(rule, RuleOrigin::BuiltIn { path })?;
+        by_id.insert(compiled.rule.id.clone(), compiled);
+    }
+
+    let Some(rules_dir) = rules_dir else { return Ok(by_id.into_values().collect()) };
+    if !std::path::Path::new(rules_dir).is_dir() {
+        return Err(anyhow!("Rules directory not found: {}", rules_dir));
+    }
+    let dir = std::path::Path::new(rules_dir).join(os.as_str());
+    if !dir.is_dir() {
+        return Ok(by_id.into_values().collect());
+    }
+
+    for entry in walkdir::WalkDir::new(&dir).max_depth(2).sort_by_file_name() {
+        let entry = entry?;
         let path = entry.path();
-        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-        if ext != "yml" && ext != "yaml" {
+        if !entry.file_type().is_file() || !is_rule_file(path) {
             continue;
         }
 
-        let bytes = std::fs::read(path)
-            .with_context(|| format!("Failed reading rule file {}", path.display()))?;
-        let rule: Rule = serde_yaml::from_slice(&bytes)
-            .with_context(|| format!("Failed parsing YAML {}", path.display()))?;
-        out.push(rule);
+        let display = path.display().to_string();
+        let bytes = std::fs::read(path).with_context(|| format!("Failed reading rule file {}", display))?;
+        let rule = parse_rule(&bytes, &lists).with_context(|| format!("Failed parsing YAML {}", display))?;
+        let replaced = by_id.get(&rule.id).map(|r| r.origin.clone());
+        if let Some(RuleOrigin::File { path: first, .. }) = &replaced {
+            return Err(anyhow!("Duplicate rule id {} in {} and {}", rule.id, first, display));
+        }
+        let origin = RuleOrigin::File { path: display, overrides: replaced.is_some() };
+        let compiled = CompiledRule::compile(rule, origin)?;
+        by_id.insert(compiled.rule.id.clone(), compiled);
     }
 
-    out.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(by_id.into_values().collect())
+}
+
+/// Rules for every OS: the built-in pack plus `rules_dir`, as `load_rules` combines them.
+pub fn load_all_rules(rules_dir: Option<&str>) -> Result<Vec<CompiledRule>> {
+    let mut out = vec![];
+    for os in TargetOs::ALL {
+        out.extend(load_rules(rules_dir, os)?);
+    }
     Ok(out)
 }
 
+/// Built-in `lists/`, then `<rules_dir>/lists/*.yml` (replacing built-in names).
+pub fn load_lists(rules_dir: Option<&str>) -> Result<Lists> {
+    let mut lists = Lists::default();
+    for (path, bytes) in builtin_files("lists", 1) {
+        let origin = format!("built-in:{}", path);
+        lists.add_file(&bytes, &origin, true).with_context(|| format!("Invalid lists file {}", origin))?;
+    }
+
+    let Some(dir) = rules_dir.map(|d| std::path::Path::new(d).join("lists")).filter(|d| d.is_dir()) else {
+        return Ok(lists);
+    };
+    for entry in walkdir::WalkDir::new(&dir).max_depth(1).sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_type().is_file() && is_rule_file(entry.path()) {
+            let origin = entry.path().display().to_string();
+            let bytes = std::fs::read(entry.path()).with_context(|| format!("Failed reading lists file {}", origin))?;
+            lists.add_file(&bytes, &origin, false).with_context(|| format!("Invalid lists file {}", origin))?;
+        }
+    }
+    Ok(lists)
+}
+
+/// Parse a rule file, substituting `$name` references in its match block from `lists`.
+pub fn parse_rule(bytes: &[u8], lists: &Lists) -> Result<Rule> {
+    let mut doc: serde_yaml::Value = serde_yaml::from_slice(bytes)?;
+    if !lists.resolve(&mut doc)? {
+        // Parsed again from the text so errors keep their line numbers
+        return Ok(serde_yaml::from_slice(bytes)?);
+    }
+    Ok(serde_yaml::from_value(doc)?)
+}
+
+/// (path relative to rules/, contents) of the YAML files under a directory of the built-in
+/// rules/ tree, down to `max_depth` as `WalkDir::max_depth` counts it for the same directory
+/// on disk (1 is only the directory's own files), so built-ins are read like `--rules-dir`.
+fn builtin_files(subdir: &str, max_depth: usize) -> Vec<(String, Vec<u8>)> {
+    let mut out = vec![];
+    let Some(dir) = BUILTIN_RULES.get_dir(subdir) else { return out };
+    let mut stack = vec![(dir, 1)];
+    while let Some((dir, depth)) = stack.pop() {
+        if depth < max_depth {
+            stack.extend(dir.dirs().map(|d| (d, depth + 1)));
+        }
+        for file in dir.files().filter(|f| is_rule_file(f.path())) {
+            out.push((file.path().display().to_string(), file.contents().to_vec()));
+        }
+    }
+    out.sort();
+    out
+}
+
+pub(crate) fn is_rule_file(path: &std::path::Path) -> bool {
+    matches!(path.extension().and_then(|e| e.to_str()), Some("yml" | "yaml"))
+}
+
 /// A generic “artifact” from collectors; rules match against these.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Artifact {
     pub kind: String,            // e.g. "windows_run_key", "linux_autostart"
     pub source: String,          // e.g. registry path, filename, "crontab -l"
     pub name: String,            // entry name or unit name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,    // owning account, when the artifact is per-user
     pub command: Option<String>, // command/exec line if available
     pub path: Option<String>,    // extracted executable path if we can parse it
     pub raw: Option<String>,     // raw line if useful
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attrs: BTreeMap<String, String>, // per-kind metadata (e.g. service start type)
 }
 
-pub fn run(rules: &[Rule], artifacts: &[Artifact]) -> Result<Vec<Finding>> {
-    let mut findings = vec![];
-    for rule in rules {
-        for art in artifacts {
-            if rule_matches(rule, art)? {
-                findings.push(Finding::from_match(rule, art));
-            }
-        }
+impl Artifact {
+    /// Value of a field by name for rule conditions: kind, source, name, user, command, path,
+    /// raw, value_path, or a collector attribute (`attrs.start` or just `start`).
+    /// `value_path` is `source\name`: the full registry value path of registry artifacts.
+    pub fn field(&self, name: &str) -> Option<Cow<'_, str>> {
+        let value = match name {
+            "kind" => Some(self.kind.as_str()),
+            "source" => Some(self.source.as_str()),
+            "name" => Some(self.name.as_str()),
+            "user" => self.user.as_deref(),
+            "command" => self.command.as_deref(),
+            "path" => self.path.as_deref(),
+            "raw" => self.raw.as_deref(),
+            "value_path" => return Some(Cow::Owned(format!("{}\\{}", self.source, self.name))),
+            other => self.attrs.get(other.strip_prefix("attrs.").unwrap_or(other)).map(String::as_str),
+        };
+        value.map(Cow::Borrowed)
     }
-    Ok(findings)
 }
 
-pub fn build_report(os: TargetOs, artifacts: Vec<Artifact>, findings: Vec<Finding>) -> Report {
-    Report::new(os, artifacts, findings)
+/// Where a set of artifacts was collected: its OS and, for offline scans, the root.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Origin {
+    pub os: TargetOs,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+}
+
+/// Evaluate every rule against every artifact, artifacts in parallel. `files` reads the
+/// files artifacts refer to, for content conditions.
+/// Findings come out grouped by rule, in artifact order within each rule.
+pub fn run(rules: &[CompiledRule], artifacts: &[Artifact], origin: &Origin, files: &dyn FileSource) -> Vec<Finding> {
+    let mut hits: Vec<(usize, Finding)> = artifacts
+        .par_iter()
+        .flat_map_iter(|art| {
+            let art_files = ArtifactFiles::new(files, art);
+            rules
+                .iter()
+                .enumerate()
+                .filter(|(_, rule)| rule_matches(rule, art, &art_files))
+                .map(|(idx, rule)| (idx, Finding::from_match(&rule.rule, art, origin)))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    hits.sort_by_key(|(idx, _)| *idx);
+    hits.into_iter().map(|(_, f)| f).collect()
+}
+
+pub fn build_report(
+    os: TargetOs,
+    artifacts: Vec<Artifact>,
+    findings: Vec<Finding>,
+    scoring: &scoring::ScoringConfig,
+) -> Report {
+    Report::new(os, artifacts, findings, scoring)
 }
 
 /// Baseline snapshot: store only artifacts (rules can change independently)
@@ -98,23 +289,31 @@ pub fn build_report(os: TargetOs, artifacts: Vec<Artifact>, findings: Vec<Findin
 pub struct ArtifactSnapshot {
     pub os: TargetOs,
     pub collected_at_utc: chrono::DateTime<chrono::Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
     pub artifacts: Vec<Artifact>,
 }
 
 impl ArtifactSnapshot {
     pub fn from_artifacts(os: TargetOs, mut artifacts: Vec<Artifact>) -> Self {
-        artifacts.sort_by(|a, b| format_key(a).cmp(&format_key(b)));
+        artifacts.sort_by_key(format_key);
         artifacts.dedup_by(|a, b| format_key(a) == format_key(b));
         Self {
             os,
             collected_at_utc: chrono::Utc::now(),
+            root: None,
             artifacts,
         }
     }
+
+    pub fn with_root(mut self, root: Option<String>) -> Self {
+        self.root = root;
+        self
+    }
 }
 
 fn format_key(a: &Artifact) -> String {
-    format!(
+    let mut key = format!(
         "{}|{}|{}|{}|{}|{}",
         a.kind,
         a.source,
@@ -122,7 +321,12 @@ fn format_key(a: &Artifact) -> String {
         a.command.as_deref().unwrap_or(""),
         a.path.as_deref().unwrap_or(""),
         a.raw.as_deref().unwrap_or("")
-    )
+    );
+    // Only artifacts that carry attrs get a longer key, so older baselines still line up.
+    for (k, v) in &a.attrs {
+        key.push_str(&format!("|{}={}", k, v));
+    }
+    key
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -172,4 +376,111 @@ impl Report {
         self.delta = Some(delta);
         self
     }
+
+    /// Mark the report as an offline scan of the given filesystem root.
+    pub fn with_root(mut self, root: Option<String>) -> Self {
+        self.root = root;
+        self
+    }
+
+    /// Findings taken out of the score by the suppressions file, and any warnings it raised.
+    pub fn with_suppressed(mut self, suppressed: Vec<suppress::SuppressedFinding>, warnings: Vec<String>) -> Self {
+        self.suppressed = suppressed;
+        self.warnings.extend(warnings);
+        self
+    }
+
+    /// Sources the collectors had to skip, which make the scan partial.
+    pub fn with_skipped(mut self, skipped: Vec<String>) -> Self {
+        self.skipped = skipped;
+        self
+    }
+
+    /// Number of findings dropped by the command-line filters.
+    pub fn with_filtered_out(mut self, filtered_out: usize) -> Self {
+        self.filtered_out = filtered_out;
+        self
+    }
+
+    /// Record every root of a combined scan (only worth doing for more than one).
+    pub fn with_targets(mut self, targets: Vec<Origin>) -> Self {
+        self.targets = targets;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules_dir(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("persist-scan-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (rel, id) in files {
+            let path = dir.join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let text = format!(
+                "id: {}\ntitle: t\nos: linux\nseverity: high\nconfidence: low\ntags: []\nrationale: r\ncheck:\n  kind: linux_user_crontab\nmatch: {{}}\n",
+                id
+            );
+            std::fs::write(path, text).unwrap();
+        }
+        dir
+    }
+
+    fn origins(rules: &[CompiledRule]) -> BTreeMap<&str, String> {
+        rules.iter().map(|r| (r.rule.id.as_str(), format!("{:?}", r.origin))).collect()
+    }
+
+    #[test]
+    fn rules_dir_overrides_built_ins_by_id() {
+        let builtin = load_rules(None, TargetOs::Linux).unwrap();
+        let builtin_origins = origins(&builtin);
+        assert_eq!(builtin_origins["LNX_CRON_ANY"], r#"BuiltIn { path: "linux/LNX_CRON_ANY.yml" }"#);
+
+        let dir = rules_dir(
+            "override",
+            &[
+                ("linux/cron.yml", "LNX_CRON_ANY"),
+                ("linux/local/extra.yml", "LNX_LOCAL_EXTRA"),
+                // Deeper than load_rules reads
+                ("linux/local/old/unused.yml", "LNX_UNUSED"),
+                ("windows/other.yml", "WIN_OTHER"),
+            ],
+        );
+        let rules = load_rules(dir.to_str(), TargetOs::Linux).unwrap();
+        let found = origins(&rules);
+        let cron = rules.iter().find(|r| r.rule.id == "LNX_CRON_ANY").unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(rules.len(), builtin.len() + 1);
+        let path = |rel: &str| dir.join(rel).display().to_string();
+        assert_eq!(found["LNX_CRON_ANY"], format!("File {{ path: {:?}, overrides: true }}", path("linux/cron.yml")));
+        assert_eq!(found["LNX_LOCAL_EXTRA"], format!("File {{ path: {:?}, overrides: false }}", path("linux/local/extra.yml")));
+        assert_eq!(found["LNX_AUTOSTART_ANY"], builtin_origins["LNX_AUTOSTART_ANY"]);
+        assert_eq!(cron.rule.severity, rule::Severity::High);
+        assert!(!found.contains_key("LNX_UNUSED"));
+    }
+
+    #[test]
+    fn duplicate_ids_in_rules_dir_fail() {
+        let dir = rules_dir("duplicate", &[("linux/a.yml", "LNX_CRON_ANY"), ("linux/sub/b.yml", "LNX_CRON_ANY")]);
+        let err = load_rules(dir.to_str(), TargetOs::Linux).err().unwrap().to_string();
+        assert_eq!(
+            err,
+            format!("Duplicate rule id LNX_CRON_ANY in {} and {}", dir.join("linux/a.yml").display(), dir.join("linux/sub/b.yml").display())
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn built_in_lists_and_rules_are_read_to_the_rules_dir_depth() {
+        let lists: Vec<String> = builtin_files("lists", 1).into_iter().map(|(p, _)| p).collect();
+        assert_eq!(lists, ["lists/suspicious_dirs.yml"]);
+        for os in TargetOs::ALL {
+            let files = builtin_files(os.as_str(), 2);
+            assert!(!files.is_empty());
+            assert!(files.iter().all(|(p, _)| p.starts_with(os.as_str()) && p.matches('/').count() <= 2), "{:?}", os);
+        }
+    }
 }
@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
 
-use super::{Artifact, TargetOs, SnapshotDelta};
+use super::attack::{self, AttackRef, TechniqueSummary};
+use super::{Artifact, Origin, TargetOs, SnapshotDelta};
 use super::rule::{Confidence, Severity, Rule};
+use super::scoring::{ScoreBreakdown, ScoringConfig};
+use super::suppress::SuppressedFinding;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Finding {
@@ -10,47 +13,110 @@ pub struct Finding {
     pub severity: Severity,
     pub confidence: Confidence,
     pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attack: Vec<AttackRef>,
     pub rationale: String,
 
     pub artifact_kind: String,
     pub source: String,
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
     pub command: Option<String>,
     pub path: Option<String>,
+
+    pub os: TargetOs,
+    /// Offline root the artifact was found under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
 }
 
 impl Finding {
-    pub fn from_match(rule: &Rule, art: &Artifact) -> Self {
+    pub fn from_match(rule: &Rule, art: &Artifact, origin: &Origin) -> Self {
         Self {
             rule_id: rule.id.clone(),
             title: rule.title.clone(),
             severity: rule.severity,
             confidence: rule.confidence,
             tags: rule.tags.clone(),
+            attack: rule.attack.clone(),
             rationale: rule.rationale.clone(),
             artifact_kind: art.kind.clone(),
             source: art.source.clone(),
             name: art.name.clone(),
+            user: art.user.clone(),
             command: art.command.clone(),
             path: art.path.clone(),
+            os: origin.os,
+            root: origin.root.clone(),
         }
     }
-
-    pub fn score(&self) -> f32 {
-        self.severity.points() as f32 * self.confidence.multiplier()
-    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Report {
+    /// OS of the scan; for a multi-root scan only the first root's (see `targets`)
     pub os: TargetOs,
     pub generated_at_utc: chrono::DateTime<chrono::Utc>,
+    /// Highest host score of the scan (hosts are scored separately, not summed)
     pub risk_score_0_100: i32,
+    /// How each host's score was produced
+    pub score_breakdown: ScoreBreakdown,
     pub counts: Counts,
     pub findings: Vec<Finding>,
+    /// ATT&CK techniques behind the findings
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attack: Vec<TechniqueSummary>,
     pub artifacts_scanned: usize,
+    /// Filesystem root or archive of an offline scan; absent for live-host scans
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    /// Every root of a combined multi-root scan, with the OS detected in each
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<Origin>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub delta: Option<SnapshotDelta>,
+    /// Findings matched by the suppressions file; not counted in the score
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suppressed: Vec<SuppressedFinding>,
+    /// Findings dropped by the command-line filters; not shown or scored
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub filtered_out: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Sources the collectors could not read (unparsable hives, corrupt keys, unreadable
+    /// folders); when present the scan is partial
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<String>,
+}
+
+/// How `to_human_readable` groups findings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Rule,
+    Kind,
+    Source,
+    Path,
+}
+
+impl GroupBy {
+    fn key(self, f: &Finding) -> &str {
+        match self {
+            GroupBy::Rule => &f.rule_id,
+            GroupBy::Kind => &f.artifact_kind,
+            GroupBy::Source => &f.source,
+            GroupBy::Path => f.path.as_deref().unwrap_or("(no path)"),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            GroupBy::Rule => "Rule",
+            GroupBy::Kind => "Kind",
+            GroupBy::Source => "Source",
+            GroupBy::Path => "Path",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -61,7 +127,7 @@ pub struct Counts {
 }
 
 impl Report {
-    pub fn new(os: TargetOs, artifacts: Vec<Artifact>, mut findings: Vec<Finding>) -> Self {
+    pub fn new(os: TargetOs, artifacts: Vec<Artifact>, mut findings: Vec<Finding>, scoring: &ScoringConfig) -> Self {
         findings.sort_by(|a, b| {
             let sa = sev_rank(a.severity);
             let sb = sev_rank(b.severity);
@@ -77,29 +143,93 @@ impl Report {
             }
         }
 
-        let total: f32 = findings.iter().map(|f| f.score()).sum();
-        let mut score = total.round() as i32;
-        if score > 100 { score = 100; }
-        if score < 0 { score = 0; }
+        let score_breakdown = scoring.score(&findings);
+        let score = score_breakdown.hosts.iter().map(|h| h.score).max().unwrap_or(0);
 
         Self {
             os,
             generated_at_utc: chrono::Utc::now(),
             risk_score_0_100: score,
+            score_breakdown,
             counts,
             artifacts_scanned: artifacts.len(),
+            attack: attack::summarize(&findings),
             findings,
+            root: None,
+            targets: vec![],
             delta: None,
+            suppressed: vec![],
+            filtered_out: 0,
+            warnings: vec![],
+            skipped: vec![],
+        }
+    }
+
+    /// Why the report fails the `--fail-on` / `--max-score` thresholds; empty when it passes.
+    pub fn threshold_failures(&self, fail_on: Option<Severity>, max_score: Option<i32>) -> Vec<String> {
+        let mut out = vec![];
+        if let Some(min) = fail_on {
+            let n = self.findings.iter().filter(|f| f.severity >= min).count();
+            if n > 0 {
+                let severity = format!("{:?}", min).to_lowercase();
+                out.push(format!("{} finding(s) of severity {} or higher", n, severity));
+            }
+        }
+        if let Some(max) = max_score {
+            if self.risk_score_0_100 > max {
+                out.push(format!("risk score {} is above {}", self.risk_score_0_100, max));
+            }
         }
+        out
     }
 
-    pub fn to_human_readable(&self) -> String {
+    /// Human output; with `group_by`, findings are listed under one heading per group, the
+    /// group holding the most severe finding first.
+    pub fn to_human_readable(&self, group_by: Option<GroupBy>) -> String {
         let mut s = String::new();
+        for w in &self.warnings {
+            s.push_str(&format!("Warning: {}\n", w));
+        }
+        if !self.skipped.is_empty() {
+            s.push_str(&format!("Partial scan: {} source(s) could not be read:\n", self.skipped.len()));
+            for what in &self.skipped {
+                s.push_str(&format!("  {}\n", what));
+            }
+        }
+        if let Some(root) = &self.root {
+            s.push_str(&format!("Offline scan of: {}\n", root));
+        }
+        if !self.targets.is_empty() {
+            s.push_str(&format!("Offline scan of {} roots:\n", self.targets.len()));
+            for t in &self.targets {
+                s.push_str(&format!("  {} ({})\n", t.root.as_deref().unwrap_or("live host"), t.os.as_str()));
+            }
+        }
         s.push_str(&format!(
             "Risk score: {} / 100\nFindings: {} high, {} medium, {} low\nArtifacts scanned: {}\n",
             self.risk_score_0_100, self.counts.high, self.counts.medium, self.counts.low, self.artifacts_scanned
         ));
 
+        if self.score_breakdown.hosts.len() > 1 {
+            s.push_str("Score per root:\n");
+            for h in &self.score_breakdown.hosts {
+                s.push_str(&format!(
+                    "  {} ({}): {} / 100\n",
+                    h.origin.root.as_deref().unwrap_or("live host"),
+                    h.origin.os.as_str(),
+                    h.score
+                ));
+            }
+        }
+        let unscored: usize = self.score_breakdown.hosts.iter().flat_map(|h| &h.excluded).map(|e| e.hits).sum();
+        if unscored > 0 {
+            s.push_str(&format!("Not scored: {} finding(s) of rules with excluded tags (see score_breakdown in --json)\n", unscored));
+        }
+
+        if self.filtered_out > 0 {
+            s.push_str(&format!("Filtered out: {} finding(s)\n", self.filtered_out));
+        }
+
         if let Some(d) = &self.delta {
             s.push_str(&format!(
                 "Delta: +{} added, -{} removed\n",
@@ -108,28 +238,92 @@ impl Report {
             ));
         }
 
-        for f in &self.findings {
-            s.push_str("\n");
-            s.push_str(&format!(
-                "[{:?}] {} ({})\n  Source: {}\n  Name: {}\n",
-                f.severity, f.rule_id, f.title, f.source, f.name
-            ));
-            if let Some(cmd) = &f.command {
-                s.push_str(&format!("  Command: {}\n", cmd));
+        if !self.attack.is_empty() {
+            s.push_str("\nATT&CK techniques:\n");
+            for t in &self.attack {
+                s.push_str(&format!(
+                    "  {:<20} {:<10} {} ({} finding(s), max {:?})\n",
+                    t.tactic, t.technique, t.name, t.findings, t.max_severity
+                ));
             }
-            if let Some(path) = &f.path {
-                s.push_str(&format!("  Path: {}\n", path));
+        }
+
+        match group_by {
+            None => {
+                for f in &self.findings {
+                    self.push_finding(&mut s, f);
+                }
+            }
+            Some(g) => {
+                let mut groups: Vec<(&str, Vec<&Finding>)> = vec![];
+                for f in &self.findings {
+                    let key = g.key(f);
+                    match groups.iter_mut().find(|(k, _)| *k == key) {
+                        Some((_, list)) => list.push(f),
+                        None => groups.push((key, vec![f])),
+                    }
+                }
+                for (key, list) in groups {
+                    s.push_str(&format!("\n== {}: {} ({} finding(s)) ==\n", g.label(), key, list.len()));
+                    for f in list {
+                        self.push_finding(&mut s, f);
+                    }
+                }
+            }
+        }
+
+        if !self.suppressed.is_empty() {
+            s.push_str(&format!("\nSuppressed ({}, not scored):\n", self.suppressed.len()));
+            for sf in &self.suppressed {
+                let f = &sf.finding;
+                s.push_str(&format!("  [{:?}] {} {}", f.severity, f.rule_id, f.name));
+                if let Some(path) = &f.path {
+                    s.push_str(&format!(" ({})", path));
+                }
+                s.push_str(&format!("\n    Reason: {}   Owner: {}", sf.reason, sf.owner));
+                if let Some(expires) = sf.expires {
+                    s.push_str(&format!("   Expires: {}", expires));
+                }
+                s.push('\n');
             }
-            s.push_str(&format!(
-                "  Confidence: {:?}   Tags: {}\n  Why: {}\n",
-                f.confidence,
-                f.tags.join(", "),
-                f.rationale
-            ));
         }
 
         s
     }
+
+    fn push_finding(&self, s: &mut String, f: &Finding) {
+        s.push('\n');
+        s.push_str(&format!(
+            "[{:?}] {} ({})\n  Source: {}\n  Name: {}\n",
+            f.severity, f.rule_id, f.title, f.source, f.name
+        ));
+        if !self.targets.is_empty() {
+            s.push_str(&format!("  Root: {} ({})\n", f.root.as_deref().unwrap_or("live host"), f.os.as_str()));
+        }
+        if let Some(user) = &f.user {
+            s.push_str(&format!("  User: {}\n", user));
+        }
+        if let Some(cmd) = &f.command {
+            s.push_str(&format!("  Command: {}\n", cmd));
+        }
+        if let Some(path) = &f.path {
+            s.push_str(&format!("  Path: {}\n", path));
+        }
+        s.push_str(&format!(
+            "  Confidence: {:?}   Tags: {}\n  Why: {}\n",
+            f.confidence,
+            f.tags.join(", "),
+            f.rationale
+        ));
+        if !f.attack.is_empty() {
+            let refs: Vec<String> = f.attack.iter().map(|a| format!("{} ({})", a.technique, a.tactic)).collect();
+            s.push_str(&format!("  ATT&CK: {}\n", refs.join(", ")));
+        }
+    }
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
 }
 
 fn sev_rank(s: Severity) -> i32 {
@@ -139,3 +333,101 @@ fn sev_rank(s: Severity) -> i32 {
         Severity::Low => 1,
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn finding(rule_id: &str, severity: Severity) -> Finding {
+        Finding {
+            rule_id: rule_id.to_string(),
+            title: String::new(),
+            severity,
+            confidence: Confidence::Medium,
+            tags: vec![],
+            attack: vec![],
+            rationale: String::new(),
+            artifact_kind: "linux_user_crontab".to_string(),
+            source: String::new(),
+            name: String::new(),
+            user: None,
+            command: None,
+            path: None,
+            os: TargetOs::Linux,
+            root: None,
+        }
+    }
+
+    pub(crate) fn report(findings: Vec<Finding>) -> Report {
+        Report::new(TargetOs::Linux, vec![], findings, &ScoringConfig::default())
+    }
+
+    #[test]
+    fn threshold_failures() {
+        // Scores 15 + 15 + 5
+        let r = report(vec![
+            finding("A", Severity::Medium),
+            finding("B", Severity::Medium),
+            finding("C", Severity::Low),
+        ]);
+        assert_eq!(r.risk_score_0_100, 35);
+
+        assert!(r.threshold_failures(None, None).is_empty());
+        assert!(r.threshold_failures(Some(Severity::High), Some(35)).is_empty());
+        assert_eq!(r.threshold_failures(Some(Severity::Medium), None), ["2 finding(s) of severity medium or higher"]);
+        assert_eq!(r.threshold_failures(Some(Severity::Low), None), ["3 finding(s) of severity low or higher"]);
+        assert_eq!(r.threshold_failures(None, Some(34)), ["risk score 35 is above 34"]);
+        assert_eq!(r.threshold_failures(Some(Severity::Medium), Some(0)).len(), 2);
+
+        // Suppressed and filtered findings are not in `findings`, so an empty report passes any gate
+        assert!(report(vec![]).threshold_failures(Some(Severity::Low), Some(0)).is_empty());
+    }
+
+    fn at(rule_id: &str, severity: Severity, kind: &str, path: Option<&str>) -> Finding {
+        Finding { artifact_kind: kind.to_string(), path: path.map(str::to_string), ..finding(rule_id, severity) }
+    }
+
+    fn group_headers(r: &Report, g: GroupBy) -> Vec<String> {
+        r.to_human_readable(Some(g)).lines().filter(|l| l.starts_with("== ")).map(str::to_string).collect()
+    }
+
+    #[test]
+    fn groups_follow_report_order() {
+        // Sorted by severity, then rule ID: A, C, D, B
+        let r = report(vec![
+            at("B", Severity::Low, "windows_service", Some(r"C:\a.exe")),
+            at("D", Severity::Medium, "windows_service", None),
+            at("A", Severity::High, "windows_run_key", Some(r"C:\a.exe")),
+            at("C", Severity::Medium, "windows_run_key", Some(r"C:\c.exe")),
+        ]);
+        assert_eq!(
+            group_headers(&r, GroupBy::Kind),
+            ["== Kind: windows_run_key (2 finding(s)) ==", "== Kind: windows_service (2 finding(s)) =="]
+        );
+        assert_eq!(
+            group_headers(&r, GroupBy::Path),
+            [
+                r"== Path: C:\a.exe (2 finding(s)) ==",
+                r"== Path: C:\c.exe (1 finding(s)) ==",
+                "== Path: (no path) (1 finding(s)) ==",
+            ]
+        );
+        assert_eq!(group_headers(&r, GroupBy::Rule).len(), 4);
+
+        // Within a group, findings keep the report order
+        let text = r.to_human_readable(Some(GroupBy::Kind));
+        let order: Vec<&str> = text.lines().filter(|l| l.starts_with('[')).filter_map(|l| l.split(' ').nth(1)).collect();
+        assert_eq!(order, ["A", "C", "D", "B"]);
+    }
+
+    #[test]
+    fn filtered_out_is_reported_when_nonzero() {
+        let r = report(vec![finding("A", Severity::Low)]);
+        assert!(!r.to_human_readable(None).contains("Filtered out"));
+        assert!(serde_json::to_value(&r).unwrap().get("filtered_out").is_none());
+
+        let r = r.with_filtered_out(3);
+        assert!(r.to_human_readable(None).contains("Filtered out: 3 finding(s)\n"));
+        assert_eq!(serde_json::to_value(&r).unwrap()["filtered_out"], 3);
+    }
+}
@@ -1,40 +1,201 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::attack::AttackRef;
+use super::Artifact;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Rule {
     pub id: String,
     pub title: String,
-    pub os: String, // "linux" or "windows" (human-readable)
+    pub os: String, // "linux", "windows" or "macos" (human-readable)
     pub severity: Severity,
     pub confidence: Confidence,
     pub tags: Vec<String>,
+    /// ATT&CK techniques the rule detects, checked against the bundled technique table
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attack: Vec<AttackRef>,
     pub rationale: String,
     pub check: Check,
     pub r#match: Match,
+    /// Sample artifacts proving the rule fires (and does not), run by `rules test`
+    #[serde(default, skip_serializing_if = "RuleTests::is_empty")]
+    pub tests: RuleTests,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RuleTests {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub should_match: Vec<SampleArtifact>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub should_not_match: Vec<SampleArtifact>,
+}
+
+impl RuleTests {
+    pub fn is_empty(&self) -> bool {
+        self.should_match.is_empty() && self.should_not_match.is_empty()
+    }
+}
+
+/// An artifact written in a rule's tests; only the fields a test cares about need to be given.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SampleArtifact {
+    /// Defaults to the rule's check.kind
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub source: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attrs: BTreeMap<String, String>,
+    /// Contents of the file(s) the artifact refers to, for rules with `content:` conditions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+impl SampleArtifact {
+    pub fn to_artifact(&self, default_kind: &str) -> Artifact {
+        Artifact {
+            kind: self.kind.clone().unwrap_or_else(|| default_kind.to_string()),
+            source: self.source.clone(),
+            name: self.name.clone(),
+            user: self.user.clone(),
+            command: self.command.clone(),
+            path: self.path.clone(),
+            raw: self.raw.clone(),
+            attrs: self.attrs.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Check {
     pub kind: String, // maps to Artifact.kind
-    #[serde(default)]
-    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>, // reserved; not used by the matcher
 }
 
+/// Match criteria. The flat lists are alternatives (an implicit `any`); `field`/`op` is a
+/// condition on any artifact field or attribute; `all`, `any` and `not` nest further blocks.
+/// Every part present in a block must hold, and an empty block matches everything of the
+/// checked kind.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct Match {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub any_path_contains: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub any_path_prefix: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub any_command_contains: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub regex_command: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub regex_path: Vec<String>,
+
+    /// Artifact field for a condition: kind, source, name, user, command, path, raw,
+    /// value_path (`source\name`), or a collector attribute (`attrs.<key>`, or the bare key)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub op: Option<FieldOp>,
+    /// Operand of the condition; with `values` the condition holds if any of them matches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+    /// Conditions ignore case unless this is set
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub case_sensitive: bool,
+
+    /// Patterns searched for in the file the artifact runs (its path, or the script given to an interpreter)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<ContentMatch>,
+
+    /// Every nested block must match
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub all: Vec<Match>,
+    /// At least one nested block must match
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub any: Vec<Match>,
+    /// The nested block must not match (use flat lists inside for "none of")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not: Option<Box<Match>>,
+}
+
+/// YARA-style patterns over file contents. Holds when some referenced file no larger than
+/// `max_size` matches as many patterns as `condition` asks for.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ContentMatch {
+    /// Text searched for as-is (ignoring ASCII case unless case_sensitive)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub strings: Vec<String>,
+    /// Byte patterns such as "6a 02 ?? 0f 05": `??` is any byte, `4?`/`?a` any nibble, `[2-4]` a jump
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hex: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regex: Vec<String>,
+    /// "any of them" (default), "all of them" or "N of them"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+    /// Files larger than this many bytes are not read (default 1 MiB)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub case_sensitive: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldOp {
+    Regex,
+    Contains,
+    Prefix,
+    Suffix,
+    Equals,
+    Glob,
+    /// Equal to one of `values` (a single `value` is an error; use `equals`)
+    InList,
+    /// The artifact has the field at all (takes no value)
+    Exists,
+}
+
+impl Match {
+    /// Operands of the field condition (`value` and `values` combined).
+    pub fn operands(&self) -> impl Iterator<Item = &String> {
+        self.value.iter().chain(self.values.iter())
+    }
+
+    /// Whether any part of a field condition other than `field` itself is given.
+    pub fn has_condition_parts(&self) -> bool {
+        self.op.is_some() || self.value.is_some() || !self.values.is_empty() || self.case_sensitive
+    }
+
+    pub fn has_flat_criteria(&self) -> bool {
+        !self.any_path_contains.is_empty()
+            || !self.any_path_prefix.is_empty()
+            || !self.any_command_contains.is_empty()
+            || !self.regex_command.is_empty()
+            || !self.regex_path.is_empty()
+    }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// Ordered from low to high.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "lowercase")]
 pub enum Severity {
     Low,
@@ -42,7 +203,8 @@ pub enum Severity {
     High,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// Ordered from low to high.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "lowercase")]
 pub enum Confidence {
     Low,
@@ -61,7 +223,7 @@ impl Severity {
 }
 
 impl Confidence {
-    pub fn multiplier(self) -> f32 {
+    pub fn multiplier(self) -> f64 {
         match self {
             Confidence::Low => 0.7,
             Confidence::Medium => 1.0,
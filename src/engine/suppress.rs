@@ -0,0 +1,367 @@
+//! Suppression files (`--suppressions suppress.yml`): known-good findings that are taken out
+//! of the score but still reported, each with a documented reason and owner.
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+use super::report::Finding;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SuppressionFile {
+    #[serde(default)]
+    suppressions: Vec<Suppression>,
+}
+
+/// One entry. Every criterion given must match; at least one is required.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suppression {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// Artifact name, compared ignoring case
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Glob over the artifact path (backslashes are literal, case-insensitive)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Regex over the command line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// SHA-256 of the file at the artifact path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    pub reason: String,
+    pub owner: String,
+    /// Last day the suppression applies; after that it is ignored and warned about
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<NaiveDate>,
+}
+
+/// A finding taken out of the score, with the suppression that matched it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuppressedFinding {
+    pub finding: Finding,
+    pub reason: String,
+    pub owner: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<NaiveDate>,
+}
+
+struct Compiled {
+    entry: Suppression,
+    path: Option<GlobMatcher>,
+    command: Option<Regex>,
+}
+
+pub struct Suppressions {
+    active: Vec<Compiled>,
+    /// Human-readable problems found while loading (expired entries)
+    pub warnings: Vec<String>,
+}
+
+impl Suppressions {
+    /// Load and validate a suppression file. Entries past their expiry date are dropped with a warning.
+    pub fn load(path: &str, today: NaiveDate) -> Result<Self> {
+        let bytes = std::fs::read(path).with_context(|| format!("Could not read suppressions file: {}", path))?;
+        Self::parse(&bytes, path, today)
+    }
+
+    fn parse(bytes: &[u8], path: &str, today: NaiveDate) -> Result<Self> {
+        let file: SuppressionFile =
+            serde_yaml::from_slice(bytes).with_context(|| format!("Failed parsing YAML {}", path))?;
+
+        let mut active = vec![];
+        let mut warnings = vec![];
+        for (i, entry) in file.suppressions.into_iter().enumerate() {
+            let at = || format!("{}: suppression #{}", path, i + 1);
+            if entry.reason.trim().is_empty() || entry.owner.trim().is_empty() {
+                anyhow::bail!("{}: reason and owner must not be empty", at());
+            }
+            if entry.rule.is_none()
+                && entry.kind.is_none()
+                && entry.name.is_none()
+                && entry.path.is_none()
+                && entry.command.is_none()
+                && entry.sha256.is_none()
+            {
+                anyhow::bail!("{}: needs at least one of rule, kind, name, path, command, sha256", at());
+            }
+            if let Some(expires) = entry.expires {
+                if expires < today {
+                    warnings.push(format!(
+                        "{} ({}, owner {}) expired on {} and was not applied",
+                        at(),
+                        entry.reason,
+                        entry.owner,
+                        expires
+                    ));
+                    continue;
+                }
+            }
+
+            let path_glob = match &entry.path {
+                Some(p) => Some(
+                    GlobBuilder::new(p)
+                        .case_insensitive(true)
+                        .backslash_escape(false)
+                        .build()
+                        .with_context(|| format!("{}: invalid path glob", at()))?
+                        .compile_matcher(),
+                ),
+                None => None,
+            };
+            let command = match &entry.command {
+                Some(c) => Some(Regex::new(c).with_context(|| format!("{}: invalid command regex", at()))?),
+                None => None,
+            };
+            active.push(Compiled { entry, path: path_glob, command });
+        }
+
+        Ok(Self { active, warnings })
+    }
+
+    /// Split findings into (kept, suppressed). `read_file` reads the file an artifact path
+    /// refers to, and is only called when a suppression needs a hash.
+    pub fn apply(
+        &self,
+        findings: Vec<Finding>,
+        read_file: impl Fn(&str) -> Option<Vec<u8>>,
+    ) -> (Vec<Finding>, Vec<SuppressedFinding>) {
+        let mut hashes: HashMap<String, Option<String>> = HashMap::new();
+        let mut kept = vec![];
+        let mut suppressed = vec![];
+
+        for f in findings {
+            let hit = self.active.iter().find(|s| {
+                s.matches(&f, |p| {
+                    hashes
+                        .entry(p.to_string())
+                        .or_insert_with(|| read_file(p).map(|b| hex(&Sha256::digest(&b))))
+                        .clone()
+                })
+            });
+            match hit {
+                Some(s) => suppressed.push(SuppressedFinding {
+                    finding: f,
+                    reason: s.entry.reason.clone(),
+                    owner: s.entry.owner.clone(),
+                    expires: s.entry.expires,
+                }),
+                None => kept.push(f),
+            }
+        }
+        (kept, suppressed)
+    }
+}
+
+impl Compiled {
+    fn matches(&self, f: &Finding, mut hash_of: impl FnMut(&str) -> Option<String>) -> bool {
+        let e = &self.entry;
+        if e.rule.as_ref().is_some_and(|r| r != &f.rule_id) {
+            return false;
+        }
+        if e.kind.as_ref().is_some_and(|k| k != &f.artifact_kind) {
+            return false;
+        }
+        if e.name.as_ref().is_some_and(|n| !n.eq_ignore_ascii_case(&f.name)) {
+            return false;
+        }
+        if let Some(glob) = &self.path {
+            if !f.path.as_deref().is_some_and(|p| glob.is_match(p)) {
+                return false;
+            }
+        }
+        if let Some(re) = &self.command {
+            if !f.command.as_deref().is_some_and(|c| re.is_match(c)) {
+                return false;
+            }
+        }
+        if let Some(want) = &e.sha256 {
+            let Some(path) = f.path.as_deref() else { return false };
+            if !hash_of(path).is_some_and(|h| h.eq_ignore_ascii_case(want)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::TargetOs;
+    use std::cell::RefCell;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, d).unwrap()
+    }
+
+    fn parse(yaml: &str, today: NaiveDate) -> Result<Suppressions> {
+        Suppressions::parse(yaml.as_bytes(), "suppress.yml", today)
+    }
+
+    fn finding(rule_id: &str, name: &str, path: Option<&str>, command: Option<&str>) -> Finding {
+        Finding {
+            rule_id: rule_id.to_string(),
+            title: String::new(),
+            severity: crate::engine::rule::Severity::Medium,
+            confidence: crate::engine::rule::Confidence::Medium,
+            tags: vec![],
+            attack: vec![],
+            rationale: String::new(),
+            artifact_kind: "windows_run_key".to_string(),
+            source: String::new(),
+            name: name.to_string(),
+            user: None,
+            command: command.map(str::to_string),
+            path: path.map(str::to_string),
+            os: TargetOs::Windows,
+            root: None,
+        }
+    }
+
+    /// Names of the suppressed findings, with the reason that took each out.
+    fn suppressed(s: &Suppressions, findings: Vec<Finding>) -> Vec<(String, String)> {
+        let (_, out) = s.apply(findings, |_| None);
+        out.into_iter().map(|sf| (sf.finding.name, sf.reason)).collect()
+    }
+
+    #[test]
+    fn expiry_day_still_applies() {
+        let yaml = "suppressions:\n  - { rule: R, reason: vendor agent, owner: it, expires: 2026-03-10 }";
+        let f = || vec![finding("R", "agent", None, None)];
+
+        let on_the_day = parse(yaml, day(10)).unwrap();
+        assert!(on_the_day.warnings.is_empty());
+        assert_eq!(suppressed(&on_the_day, f()).len(), 1);
+
+        let day_after = parse(yaml, day(11)).unwrap();
+        assert!(suppressed(&day_after, f()).is_empty());
+        assert_eq!(
+            day_after.warnings,
+            ["suppress.yml: suppression #1 (vendor agent, owner it) expired on 2026-03-10 and was not applied"]
+        );
+    }
+
+    #[test]
+    fn incomplete_entries_are_rejected() {
+        let cases = [
+            ("{ rule: R, reason: '', owner: it }", "reason and owner must not be empty"),
+            ("{ rule: R, reason: x, owner: '  ' }", "reason and owner must not be empty"),
+            ("{ reason: x, owner: it, expires: 2026-12-31 }", "needs at least one of rule, kind, name, path, command, sha256"),
+            ("{ rule: R, reason: x }", "missing field `owner`"),
+            ("{ rule: R, reason: x, owner: it, host: a }", "unknown field `host`"),
+            ("{ command: '(', reason: x, owner: it }", "invalid command regex"),
+        ];
+        for (entry, want) in cases {
+            let yaml = format!("suppressions:\n  - {{ rule: OK, reason: x, owner: it }}\n  - {}", entry);
+            let err = format!("{:#}", parse(&yaml, day(1)).err().expect(entry));
+            assert!(err.contains(want), "{}: {}", entry, err);
+            if !want.contains("field") {
+                assert!(err.starts_with("suppress.yml: suppression #2"), "{}", err);
+            }
+        }
+    }
+
+    #[test]
+    fn path_glob_ignores_case_and_keeps_backslashes() {
+        let s = parse(
+            r#"suppressions:
+  - { path: 'C:\Program Files\*\*.exe', reason: installed software, owner: it }"#,
+            day(1),
+        )
+        .unwrap();
+        let found = suppressed(
+            &s,
+            vec![
+                finding("R", "lower", Some(r"c:\program files\Vendor\agent.EXE"), None),
+                finding("R", "slashes", Some("C:/Program Files/Vendor/agent.exe"), None),
+                finding("R", "other", Some(r"C:\Users\bob\agent.exe"), None),
+                finding("R", "no path", None, None),
+            ],
+        );
+        assert_eq!(found, [("lower".to_string(), "installed software".to_string())]);
+    }
+
+    #[test]
+    fn command_regex_and_exact_fields() {
+        let s = parse(
+            r#"suppressions:
+  - { rule: LNX_CRON_ANY, name: Backup, command: '^/usr/local/bin/backup\.sh( |$)', reason: nightly backup, owner: ops }"#,
+            day(1),
+        )
+        .unwrap();
+        let found = suppressed(
+            &s,
+            vec![
+                finding("LNX_CRON_ANY", "backup", None, Some("/usr/local/bin/backup.sh --full")),
+                finding("LNX_CRON_ANY", "backup", None, Some("/usr/local/bin/backup.sh.evil")),
+                finding("LNX_CRON_ANY", "backup", None, None),
+                // Rule IDs compare exactly
+                finding("lnx_cron_any", "backup", None, Some("/usr/local/bin/backup.sh")),
+                finding("LNX_CRON_ANY", "other", None, Some("/usr/local/bin/backup.sh")),
+            ],
+        );
+        assert_eq!(found, [("backup".to_string(), "nightly backup".to_string())]);
+    }
+
+    #[test]
+    fn sha256_is_read_once_per_path() {
+        // SHA-256 of "agent"
+        let hash = hex(&Sha256::digest(b"agent"));
+        let yaml = format!(
+            "suppressions:\n  - {{ sha256: {}, reason: known build, owner: it }}\n  - {{ sha256: '{}', name: x, reason: r, owner: it }}",
+            hash.to_uppercase(),
+            "0".repeat(64)
+        );
+        let s = parse(&yaml, day(1)).unwrap();
+        let reads = RefCell::new(vec![]);
+        let read_file = |p: &str| {
+            reads.borrow_mut().push(p.to_string());
+            (p == "/opt/agent").then(|| b"agent".to_vec())
+        };
+        let (kept, out) = s.apply(
+            vec![
+                finding("A", "one", Some("/opt/agent"), None),
+                finding("B", "two", Some("/opt/agent"), None),
+                finding("C", "three", Some("/opt/other"), None),
+                finding("D", "four", Some("/opt/other"), None),
+                finding("E", "no path", None, None),
+            ],
+            read_file,
+        );
+        let names: Vec<&str> = out.iter().map(|sf| sf.finding.name.as_str()).collect();
+        assert_eq!(names, ["one", "two"]);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(*reads.borrow(), ["/opt/agent", "/opt/other"]);
+    }
+
+    #[test]
+    fn first_matching_entry_wins() {
+        let s = parse(
+            r#"suppressions:
+  - { rule: R, name: agent, reason: specific, owner: a, expires: 2026-06-01 }
+  - { rule: R, reason: whole rule, owner: b }"#,
+            day(1),
+        )
+        .unwrap();
+        let (_, out) = s.apply(vec![finding("R", "agent", None, None), finding("R", "other", None, None)], |_| None);
+        let got: Vec<(&str, &str, &str, Option<NaiveDate>)> = out
+            .iter()
+            .map(|sf| (sf.finding.name.as_str(), sf.reason.as_str(), sf.owner.as_str(), sf.expires))
+            .collect();
+        assert_eq!(got, [("agent", "specific", "a", NaiveDate::from_ymd_opt(2026, 6, 1)), ("other", "whole rule", "b", None)]);
+    }
+}
@@ -3,58 +3,358 @@ use anyhow::{Context, Result};
 mod cli;
 mod collectors;
 mod engine;
+mod parsers;
 mod util;
+mod vfs;
 
 use clap::Parser;
-use cli::{Args, Command};
+use cli::{Args, Command, RulesCommand};
+use engine::content::FileSource;
+use engine::matcher::{CompiledRule, RuleOrigin};
+use std::collections::BTreeMap;
+use std::process::ExitCode;
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+/// Exit status when a --fail-on / --max-score threshold is crossed.
+const EXIT_THRESHOLD: u8 = 1;
+/// Exit status on errors, so they cannot be mistaken for findings.
+const EXIT_ERROR: u8 = 2;
+/// Exit status when collection was partial: some sources could not be read (they are listed
+/// in the report). A crossed threshold takes precedence.
+const EXIT_PARTIAL: u8 = 3;
 
-    let os = engine::detect_os(&args.os)?;
-    let rules = engine::load_rules(&args.rules_dir, os)
-        .with_context(|| format!("Failed loading rules from {}", args.rules_dir))?;
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
 
-    match args.cmd {
-        Command::Scan => {
-            let artifacts = collectors::collect(os)?;
-            let findings = engine::run(&rules, &artifacts)?;
-            let report = engine::build_report(os, artifacts, findings);
+fn run(args: Args) -> Result<ExitCode> {
+    if let Command::Rules { cmd } = &args.cmd {
+        rules_command(&args, cmd)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let requested_os = engine::parse_os(&args.os)?;
+    let (filter, group_by) = match &args.cmd {
+        Command::Scan { filter, .. } | Command::Diff { filter, .. } => finding_filter(filter)?,
+        _ => Default::default(),
+    };
+    let (fail_on, max_score) = match &args.cmd {
+        Command::Scan { thresholds, .. } | Command::Diff { thresholds, .. } => (
+            thresholds.fail_on.as_deref().map(|s| engine::parse_severity("--fail-on", s)).transpose()?,
+            thresholds.max_score,
+        ),
+        _ => (None, None),
+    };
+    let collect_opts = collectors::CollectOptions {
+        roots: args.root.iter().map(std::path::PathBuf::from).collect(),
+        archive: args.from_archive.as_ref().map(std::path::PathBuf::from),
+    };
+    let targets = collect_opts.targets(requested_os)?;
+
+    let mut rules_by_os: BTreeMap<&str, Vec<CompiledRule>> = BTreeMap::new();
+    for t in &targets {
+        if !rules_by_os.contains_key(t.os.as_str()) {
+            let rules = engine::load_rules(args.rules_dir.as_deref(), t.os).context("Failed loading rules")?;
+            rules_by_os.insert(t.os.as_str(), rules);
+        }
+    }
+
+    let suppressions = match &args.suppressions {
+        Some(path) => Some(engine::suppress::Suppressions::load(path, chrono::Local::now().date_naive())?),
+        None => None,
+    };
+
+    let scoring = match &args.scoring {
+        Some(path) => engine::scoring::ScoringConfig::load(path)?,
+        None => engine::scoring::ScoringConfig::default(),
+    };
+    let known_rules = rules_by_os.values().flatten().map(|r| r.rule.id.as_str());
+    let mut warnings = suppressions.as_ref().map(|s| s.warnings.clone()).unwrap_or_default();
+    warnings.extend(scoring.unknown_rules(known_rules));
+
+    let report = match args.cmd {
+        Command::Scan { .. } => {
+            let mut artifacts = vec![];
+            let mut findings = vec![];
+            let mut suppressed = vec![];
+            let mut origins = vec![];
+            let mut filtered_out = 0;
+            let mut skipped = vec![];
+            for t in &targets {
+                let origin = t.origin();
+                let collected = collectors::collect(t.os, t.fs.as_deref())?;
+                let (matched, dropped) =
+                    filter.apply(engine::run(&rules_by_os[t.os.as_str()], &collected.artifacts, &origin, t));
+                let (kept, quiet) = suppress(suppressions.as_ref(), t, matched);
+                filtered_out += dropped;
+                findings.extend(kept);
+                suppressed.extend(quiet);
+                artifacts.extend(collected.artifacts);
+                match (&origin.root, targets.len()) {
+                    (Some(root), 2..) => skipped.extend(collected.skipped.iter().map(|s| format!("{}: {}", root, s))),
+                    _ => skipped.extend(collected.skipped),
+                }
+                origins.push(origin);
+            }
+
+            // A mixed scan has no single OS: Report.os is the first root's, `targets` has them all
+            let report = engine::build_report(origins[0].os, artifacts, findings, &scoring);
+            let report = if origins.len() == 1 {
+                report.with_root(origins[0].root.clone())
+            } else {
+                report.with_targets(origins)
+            };
+            let report = report
+                .with_suppressed(suppressed, warnings)
+                .with_filtered_out(filtered_out)
+                .with_skipped(skipped);
+            write_attack_layer(args.attack_layer.as_deref(), &report)?;
 
             if args.json {
                 println!("{}", serde_json::to_string_pretty(&report)?);
             } else {
-                println!("{}", report.to_human_readable());
+                println!("{}", report.to_human_readable(group_by));
             }
+            report
         }
         Command::Baseline { out } => {
-            let artifacts = collectors::collect(os)?;
-            let snapshot = engine::ArtifactSnapshot::from_artifacts(os, artifacts);
+            let (target, origin) = single_target(&targets)?;
+            let collected = collectors::collect(target.os, target.fs.as_deref())?;
+            let snapshot =
+                engine::ArtifactSnapshot::from_artifacts(origin.os, collected.artifacts).with_root(origin.root);
             std::fs::write(&out, serde_json::to_vec_pretty(&snapshot)?)?;
             println!("Wrote baseline snapshot to {}", out);
+            if collected.skipped.is_empty() {
+                return Ok(ExitCode::SUCCESS);
+            }
+            eprintln!("Partial baseline: {} source(s) could not be read:", collected.skipped.len());
+            for what in &collected.skipped {
+                eprintln!("  {}", what);
+            }
+            return Ok(ExitCode::from(EXIT_PARTIAL));
         }
-        Command::Diff { baseline, show_removed, json } => {
+        Command::Diff { baseline, show_removed, json, .. } => {
+            let (target, origin) = single_target(&targets)?;
             let base_bytes = std::fs::read(&baseline)
                 .with_context(|| format!("Could not read baseline file: {}", baseline))?;
             let base: engine::ArtifactSnapshot = serde_json::from_slice(&base_bytes)
                 .with_context(|| "Baseline JSON format invalid")?;
 
-            let current_artifacts = collectors::collect(os)?;
-            let current_snapshot = engine::ArtifactSnapshot::from_artifacts(os, current_artifacts.clone());
+            let collected = collectors::collect(target.os, target.fs.as_deref())?;
+            let current_snapshot = engine::ArtifactSnapshot::from_artifacts(origin.os, collected.artifacts)
+                .with_root(origin.root.clone());
 
             let delta = engine::diff_snapshots(&base, &current_snapshot);
             let delta_artifacts = engine::artifacts_from_delta(&delta, show_removed);
 
-            let findings = engine::run(&rules, &delta_artifacts)?;
-            let report = engine::build_report(os, delta_artifacts, findings).with_delta(delta);
+            let findings = engine::run(&rules_by_os[origin.os.as_str()], &delta_artifacts, &origin, target);
+            let (findings, filtered_out) = filter.apply(findings);
+            let (findings, suppressed) = suppress(suppressions.as_ref(), target, findings);
+            let report = engine::build_report(origin.os, delta_artifacts, findings, &scoring)
+                .with_delta(delta)
+                .with_root(origin.root.clone())
+                .with_suppressed(suppressed, warnings)
+                .with_filtered_out(filtered_out)
+                .with_skipped(collected.skipped);
+            write_attack_layer(args.attack_layer.as_deref(), &report)?;
 
             if json {
                 println!("{}", serde_json::to_string_pretty(&report)?);
             } else {
-                println!("{}", report.to_human_readable());
+                println!("{}", report.to_human_readable(group_by));
             }
+            report
         }
+        Command::Rules { .. } => unreachable!("rules subcommands are handled before collection"),
+    };
+
+    let failures = report.threshold_failures(fail_on, max_score);
+    for f in &failures {
+        eprintln!("Threshold exceeded: {}", f);
+    }
+    if !failures.is_empty() {
+        return Ok(ExitCode::from(EXIT_THRESHOLD));
+    }
+    if !report.skipped.is_empty() {
+        return Ok(ExitCode::from(EXIT_PARTIAL));
     }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Parse the `scan` / `diff` filter options.
+fn finding_filter(
+    args: &cli::FilterArgs,
+) -> Result<(engine::filter::FindingFilter, Option<engine::report::GroupBy>)> {
+    let filter = engine::filter::FindingFilter {
+        min_severity: args.min_severity.as_deref().map(|s| engine::parse_severity("--min-severity", s)).transpose()?,
+        min_confidence: args
+            .min_confidence
+            .as_deref()
+            .map(|s| engine::parse_confidence("--min-confidence", s))
+            .transpose()?,
+        tags: args.tag.clone(),
+        exclude_tags: args.exclude_tag.clone(),
+        rules: args.rule.clone(),
+        kinds: args.kind.clone(),
+        users: args.user.clone(),
+    };
+    let group_by = args.group_by.as_deref().map(engine::parse_group_by).transpose()?;
+    Ok((filter, group_by))
+}
+
+/// Split off suppressed findings; file hashes are read from the target the findings came from.
+fn suppress(
+    suppressions: Option<&engine::suppress::Suppressions>,
+    target: &collectors::ScanTarget,
+    findings: Vec<engine::report::Finding>,
+) -> (Vec<engine::report::Finding>, Vec<engine::suppress::SuppressedFinding>) {
+    match suppressions {
+        Some(s) => s.apply(findings, |path| target.read_file(path)),
+        None => (findings, vec![]),
+    }
+}
 
+fn write_attack_layer(path: Option<&str>, report: &engine::report::Report) -> Result<()> {
+    let Some(path) = path else { return Ok(()) };
+    let name = match &report.root {
+        Some(root) => format!("persist-scan: {}", root),
+        None => "persist-scan".to_string(),
+    };
+    let layer = engine::attack::navigator_layer(report, &name);
+    std::fs::write(path, serde_json::to_vec_pretty(&layer)?)
+        .with_context(|| format!("Could not write ATT&CK layer: {}", path))?;
+    eprintln!("Wrote ATT&CK Navigator layer to {}", path);
+    Ok(())
+}
+
+/// Baselines and diffs compare one system over time, so they need exactly one root.
+fn single_target(targets: &[collectors::ScanTarget]) -> Result<(&collectors::ScanTarget, engine::Origin)> {
+    match targets {
+        [t] => Ok((t, t.origin())),
+        _ => anyhow::bail!(
+            "baseline/diff work on a single system, but {} roots were found (pass a single partition as --root)",
+            targets.len()
+        ),
+    }
+}
+
+/// `rules ...` subcommands work on rule files only; nothing is collected.
+fn rules_command(args: &Args, cmd: &RulesCommand) -> Result<()> {
+    match cmd {
+        RulesCommand::List => {
+            let rules = engine::load_all_rules(args.rules_dir.as_deref()).context("Failed loading rules")?;
+            if args.json {
+                let list: Vec<serde_json::Value> = rules
+                    .iter()
+                    .map(|r| {
+                        let (origin, overrides) = match &r.origin {
+                            RuleOrigin::BuiltIn { .. } => ("built-in".to_string(), false),
+                            RuleOrigin::File { path, overrides } => (path.clone(), *overrides),
+                        };
+                        serde_json::json!({
+                            "id": r.rule.id,
+                            "os": r.rule.os,
+                            "severity": r.rule.severity,
+                            "title": r.rule.title,
+                            "origin": origin,
+                            "overrides_builtin": overrides,
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&list)?);
+                return Ok(());
+            }
+
+            let width = rules.iter().map(|r| r.rule.id.len()).max().unwrap_or(2).max(2);
+            println!("{:<7}  {:<width$}  {:<8}  ORIGIN", "OS", "ID", "SEVERITY");
+            for r in &rules {
+                let origin = match &r.origin {
+                    RuleOrigin::BuiltIn { .. } => "built-in".to_string(),
+                    RuleOrigin::File { path, overrides: true } => format!("{} (overrides built-in)", path),
+                    RuleOrigin::File { path, overrides: false } => path.clone(),
+                };
+                let severity = format!("{:?}", r.rule.severity).to_lowercase();
+                println!("{:<7}  {:<width$}  {:<8}  {}", r.rule.os, r.rule.id, severity, origin);
+            }
+            let builtin = rules.iter().filter(|r| matches!(r.origin, RuleOrigin::BuiltIn { .. })).count();
+            println!("\n{} rules: {} built-in, {} from --rules-dir", rules.len(), builtin, rules.len() - builtin);
+        }
+        RulesCommand::ImportSigma { dir, out } => {
+            let import = engine::sigma::import_sigma_dir(dir)?;
+            for imported in &import.converted {
+                let os_dir = std::path::Path::new(out).join(&imported.rule.os);
+                std::fs::create_dir_all(&os_dir)
+                    .with_context(|| format!("Could not create directory: {}", os_dir.display()))?;
+                let path = os_dir.join(format!("{}.yml", imported.rule.id));
+                std::fs::write(&path, serde_yaml::to_string(&imported.rule)?)
+                    .with_context(|| format!("Could not write rule: {}", path.display()))?;
+                println!("converted: {} -> {}", imported.file, path.display());
+            }
+            for (file, reason) in &import.skipped {
+                println!("skipped: {}: {}", file, reason);
+            }
+            let files: std::collections::BTreeSet<&str> = import.converted.iter().map(|r| r.file.as_str()).collect();
+            println!(
+                "\n{} Sigma rules converted into {} rules under {}; {} skipped",
+                files.len(),
+                import.converted.len(),
+                out,
+                import.skipped.len()
+            );
+        }
+        RulesCommand::Lint => {
+            let Some(rules_dir) = args.rules_dir.as_deref() else {
+                anyhow::bail!("rules lint checks rule files on disk; pass --rules-dir (e.g. --rules-dir ./rules)");
+            };
+            let report = engine::lint::lint_rules(rules_dir, collectors::artifact_kinds)?;
+            for issue in &report.issues {
+                println!("{}", issue);
+            }
+            println!(
+                "Checked {} rule files: {} errors, {} warnings",
+                report.files,
+                report.errors(),
+                report.warnings()
+            );
+            if report.errors() > 0 {
+                anyhow::bail!("Rule lint failed with {} error(s)", report.errors());
+            }
+        }
+        RulesCommand::Test { rule } => {
+            let mut rules = engine::load_all_rules(args.rules_dir.as_deref()).context("Failed loading rules")?;
+            if let Some(id) = rule {
+                rules.retain(|r| &r.rule.id == id);
+                if rules.is_empty() {
+                    anyhow::bail!("No rule with ID {}", id);
+                }
+            }
+
+            let outcomes = engine::rule_tests::run_rule_tests(&rules);
+            let width = outcomes.iter().map(|o| o.rule_id.len()).max().unwrap_or(4).max(4);
+            println!("{:<6}  {:<width$}  CASE", "RESULT", "RULE");
+            for o in &outcomes {
+                let result = if o.passed { "pass" } else { "FAIL" };
+                println!("{:<6}  {:<width$}  {}", result, o.rule_id, o.case);
+            }
+
+            let failed = outcomes.iter().filter(|o| !o.passed).count();
+            let untested = rules.iter().filter(|r| r.rule.tests.is_empty()).count();
+            println!(
+                "\n{} passed, {} failed; {} of {} rules have no tests",
+                outcomes.len() - failed,
+                failed,
+                untested,
+                rules.len()
+            );
+            if failed > 0 {
+                anyhow::bail!("{} rule test(s) failed", failed);
+            }
+        }
+    }
     Ok(())
 }
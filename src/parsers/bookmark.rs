@@ -0,0 +1,110 @@
+//! macOS bookmark data ("book" blobs), as stored for login items and background tasks.
+//!
+//! Only the path is recovered: the array of UTF-8 path components under key 0x1004.
+//! Layout: a header whose u32 at 0x0C gives the start of the data area; the data area
+//! starts with the offset of the first table of contents, and every TOC entry points to
+//! an item (u32 length, u32 type, payload) relative to the data area.
+
+use anyhow::{anyhow, bail, Result};
+
+const MAGIC: &[u8; 4] = b"book";
+const TOC_MAGIC: u32 = 0xFFFF_FFFE;
+const KEY_PATH: u32 = 0x1004;
+const TYPE_STRING: u32 = 0x0101;
+const TYPE_ARRAY: u32 = 0x0601;
+const MAX_TOCS: usize = 16;
+
+pub fn is_bookmark(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// POSIX path of the bookmarked file, e.g. "/Applications/Foo.app".
+pub fn bookmark_path(data: &[u8]) -> Result<Option<String>> {
+    if !is_bookmark(data) {
+        bail!("Not bookmark data (missing book signature)");
+    }
+    let base = read_u32(data, 0x0C)? as usize;
+    let area = data.get(base..).ok_or_else(|| anyhow!("Bookmark header size out of range"))?;
+
+    let mut toc = read_u32(area, 0)? as usize;
+    for _ in 0..MAX_TOCS {
+        if read_u32(area, toc + 4)? != TOC_MAGIC {
+            bail!("Bad bookmark table of contents at {:#x}", toc);
+        }
+        let next = read_u32(area, toc + 12)? as usize;
+        let count = read_u32(area, toc + 16)? as usize;
+        for i in 0..count {
+            let entry = toc + 20 + i * 12;
+            if read_u32(area, entry)? == KEY_PATH {
+                return path_components(area, read_u32(area, entry + 4)? as usize);
+            }
+        }
+        if next == 0 {
+            break;
+        }
+        toc = next;
+    }
+    Ok(None)
+}
+
+fn path_components(area: &[u8], offset: usize) -> Result<Option<String>> {
+    let (kind, payload) = item(area, offset)?;
+    if kind != TYPE_ARRAY {
+        return Ok(None);
+    }
+    let mut parts = vec![];
+    for chunk in payload.chunks_exact(4) {
+        let (kind, s) = item(area, u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as usize)?;
+        if kind == TYPE_STRING {
+            parts.push(String::from_utf8_lossy(s).to_string());
+        }
+    }
+    Ok(Some(format!("/{}", parts.join("/"))))
+}
+
+fn item(area: &[u8], offset: usize) -> Result<(u32, &[u8])> {
+    let len = read_u32(area, offset)? as usize;
+    let kind = read_u32(area, offset + 4)?;
+    let payload = slice(area, offset + 8, len)?;
+    Ok((kind, payload))
+}
+
+fn slice(buf: &[u8], start: usize, len: usize) -> Result<&[u8]> {
+    let end = start.checked_add(len).ok_or_else(|| anyhow!("Offset overflow"))?;
+    buf.get(start..end)
+        .ok_or_else(|| anyhow!("Truncated bookmark data at {:#x}", start))
+}
+
+fn read_u32(buf: &[u8], at: usize) -> Result<u32> {
+    let b = slice(buf, at, 4)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const APP: &[u8] = include_bytes!("../../tests/fixtures/macos/bookmark/app.book");
+
+    #[test]
+    fn path_from_chained_tables_of_contents() {
+        assert!(is_bookmark(APP));
+        assert_eq!(bookmark_path(APP).unwrap().as_deref(), Some("/Applications/Evil Updater.app"));
+    }
+
+    #[test]
+    fn truncated_bookmarks_are_errors() {
+        let truncated = include_bytes!("../../tests/fixtures/macos/bookmark/truncated.book");
+        let err = bookmark_path(truncated).unwrap_err().to_string();
+        assert!(err.starts_with("Truncated bookmark data"), "{}", err);
+        // Shorter prefixes fail cleanly rather than panicking. The file ends with the volume
+        // name entry's offset and reserved word, which are never read
+        for len in 0..APP.len() {
+            if let Ok(path) = bookmark_path(&APP[..len]) {
+                assert!(len >= APP.len() - 8, "{} bytes", len);
+                assert_eq!(path.as_deref(), Some("/Applications/Evil Updater.app"));
+            }
+        }
+        assert!(bookmark_path(b"alis\0\0\0\0").is_err());
+    }
+}
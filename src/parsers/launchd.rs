@@ -0,0 +1,137 @@
+//! launchd job definitions (LaunchAgents / LaunchDaemons property lists, XML or binary).
+
+use anyhow::{bail, Context, Result};
+use plist::{Dictionary, Value};
+use std::io::Cursor;
+
+#[derive(Debug, Clone, Default)]
+pub struct LaunchdJob {
+    pub label: Option<String>,
+    /// Executable; when absent launchd runs ProgramArguments[0]
+    pub program: Option<String>,
+    pub program_arguments: Vec<String>,
+    pub run_at_load: bool,
+    /// "true", "false" or the condition keys of a KeepAlive dictionary ("SuccessfulExit,PathState")
+    pub keep_alive: Option<String>,
+    pub disabled: bool,
+    pub user_name: Option<String>,
+    /// Other launch triggers present: StartInterval, StartCalendarInterval, WatchPaths, ...
+    pub triggers: Vec<String>,
+}
+
+const TRIGGER_KEYS: &[&str] = &[
+    "StartInterval",
+    "StartCalendarInterval",
+    "WatchPaths",
+    "QueueDirectories",
+    "StartOnMount",
+    "Sockets",
+    "MachServices",
+    "LaunchEvents",
+];
+
+impl LaunchdJob {
+    /// The executable launchd starts.
+    pub fn executable(&self) -> Option<&str> {
+        self.program.as_deref().or(self.program_arguments.first().map(String::as_str))
+    }
+
+    /// Command line as launchd would exec it (ProgramArguments[0] is only argv[0] when Program is set).
+    pub fn command_line(&self) -> Option<String> {
+        let exe = self.executable()?;
+        let args = self.program_arguments.iter().skip(1);
+        Some(std::iter::once(exe).chain(args.map(String::as_str)).map(quote).collect::<Vec<_>>().join(" "))
+    }
+}
+
+pub fn parse_launchd_plist(bytes: &[u8]) -> Result<LaunchdJob> {
+    let dict = parse_dictionary(bytes)?;
+
+    let program_arguments = dict
+        .get("ProgramArguments")
+        .and_then(Value::as_array)
+        .map(|a| a.iter().filter_map(Value::as_string).map(str::to_string).collect())
+        .unwrap_or_default();
+
+    let keep_alive = dict.get("KeepAlive").map(|v| match v {
+        Value::Boolean(b) => b.to_string(),
+        Value::Dictionary(d) => d.keys().cloned().collect::<Vec<_>>().join(","),
+        _ => "true".to_string(),
+    });
+
+    Ok(LaunchdJob {
+        label: string(&dict, "Label"),
+        program: string(&dict, "Program"),
+        program_arguments,
+        run_at_load: boolean(&dict, "RunAtLoad"),
+        keep_alive,
+        disabled: boolean(&dict, "Disabled"),
+        user_name: string(&dict, "UserName"),
+        triggers: TRIGGER_KEYS.iter().filter(|k| dict.contains_key(k)).map(|k| k.to_string()).collect(),
+    })
+}
+
+/// Top-level dictionary of an XML or binary property list.
+pub fn parse_dictionary(bytes: &[u8]) -> Result<Dictionary> {
+    let value = Value::from_reader(Cursor::new(bytes)).context("Invalid property list")?;
+    match value {
+        Value::Dictionary(d) => Ok(d),
+        _ => bail!("Property list root is not a dictionary"),
+    }
+}
+
+fn string(dict: &Dictionary, key: &str) -> Option<String> {
+    dict.get(key).and_then(Value::as_string).map(str::to_string)
+}
+
+fn boolean(dict: &Dictionary, key: &str) -> bool {
+    dict.get(key).and_then(Value::as_boolean).unwrap_or(false)
+}
+
+fn quote(s: &str) -> String {
+    if s.contains(' ') {
+        format!("\"{}\"", s)
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xml_job_with_program_arguments() {
+        let job = parse_launchd_plist(include_bytes!("../../tests/fixtures/macos/launchd/agent_xml.plist")).unwrap();
+        assert_eq!(job.label.as_deref(), Some("com.example.agent"));
+        assert_eq!(job.program, None);
+        assert_eq!(job.executable(), Some("/Users/bob/Library/.hidden/agent"));
+        assert_eq!(job.command_line().as_deref(), Some(r#"/Users/bob/Library/.hidden/agent --daemon "two words""#));
+        assert!(job.run_at_load);
+        assert!(!job.disabled);
+        // The conditions of a KeepAlive dictionary, in key order
+        assert_eq!(job.keep_alive.as_deref(), Some("NetworkState,SuccessfulExit"));
+        assert_eq!(job.triggers, ["StartInterval"]);
+    }
+
+    #[test]
+    fn binary_job_with_program() {
+        let job = parse_launchd_plist(include_bytes!("../../tests/fixtures/macos/launchd/daemon_binary.plist")).unwrap();
+        assert_eq!(job.label.as_deref(), Some("com.example.helper"));
+        // ProgramArguments[0] is only argv[0] when Program is set
+        assert_eq!(job.executable(), Some("/usr/local/libexec/helper"));
+        assert_eq!(job.command_line().as_deref(), Some("/usr/local/libexec/helper -v"));
+        assert!(!job.run_at_load);
+        assert_eq!(job.keep_alive.as_deref(), Some("true"));
+        assert_eq!(job.user_name.as_deref(), Some("root"));
+        assert!(job.disabled);
+        assert_eq!(job.triggers, ["WatchPaths"]);
+    }
+
+    #[test]
+    fn invalid_plists_are_errors() {
+        assert!(parse_launchd_plist(include_bytes!("../../tests/fixtures/macos/launchd/invalid.plist")).is_err());
+        let err = parse_dictionary(br#"<plist version="1.0"><array/></plist>"#).unwrap_err();
+        assert_eq!(err.to_string(), "Property list root is not a dictionary");
+    }
+}
@@ -0,0 +1,259 @@
+//! Windows Shell Link (.lnk) files, per MS-SHLLINK.
+//!
+//! The target is taken from LinkInfo (local base path or network share), falling back to
+//! the EnvironmentVariableDataBlock and then the relative path. The LinkTargetIDList is
+//! skipped: it is only needed for shortcuts to virtual folders.
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::util::text::utf16le;
+
+const HEADER_SIZE: u32 = 0x4C;
+const LINK_CLSID: [u8; 16] = [
+    0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46,
+];
+
+const HAS_LINK_TARGET_ID_LIST: u32 = 0x01;
+const HAS_LINK_INFO: u32 = 0x02;
+const HAS_NAME: u32 = 0x04;
+const HAS_RELATIVE_PATH: u32 = 0x08;
+const HAS_WORKING_DIR: u32 = 0x10;
+const HAS_ARGUMENTS: u32 = 0x20;
+const HAS_ICON_LOCATION: u32 = 0x40;
+const IS_UNICODE: u32 = 0x80;
+
+const VOLUME_ID_AND_LOCAL_BASE_PATH: u32 = 0x01;
+const COMMON_NETWORK_RELATIVE_LINK: u32 = 0x02;
+
+const ENVIRONMENT_BLOCK_SIGNATURE: u32 = 0xA000_0001;
+
+#[derive(Debug, Clone, Default)]
+pub struct ShellLink {
+    pub target: Option<String>,
+    pub arguments: Option<String>,
+    pub working_dir: Option<String>,
+    pub icon_location: Option<String>,
+    pub relative_path: Option<String>,
+    pub description: Option<String>,
+}
+
+impl ShellLink {
+    /// Target and arguments as they would be executed.
+    pub fn command_line(&self) -> Option<String> {
+        let target = self.target.as_deref().or(self.relative_path.as_deref())?;
+        let quoted = if target.contains(' ') { format!("\"{}\"", target) } else { target.to_string() };
+        Some(match &self.arguments {
+            Some(a) => format!("{} {}", quoted, a),
+            None => quoted,
+        })
+    }
+}
+
+pub fn parse_lnk(data: &[u8]) -> Result<ShellLink> {
+    if data.len() < HEADER_SIZE as usize || read_u32(data, 0)? != HEADER_SIZE || data[4..20] != LINK_CLSID {
+        bail!("Not a shell link (bad header)");
+    }
+    let flags = read_u32(data, 0x14)?;
+    let unicode = flags & IS_UNICODE != 0;
+    let mut pos = HEADER_SIZE as usize;
+    let mut link = ShellLink::default();
+
+    if flags & HAS_LINK_TARGET_ID_LIST != 0 {
+        pos += 2 + read_u16(data, pos)? as usize;
+    }
+
+    if flags & HAS_LINK_INFO != 0 {
+        let size = read_u32(data, pos)? as usize;
+        let info = slice(data, pos, size)?;
+        link.target = link_info_target(info)?;
+        pos += size;
+    }
+
+    let mut strings = [
+        (HAS_NAME, &mut link.description),
+        (HAS_RELATIVE_PATH, &mut link.relative_path),
+        (HAS_WORKING_DIR, &mut link.working_dir),
+        (HAS_ARGUMENTS, &mut link.arguments),
+        (HAS_ICON_LOCATION, &mut link.icon_location),
+    ];
+    for (flag, field) in strings.iter_mut() {
+        if flags & *flag == 0 {
+            continue;
+        }
+        let chars = read_u16(data, pos)? as usize;
+        pos += 2;
+        let len = if unicode { chars * 2 } else { chars };
+        let raw = slice(data, pos, len)?;
+        let s = if unicode { utf16le(raw) } else { ansi(raw) };
+        **field = Some(s).filter(|s| !s.is_empty());
+        pos += len;
+    }
+
+    if link.target.is_none() {
+        link.target = environment_target(data, pos)?;
+    }
+
+    Ok(link)
+}
+
+fn link_info_target(info: &[u8]) -> Result<Option<String>> {
+    let header_size = read_u32(info, 4)? as usize;
+    let flags = read_u32(info, 8)?;
+    let suffix_offset = read_u32(info, 0x18)? as usize;
+
+    let unicode_offsets = header_size >= 0x24;
+    let suffix = if unicode_offsets && read_u32(info, 0x20)? != 0 {
+        cstr16(info, read_u32(info, 0x20)? as usize)?
+    } else {
+        cstr(info, suffix_offset)?
+    };
+
+    if flags & VOLUME_ID_AND_LOCAL_BASE_PATH != 0 {
+        let base = if unicode_offsets && read_u32(info, 0x1C)? != 0 {
+            cstr16(info, read_u32(info, 0x1C)? as usize)?
+        } else {
+            cstr(info, read_u32(info, 0x10)? as usize)?
+        };
+        return Ok(Some(join_target(&base, &suffix)));
+    }
+
+    if flags & COMMON_NETWORK_RELATIVE_LINK != 0 {
+        let net = read_u32(info, 0x14)? as usize;
+        let net_name_offset = read_u32(info, net + 8)? as usize;
+        let share = cstr(info, net + net_name_offset)?;
+        return Ok(Some(join_target(&share, &suffix)));
+    }
+
+    Ok(None)
+}
+
+/// Scan ExtraData for an EnvironmentVariableDataBlock ("%windir%\system32\cmd.exe" style targets).
+fn environment_target(data: &[u8], mut pos: usize) -> Result<Option<String>> {
+    while pos + 8 <= data.len() {
+        let size = read_u32(data, pos)? as usize;
+        if size < 8 {
+            break;
+        }
+        if read_u32(data, pos + 4)? == ENVIRONMENT_BLOCK_SIGNATURE && size >= 8 + 260 + 520 {
+            let wide = cstr16(slice(data, pos + 8 + 260, 520)?, 0)?;
+            let target = if wide.is_empty() { cstr(slice(data, pos + 8, 260)?, 0)? } else { wide };
+            return Ok(Some(target).filter(|t| !t.is_empty()));
+        }
+        pos += size;
+    }
+    Ok(None)
+}
+
+fn join_target(base: &str, suffix: &str) -> String {
+    if suffix.is_empty() {
+        base.to_string()
+    } else if base.ends_with('\\') {
+        format!("{}{}", base, suffix)
+    } else {
+        format!("{}\\{}", base, suffix)
+    }
+}
+
+fn slice(buf: &[u8], start: usize, len: usize) -> Result<&[u8]> {
+    let end = start.checked_add(len).ok_or_else(|| anyhow!("Offset overflow"))?;
+    buf.get(start..end)
+        .ok_or_else(|| anyhow!("Truncated shell link at {:#x}", start))
+}
+
+fn read_u16(buf: &[u8], at: usize) -> Result<u16> {
+    let b = slice(buf, at, 2)?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(buf: &[u8], at: usize) -> Result<u32> {
+    let b = slice(buf, at, 4)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// System codepage strings are decoded as Latin-1; exact for ASCII paths.
+fn ansi(b: &[u8]) -> String {
+    b.iter().map(|&c| c as char).collect()
+}
+
+fn cstr(buf: &[u8], at: usize) -> Result<String> {
+    let rest = buf.get(at..).ok_or_else(|| anyhow!("String offset out of range"))?;
+    let end = rest.iter().position(|&c| c == 0).unwrap_or(rest.len());
+    Ok(ansi(&rest[..end]))
+}
+
+fn cstr16(buf: &[u8], at: usize) -> Result<String> {
+    let rest = buf.get(at..).ok_or_else(|| anyhow!("String offset out of range"))?;
+    let end = rest
+        .chunks_exact(2)
+        .position(|c| c == [0, 0])
+        .map(|i| i * 2)
+        .unwrap_or(rest.len() & !1);
+    Ok(utf16le(&rest[..end]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Generated by tests/fixtures/make_fixtures.py
+    const LOCAL_PATH: &[u8] = include_bytes!("../../tests/fixtures/lnk/local_path.lnk");
+    const RELATIVE_PATH: &[u8] = include_bytes!("../../tests/fixtures/lnk/relative_path.lnk");
+    const ARGUMENTS: &[u8] = include_bytes!("../../tests/fixtures/lnk/arguments.lnk");
+    const UNICODE: &[u8] = include_bytes!("../../tests/fixtures/lnk/unicode.lnk");
+    const ENVIRONMENT: &[u8] = include_bytes!("../../tests/fixtures/lnk/environment.lnk");
+    const BAD_HEADER: &[u8] = include_bytes!("../../tests/fixtures/lnk/bad_header.lnk");
+    const TRUNCATED: &[u8] = include_bytes!("../../tests/fixtures/lnk/truncated.lnk");
+
+    #[test]
+    fn link_info_local_base_path() {
+        let link = parse_lnk(LOCAL_PATH).unwrap();
+        assert_eq!(link.target.as_deref(), Some(r"C:\Program Files\Contoso\start.bat"));
+        assert_eq!(link.working_dir.as_deref(), Some(r"C:\Program Files\Contoso"));
+        assert_eq!(link.arguments, None);
+        assert_eq!(link.command_line().as_deref(), Some(r#""C:\Program Files\Contoso\start.bat""#));
+    }
+
+    #[test]
+    fn relative_path_and_working_dir() {
+        let link = parse_lnk(RELATIVE_PATH).unwrap();
+        assert_eq!(link.target, None);
+        assert_eq!(link.relative_path.as_deref(), Some(r"..\..\..\..\Tools\sync tool.cmd"));
+        assert_eq!(link.working_dir.as_deref(), Some(r"C:\Tools"));
+        assert_eq!(link.command_line().as_deref(), Some(r#""..\..\..\..\Tools\sync tool.cmd""#));
+    }
+
+    #[test]
+    fn arguments_and_ansi_strings() {
+        let link = parse_lnk(ARGUMENTS).unwrap();
+        // LocalBasePath and CommonPathSuffix are joined
+        assert_eq!(link.target.as_deref(), Some(r"C:\Windows\System32\WindowsPowerShell\v1.0\powershell.exe"));
+        assert_eq!(link.description.as_deref(), Some("Updater"));
+        assert_eq!(link.icon_location.as_deref(), Some(r"%SystemRoot%\System32\shell32.dll"));
+        assert_eq!(
+            link.command_line().as_deref(),
+            Some(r"C:\Windows\System32\WindowsPowerShell\v1.0\powershell.exe -w hidden -ep bypass -f C:\Users\bob\AppData\Roaming\u.ps1")
+        );
+    }
+
+    #[test]
+    fn unicode_link_info_and_strings() {
+        let link = parse_lnk(UNICODE).unwrap();
+        assert_eq!(link.target.as_deref(), Some(r"C:\Users\Jürgen\Документы\запуск.vbs"));
+        assert_eq!(link.arguments.as_deref(), Some("/задача «ночь»"));
+    }
+
+    #[test]
+    fn environment_block_target() {
+        let link = parse_lnk(ENVIRONMENT).unwrap();
+        assert_eq!(link.target.as_deref(), Some(r"%windir%\system32\cmd.exe"));
+        assert_eq!(link.arguments.as_deref(), Some(r"/c start /min C:\ProgramData\x.bat"));
+    }
+
+    #[test]
+    fn malformed_links_are_errors() {
+        let err = parse_lnk(BAD_HEADER).unwrap_err();
+        assert!(err.to_string().contains("bad header"), "{:#}", err);
+        assert!(parse_lnk(TRUNCATED).is_err());
+        assert!(parse_lnk(&[]).is_err());
+    }
+}
@@ -0,0 +1,8 @@
+//! Parsers for on-disk artifact formats read by offline collectors.
+
+pub mod bookmark;
+pub mod launchd;
+pub mod lnk;
+pub mod regf;
+pub mod task_xml;
+pub mod wmi;
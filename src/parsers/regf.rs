@@ -0,0 +1,404 @@
+//! Minimal read-only parser for Windows registry hive files (REGF):
+//! NTUSER.DAT, UsrClass.dat, SOFTWARE, SYSTEM, ...
+//!
+//! Only the primary file is read; pending transaction logs (.LOG1/.LOG2) are not replayed,
+//! so a dirty hive shows its last flushed state. All offsets are bounds-checked because
+//! hives come from untrusted evidence.
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::util::text::utf16le as utf16;
+
+const BASE_BLOCK_SIZE: usize = 0x1000;
+const KEY_COMP_NAME: u16 = 0x0020;
+const VALUE_COMP_NAME: u16 = 0x0001;
+const DATA_INLINE: u32 = 0x8000_0000;
+const BIG_DATA_THRESHOLD: usize = 16344;
+const MAX_LIST_DEPTH: usize = 4;
+
+pub const REG_NONE: u32 = 0;
+pub const REG_SZ: u32 = 1;
+pub const REG_EXPAND_SZ: u32 = 2;
+pub const REG_BINARY: u32 = 3;
+pub const REG_DWORD: u32 = 4;
+pub const REG_DWORD_BIG_ENDIAN: u32 = 5;
+pub const REG_MULTI_SZ: u32 = 7;
+pub const REG_QWORD: u32 = 11;
+
+pub struct Hive {
+    data: Vec<u8>,
+    root_offset: u32,
+}
+
+#[derive(Clone)]
+pub struct Key<'a> {
+    hive: &'a Hive,
+    offset: u32,
+    name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Value {
+    /// Empty for the key's default value.
+    pub name: String,
+    pub data: ValueData,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueData {
+    None,
+    String(String),
+    ExpandString(String),
+    MultiString(Vec<String>),
+    Dword(u32),
+    Qword(u64),
+    Binary(Vec<u8>),
+    Other(u32, Vec<u8>),
+}
+
+impl ValueData {
+    /// Render the value the way regedit would show it in a single line.
+    pub fn to_display_string(&self) -> String {
+        match self {
+            ValueData::None => String::new(),
+            ValueData::String(s) | ValueData::ExpandString(s) => s.clone(),
+            ValueData::MultiString(v) => v.join(" "),
+            ValueData::Dword(d) => d.to_string(),
+            ValueData::Qword(q) => q.to_string(),
+            ValueData::Binary(b) | ValueData::Other(_, b) => {
+                b.iter().map(|x| format!("{:02x}", x)).collect::<Vec<_>>().join("")
+            }
+        }
+    }
+
+    pub fn as_strings(&self) -> Vec<String> {
+        match self {
+            ValueData::String(s) | ValueData::ExpandString(s) => vec![s.clone()],
+            ValueData::MultiString(v) => v.clone(),
+            _ => vec![],
+        }
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        match self {
+            ValueData::Dword(d) => Some(*d),
+            ValueData::Qword(q) => u32::try_from(*q).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl Hive {
+    pub fn parse(data: Vec<u8>) -> Result<Self> {
+        if data.len() < BASE_BLOCK_SIZE + 4 || &data[0..4] != b"regf" {
+            bail!("Not a registry hive (missing regf signature)");
+        }
+        let root_offset = read_u32(&data, 0x24)?;
+        let hive = Self { data, root_offset };
+        hive.root()?;
+        Ok(hive)
+    }
+
+    pub fn root(&self) -> Result<Key<'_>> {
+        self.key_at(self.root_offset)
+    }
+
+    /// Open a key by backslash-separated path relative to the hive root (case-insensitive).
+    pub fn open(&self, path: &str) -> Result<Option<Key<'_>>> {
+        self.root()?.open(path)
+    }
+
+    fn key_at(&self, offset: u32) -> Result<Key<'_>> {
+        let cell = self.cell(offset)?;
+        if cell.len() < 0x4C || &cell[0..2] != b"nk" {
+            bail!("Expected key node at offset {:#x}", offset);
+        }
+        let flags = read_u16(cell, 0x02)?;
+        let name_len = read_u16(cell, 0x48)? as usize;
+        let raw = slice(cell, 0x4C, name_len)?;
+        let name = if flags & KEY_COMP_NAME != 0 { latin1(raw) } else { utf16(raw) };
+        Ok(Key { hive: self, offset, name })
+    }
+
+    /// Data of the cell at a hive-bin relative offset (without the size header).
+    fn cell(&self, offset: u32) -> Result<&[u8]> {
+        let start = BASE_BLOCK_SIZE
+            .checked_add(offset as usize)
+            .ok_or_else(|| anyhow!("Cell offset overflow"))?;
+        let size = read_i32(&self.data, start)?;
+        let size = size.unsigned_abs() as usize;
+        if size < 4 {
+            bail!("Invalid cell size at offset {:#x}", offset);
+        }
+        slice(&self.data, start + 4, size - 4)
+    }
+
+    /// Offsets of all key nodes reachable from a subkey list cell.
+    fn subkey_offsets(&self, list_offset: u32, depth: usize, out: &mut Vec<u32>) -> Result<()> {
+        if depth > MAX_LIST_DEPTH {
+            bail!("Subkey list nesting too deep");
+        }
+        let cell = self.cell(list_offset)?;
+        let sig = slice(cell, 0, 2)?;
+        let count = read_u16(cell, 2)? as usize;
+        match sig {
+            b"lf" | b"lh" => {
+                for i in 0..count {
+                    out.push(read_u32(cell, 4 + i * 8)?);
+                }
+            }
+            b"li" => {
+                for i in 0..count {
+                    out.push(read_u32(cell, 4 + i * 4)?);
+                }
+            }
+            b"ri" => {
+                for i in 0..count {
+                    self.subkey_offsets(read_u32(cell, 4 + i * 4)?, depth + 1, out)?;
+                }
+            }
+            _ => bail!("Unknown subkey list type at offset {:#x}", list_offset),
+        }
+        Ok(())
+    }
+
+    fn value_at(&self, offset: u32) -> Result<Value> {
+        let cell = self.cell(offset)?;
+        if cell.len() < 0x14 || &cell[0..2] != b"vk" {
+            bail!("Expected value node at offset {:#x}", offset);
+        }
+        let name_len = read_u16(cell, 0x02)? as usize;
+        let size_raw = read_u32(cell, 0x04)?;
+        let data_offset = read_u32(cell, 0x08)?;
+        let kind = read_u32(cell, 0x0C)?;
+        let flags = read_u16(cell, 0x10)?;
+        let raw_name = slice(cell, 0x14, name_len)?;
+        let name = if flags & VALUE_COMP_NAME != 0 { latin1(raw_name) } else { utf16(raw_name) };
+
+        let bytes = if size_raw & DATA_INLINE != 0 {
+            let size = ((size_raw & !DATA_INLINE) as usize).min(4);
+            slice(cell, 0x08, size)?.to_vec()
+        } else {
+            self.value_bytes(data_offset, size_raw as usize)?
+        };
+
+        Ok(Value { name, data: decode(kind, bytes) })
+    }
+
+    fn value_bytes(&self, offset: u32, size: usize) -> Result<Vec<u8>> {
+        if size == 0 {
+            return Ok(vec![]);
+        }
+        let cell = self.cell(offset)?;
+        if size > BIG_DATA_THRESHOLD && cell.len() >= 8 && &cell[0..2] == b"db" {
+            let count = read_u16(cell, 2)? as usize;
+            let list = self.cell(read_u32(cell, 4)?)?;
+            // Find the segments first so the buffer is sized by data that exists, not by the
+            // (untrusted) size in the value header
+            let mut segments = Vec::with_capacity(count.min(list.len() / 4));
+            let mut available = 0;
+            for i in 0..count {
+                let seg = self.cell(read_u32(list, i * 4)?)?;
+                let seg = &seg[..seg.len().min(BIG_DATA_THRESHOLD)];
+                available += seg.len();
+                segments.push(seg);
+                if available >= size {
+                    break;
+                }
+            }
+            let mut out = Vec::with_capacity(size.min(available));
+            for seg in segments {
+                let take = (size - out.len()).min(seg.len());
+                out.extend_from_slice(&seg[..take]);
+            }
+            return Ok(out);
+        }
+        Ok(slice(cell, 0, size.min(cell.len()))?.to_vec())
+    }
+}
+
+impl<'a> Key<'a> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn subkeys(&self) -> Result<Vec<Key<'a>>> {
+        let cell = self.hive.cell(self.offset)?;
+        let count = read_u32(cell, 0x14)?;
+        if count == 0 {
+            return Ok(vec![]);
+        }
+        let mut offsets = vec![];
+        self.hive.subkey_offsets(read_u32(cell, 0x1C)?, 0, &mut offsets)?;
+        offsets.into_iter().map(|o| self.hive.key_at(o)).collect()
+    }
+
+    pub fn subkey(&self, name: &str) -> Result<Option<Key<'a>>> {
+        Ok(self.subkeys()?.into_iter().find(|k| k.name.eq_ignore_ascii_case(name)))
+    }
+
+    /// Open a descendant by backslash-separated path (case-insensitive).
+    pub fn open(&self, path: &str) -> Result<Option<Key<'a>>> {
+        let mut cur = self.clone();
+        for part in path.split('\\').filter(|p| !p.is_empty()) {
+            match cur.subkey(part)? {
+                Some(k) => cur = k,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(cur))
+    }
+
+    pub fn values(&self) -> Result<Vec<Value>> {
+        let cell = self.hive.cell(self.offset)?;
+        let count = read_u32(cell, 0x24)? as usize;
+        if count == 0 {
+            return Ok(vec![]);
+        }
+        let list = self.hive.cell(read_u32(cell, 0x28)?)?;
+        // The count is untrusted; the list cell bounds how many offsets there can be
+        let mut out = Vec::with_capacity(count.min(list.len() / 4));
+        for i in 0..count {
+            out.push(self.hive.value_at(read_u32(list, i * 4)?)?);
+        }
+        Ok(out)
+    }
+}
+
+/// Decode raw value bytes by registry type (shared with the live winreg backend).
+pub fn decode(kind: u32, bytes: Vec<u8>) -> ValueData {
+    match kind {
+        REG_NONE if bytes.is_empty() => ValueData::None,
+        REG_SZ => ValueData::String(utf16_z(&bytes)),
+        REG_EXPAND_SZ => ValueData::ExpandString(utf16_z(&bytes)),
+        REG_MULTI_SZ => ValueData::MultiString(
+            utf16(&bytes)
+                .split('\0')
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect(),
+        ),
+        REG_DWORD if bytes.len() >= 4 => ValueData::Dword(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        REG_DWORD_BIG_ENDIAN if bytes.len() >= 4 => {
+            ValueData::Dword(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        }
+        REG_QWORD if bytes.len() >= 8 => {
+            let mut b = [0u8; 8];
+            b.copy_from_slice(&bytes[..8]);
+            ValueData::Qword(u64::from_le_bytes(b))
+        }
+        REG_BINARY => ValueData::Binary(bytes),
+        other => ValueData::Other(other, bytes),
+    }
+}
+
+fn slice(buf: &[u8], start: usize, len: usize) -> Result<&[u8]> {
+    let end = start.checked_add(len).ok_or_else(|| anyhow!("Offset overflow"))?;
+    buf.get(start..end)
+        .ok_or_else(|| anyhow!("Truncated hive data at {:#x}", start))
+}
+
+fn read_u16(buf: &[u8], at: usize) -> Result<u16> {
+    let b = slice(buf, at, 2)?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(buf: &[u8], at: usize) -> Result<u32> {
+    let b = slice(buf, at, 4)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn read_i32(buf: &[u8], at: usize) -> Result<i32> {
+    Ok(read_u32(buf, at)? as i32)
+}
+
+fn latin1(b: &[u8]) -> String {
+    b.iter().map(|&c| c as char).collect()
+}
+
+/// UTF-16LE string truncated at the first NUL.
+fn utf16_z(b: &[u8]) -> String {
+    let s = utf16(b);
+    match s.find('\0') {
+        Some(i) => s[..i].to_string(),
+        None => s,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Generated by tests/fixtures/make_fixtures.py
+    const NTUSER_RUN: &[u8] = include_bytes!("../../tests/fixtures/regf/ntuser_run.dat");
+    const NTUSER_TRUNCATED: &[u8] = include_bytes!("../../tests/fixtures/regf/ntuser_truncated.dat");
+
+    const RUN: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+    const RUN_ONCE: &str = r"Software\Microsoft\Windows\CurrentVersion\RunOnce";
+
+    fn value<'a>(values: &'a [Value], name: &str) -> &'a ValueData {
+        &values.iter().find(|v| v.name == name).unwrap_or_else(|| panic!("no value {}", name)).data
+    }
+
+    #[test]
+    fn run_key_values_decode_by_type() {
+        let hive = Hive::parse(NTUSER_RUN.to_vec()).unwrap();
+        let values = hive.open(&RUN.to_lowercase()).unwrap().expect("Run key").values().unwrap();
+
+        assert_eq!(values.len(), 5);
+        assert_eq!(
+            value(&values, "Updater"),
+            &ValueData::String(r#""C:\Users\bob\AppData\Local\Temp\upd.exe" /silent"#.to_string())
+        );
+        assert_eq!(value(&values, "Expand"), &ValueData::ExpandString(r"%APPDATA%\Microsoft\svc.exe".to_string()));
+        assert_eq!(
+            value(&values, "Multi"),
+            &ValueData::MultiString(vec![r"C:\a.exe".to_string(), r"C:\b.exe".to_string()])
+        );
+        assert_eq!(value(&values, "Flag"), &ValueData::Dword(1));
+        // Value names stored as UTF-16 rather than compressed
+        assert_eq!(value(&values, "Überwachung"), &ValueData::String(r"C:\Tools\mon.exe".to_string()));
+    }
+
+    #[test]
+    fn big_data_values_are_reassembled() {
+        let hive = Hive::parse(NTUSER_RUN.to_vec()).unwrap();
+        let values = hive.open(RUN_ONCE).unwrap().expect("RunOnce key").values().unwrap();
+
+        assert_eq!(value(&values, "Cleanup"), &ValueData::String(r"cmd.exe /c del C:\Temp\x.tmp".to_string()));
+        let ValueData::String(big) = value(&values, "Big") else { panic!("Big is not REG_SZ") };
+        assert!(big.starts_with(r"C:\Windows\System32\cmd.exe /c AAAA"));
+        assert_eq!(big.len(), r"C:\Windows\System32\cmd.exe /c ".len() + 20000);
+    }
+
+    #[test]
+    fn truncated_hive_is_an_error() {
+        assert!(Hive::parse(NTUSER_TRUNCATED.to_vec()).is_err());
+        for len in [0, 4, BASE_BLOCK_SIZE, NTUSER_RUN.len() / 2, NTUSER_RUN.len() - 0x1000] {
+            assert!(Hive::parse(NTUSER_RUN[..len].to_vec()).is_err(), "cut at {:#x}", len);
+        }
+    }
+
+    #[test]
+    fn untrusted_counts_and_sizes_do_not_overallocate() {
+        let hive = Hive::parse(NTUSER_RUN.to_vec()).unwrap();
+        let run = hive.open(RUN).unwrap().unwrap().offset as usize;
+
+        // A value count far beyond the value list runs off the list and fails
+        let mut data = NTUSER_RUN.to_vec();
+        data[BASE_BLOCK_SIZE + run + 4 + 0x24..][..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let hive = Hive::parse(data).unwrap();
+        assert!(hive.open(RUN).unwrap().unwrap().values().is_err());
+
+        // A big-data value claiming ~2 GiB only yields the segments that exist
+        let mut data = NTUSER_RUN.to_vec();
+        let once = Hive::parse(data.clone()).unwrap().open(RUN_ONCE).unwrap().unwrap().offset as usize;
+        let list = read_u32(&data, BASE_BLOCK_SIZE + once + 4 + 0x28).unwrap() as usize;
+        let vk = read_u32(&data, BASE_BLOCK_SIZE + list + 4 + 4).unwrap() as usize;
+        data[BASE_BLOCK_SIZE + vk + 4 + 0x04..][..4].copy_from_slice(&0x7FFF_FFF0u32.to_le_bytes());
+        let hive = Hive::parse(data).unwrap();
+        let values = hive.open(RUN_ONCE).unwrap().unwrap().values().unwrap();
+        let ValueData::String(big) = value(&values, "Big") else { panic!("Big is not REG_SZ") };
+        assert_eq!(big.len(), r"C:\Windows\System32\cmd.exe /c ".len() + 20000);
+    }
+}
@@ -0,0 +1,195 @@
+//! Task Scheduler 2.0 job definitions (the XML files under C:\Windows\System32\Tasks).
+
+use anyhow::{bail, Context, Result};
+use roxmltree::{Document, Node};
+
+use crate::util::text::decode_text;
+
+#[derive(Debug, Clone, Default)]
+pub struct ScheduledTask {
+    pub author: Option<String>,
+    pub hidden: bool,
+    pub enabled: bool,
+    /// Trigger element names, e.g. "LogonTrigger", "BootTrigger", "CalendarTrigger"
+    pub triggers: Vec<String>,
+    pub user_id: Option<String>,
+    pub group_id: Option<String>,
+    pub logon_type: Option<String>,
+    pub run_level: Option<String>,
+    pub actions: Vec<TaskAction>,
+}
+
+#[derive(Debug, Clone)]
+pub enum TaskAction {
+    Exec {
+        command: String,
+        arguments: Option<String>,
+        working_directory: Option<String>,
+    },
+    ComHandler {
+        class_id: String,
+        data: Option<String>,
+    },
+}
+
+pub fn parse_task(bytes: &[u8]) -> Result<ScheduledTask> {
+    let text = decode_text(bytes);
+    let doc = Document::parse(text.trim_start_matches('\u{feff}')).context("Invalid task XML")?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "Task" {
+        bail!("Not a scheduled task definition (root element <{}>)", root.tag_name().name());
+    }
+
+    let mut task = ScheduledTask {
+        enabled: true,
+        ..Default::default()
+    };
+
+    if let Some(info) = child(root, "RegistrationInfo") {
+        task.author = child_text(info, "Author");
+    }
+
+    if let Some(settings) = child(root, "Settings") {
+        task.hidden = child_text(settings, "Hidden").is_some_and(|v| v.eq_ignore_ascii_case("true"));
+        task.enabled = !child_text(settings, "Enabled").is_some_and(|v| v.eq_ignore_ascii_case("false"));
+    }
+
+    if let Some(triggers) = child(root, "Triggers") {
+        task.triggers = triggers
+            .children()
+            .filter(|n| n.is_element())
+            .map(|n| n.tag_name().name().to_string())
+            .collect();
+    }
+
+    if let Some(principal) = child(root, "Principals").and_then(|p| child(p, "Principal")) {
+        task.user_id = child_text(principal, "UserId");
+        task.group_id = child_text(principal, "GroupId");
+        task.logon_type = child_text(principal, "LogonType");
+        task.run_level = child_text(principal, "RunLevel");
+    }
+
+    if let Some(actions) = child(root, "Actions") {
+        for a in actions.children().filter(|n| n.is_element()) {
+            match a.tag_name().name() {
+                "Exec" => {
+                    if let Some(command) = child_text(a, "Command") {
+                        task.actions.push(TaskAction::Exec {
+                            command,
+                            arguments: child_text(a, "Arguments"),
+                            working_directory: child_text(a, "WorkingDirectory"),
+                        });
+                    }
+                }
+                "ComHandler" => {
+                    if let Some(class_id) = child_text(a, "ClassId") {
+                        task.actions.push(TaskAction::ComHandler {
+                            class_id,
+                            data: child_text(a, "Data"),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(task)
+}
+
+fn child<'a, 'i>(node: Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+    node.children().find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn child_text(node: Node<'_, '_>, name: &str) -> Option<String> {
+    child(node, name)
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOGON_UTF8: &[u8] = include_bytes!("../../tests/fixtures/tasks/logon_utf8.xml");
+    const LOGON_UTF16: &[u8] = include_bytes!("../../tests/fixtures/tasks/logon_utf16.xml");
+    const MULTI_EXEC: &[u8] = include_bytes!("../../tests/fixtures/tasks/multi_exec.xml");
+    const COM_HANDLER: &[u8] = include_bytes!("../../tests/fixtures/tasks/com_handler.xml");
+    const MINIMAL: &[u8] = include_bytes!("../../tests/fixtures/tasks/minimal.xml");
+
+    fn exec(action: &TaskAction) -> (&str, Option<&str>, Option<&str>) {
+        match action {
+            TaskAction::Exec { command, arguments, working_directory } => {
+                (command, arguments.as_deref(), working_directory.as_deref())
+            }
+            other => panic!("expected Exec, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn utf16_and_utf8_task_files_parse_the_same() {
+        for bytes in [LOGON_UTF8, LOGON_UTF16] {
+            let task = parse_task(bytes).unwrap();
+            assert_eq!(task.author.as_deref(), Some(r"DESKTOP-01\Jürgen"));
+            assert_eq!(task.triggers, ["LogonTrigger"]);
+            assert_eq!(task.user_id.as_deref(), Some(r"DESKTOP-01\Jürgen"));
+            assert_eq!(task.logon_type.as_deref(), Some("InteractiveToken"));
+            assert_eq!(task.run_level.as_deref(), Some("LeastPrivilege"));
+            assert!(task.enabled && !task.hidden);
+            assert_eq!(task.actions.len(), 1);
+            assert_eq!(exec(&task.actions[0]), (r"C:\Users\Jürgen\AppData\Local\Tools\start.ps1", None, None));
+        }
+    }
+
+    #[test]
+    fn every_exec_action_is_kept() {
+        let task = parse_task(MULTI_EXEC).unwrap();
+        assert_eq!(task.triggers, ["BootTrigger", "CalendarTrigger"]);
+        assert_eq!(task.user_id.as_deref(), Some("S-1-5-18"));
+        assert_eq!(task.run_level.as_deref(), Some("HighestAvailable"));
+        assert!(task.hidden && !task.enabled);
+        assert_eq!(task.actions.len(), 2);
+        assert_eq!(
+            exec(&task.actions[0]),
+            (
+                r"C:\Program Files\Contoso\Sync\run.cmd",
+                Some(r#"--quiet --log "C:\ProgramData\Contoso\sync.log""#),
+                Some(r"C:\Program Files\Contoso\Sync"),
+            )
+        );
+        assert_eq!(
+            exec(&task.actions[1]),
+            (r"%windir%\System32\rundll32.exe", Some(r"C:\ProgramData\Contoso\helper.dll,Start"), None)
+        );
+    }
+
+    #[test]
+    fn com_handler_action() {
+        let task = parse_task(COM_HANDLER).unwrap();
+        assert_eq!(task.group_id.as_deref(), Some("S-1-5-32-545"));
+        assert_eq!(task.user_id, None);
+        match &task.actions[..] {
+            [TaskAction::ComHandler { class_id, data }] => {
+                assert_eq!(class_id, "{A6BA00FE-40E8-477C-B713-C64A14F18ADB}");
+                assert_eq!(data.as_deref(), Some("refresh"));
+            }
+            other => panic!("expected one ComHandler, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn principal_and_triggers_are_optional() {
+        let task = parse_task(MINIMAL).unwrap();
+        assert!(task.triggers.is_empty());
+        assert_eq!((task.user_id, task.group_id, task.logon_type, task.run_level), (None, None, None, None));
+        assert!(task.enabled && !task.hidden);
+        assert_eq!(exec(&task.actions[0]), (r#""C:\Users\bob\AppData\Roaming\upd.vbs""#, None, None));
+    }
+
+    #[test]
+    fn other_xml_is_rejected() {
+        assert!(parse_task(b"<Job><Actions/></Job>").is_err());
+        assert!(parse_task(b"<Task><Actions>").is_err());
+    }
+}
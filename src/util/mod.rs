@@ -1 +1,4 @@
 pub mod paths;
+pub mod skipped;
+pub mod text;
+pub mod users;
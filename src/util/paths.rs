@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Expand "~" on unix-like systems; on Windows returns as-is.
 pub fn expand_tilde(s: &str) -> PathBuf {
@@ -9,6 +9,102 @@ pub fn expand_tilde(s: &str) -> PathBuf {
     home.join(s.trim_start_matches("~/"))
 }
 
+/// Re-base an absolute path (as seen inside an image) onto an alternate filesystem root.
+/// "/etc/passwd" under root "/mnt/img" becomes "/mnt/img/etc/passwd".
+pub fn rebase(root: &Path, p: &Path) -> PathBuf {
+    let rel: PathBuf = p
+        .components()
+        .filter(|c| matches!(c, std::path::Component::Normal(_)))
+        .collect();
+    root.join(rel)
+}
+
+/// Render an image path ("/Users/bob/x.lnk") as it appears on the Windows system ("C:\Users\bob\x.lnk").
+/// Offline images are assumed to be the system drive.
+pub fn to_windows_path(p: &str) -> String {
+    format!("C:{}", p.replace('/', "\\"))
+}
+
+/// Resolve a Windows service ImagePath / ServiceDll to a plain executable path:
+/// - strips quotes and arguments (unquoted paths are cut after the first .exe/.dll/.sys)
+/// - expands "\SystemRoot\", "%SystemRoot%", "%windir%" and drops "\??\"
+/// - roots bare "system32\..." paths under C:\Windows
+pub fn normalize_windows_image_path(s: &str) -> Option<String> {
+    let c = s.trim();
+    if c.is_empty() {
+        return None;
+    }
+
+    let p = match c.strip_prefix('"') {
+        Some(rest) => rest.split('"').next().unwrap_or(rest),
+        None => unquoted_executable(c),
+    };
+    normalize_windows_path(p)
+}
+
+/// Normalize a field that already holds just a path (a task's Command, a shortcut target):
+/// like `normalize_windows_image_path` but the whole value is the path, spaces included.
+/// Surrounding quotes are dropped.
+pub fn normalize_windows_path(s: &str) -> Option<String> {
+    let c = s.trim();
+    let c = c.strip_prefix('"').and_then(|r| r.strip_suffix('"')).unwrap_or(c).trim();
+    if c.is_empty() {
+        return None;
+    }
+
+    let mut p = c.to_string();
+    if let Some(rest) = strip_prefix_ci(&p, "\\??\\") {
+        p = rest.to_string();
+    }
+    for prefix in ["\\SystemRoot\\", "%SystemRoot%\\", "%windir%\\"] {
+        if let Some(rest) = strip_prefix_ci(&p, prefix) {
+            p = format!("C:\\Windows\\{}", rest);
+        }
+    }
+    if strip_prefix_ci(&p, "system32\\").is_some() || strip_prefix_ci(&p, "SysWOW64\\").is_some() {
+        p = format!("C:\\Windows\\{}", p);
+    }
+
+    Some(p)
+}
+
+/// An unquoted command whose executable path contains spaces; Windows will try
+/// "C:\Program.exe", "C:\Program Files\My.exe", ... in turn (hijackable).
+pub fn is_unquoted_path_with_spaces(s: &str) -> bool {
+    let c = s.trim();
+    !c.starts_with('"') && unquoted_executable(c).contains(' ')
+}
+
+/// Text up to the first token ending in a known executable extension, else the first token.
+fn unquoted_executable(c: &str) -> &str {
+    let lower = c.to_ascii_lowercase();
+    let mut best: Option<usize> = None;
+    for ext in [".exe", ".dll", ".sys"] {
+        let mut from = 0;
+        while let Some(i) = lower[from..].find(ext) {
+            let end = from + i + ext.len();
+            if lower[end..].chars().next().is_none_or(char::is_whitespace) {
+                best = Some(best.map_or(end, |b| b.min(end)));
+                break;
+            }
+            from = end;
+        }
+    }
+    match best {
+        Some(end) => &c[..end],
+        None => c.split_whitespace().next().unwrap_or(c),
+    }
+}
+
+fn strip_prefix_ci<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    if head.eq_ignore_ascii_case(prefix) {
+        Some(&s[prefix.len()..])
+    } else {
+        None
+    }
+}
+
 /// Very conservative "best effort" parsing:
 /// - If command starts with quoted string, take that
 /// - Else take first token
@@ -18,8 +114,7 @@ pub fn extract_executable_path_guess(command: &str) -> Option<String> {
         return None;
     }
 
-    if c.starts_with('"') {
-        let rest = &c[1..];
+    if let Some(rest) = c.strip_prefix('"') {
         if let Some(end) = rest.find('"') {
             let p = &rest[..end];
             return Some(p.to_string());
@@ -29,3 +124,34 @@ pub fn extract_executable_path_guess(command: &str) -> Option<String> {
     let first = c.split_whitespace().next()?;
     Some(first.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_paths_drop_arguments() {
+        assert_eq!(
+            normalize_windows_image_path(r"C:\Program Files\Foo\svc.exe -k netsvcs").as_deref(),
+            Some(r"C:\Program Files\Foo\svc.exe")
+        );
+        assert_eq!(
+            normalize_windows_image_path(r#""%SystemRoot%\System32\svchost.exe" -k x"#).as_deref(),
+            Some(r"C:\Windows\System32\svchost.exe")
+        );
+        assert_eq!(normalize_windows_image_path(r"system32\drivers\x.sys").as_deref(), Some(r"C:\Windows\system32\drivers\x.sys"));
+    }
+
+    #[test]
+    fn plain_paths_keep_spaces() {
+        for (input, want) in [
+            (r"C:\Program Files\Foo\run.cmd", r"C:\Program Files\Foo\run.cmd"),
+            (r#""C:\Program Files\Foo\start.ps1""#, r"C:\Program Files\Foo\start.ps1"),
+            (r"%windir%\Temp\my script.vbs", r"C:\Windows\Temp\my script.vbs"),
+            (r"\??\C:\Tools\a b.js", r"C:\Tools\a b.js"),
+        ] {
+            assert_eq!(normalize_windows_path(input).as_deref(), Some(want), "{}", input);
+        }
+        assert_eq!(normalize_windows_path(" \"\" "), None);
+    }
+}
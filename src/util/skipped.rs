@@ -0,0 +1,19 @@
+//! Sources the collectors had to skip: hives that do not parse, keys with corrupt cells, folders
+//! that cannot be read, archive entries over the size limit. They are noted where they happen,
+//! deep inside collectors and the VFS, and taken by `collectors::collect` for the report.
+
+use std::cell::RefCell;
+
+thread_local! {
+    static SKIPPED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Note a source that could not be read; collection goes on without it.
+pub fn record(what: String) {
+    SKIPPED.with(|s| s.borrow_mut().push(what));
+}
+
+/// Everything noted since the last call.
+pub fn take() -> Vec<String> {
+    SKIPPED.with(|s| std::mem::take(&mut *s.borrow_mut()))
+}
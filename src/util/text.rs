@@ -0,0 +1,20 @@
+/// Decode a text file that may be UTF-8 or UTF-16LE (with or without BOM), as Windows
+/// tools often write UTF-16.
+pub fn decode_text(bytes: &[u8]) -> String {
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8_lossy(rest).to_string();
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return utf16le(rest);
+    }
+    // "<?" written as UTF-16LE without a BOM
+    if bytes.len() >= 4 && bytes[1] == 0 && bytes[3] == 0 && bytes[0] != 0 {
+        return utf16le(bytes);
+    }
+    String::from_utf8_lossy(bytes).to_string()
+}
+
+pub fn utf16le(b: &[u8]) -> String {
+    let units: Vec<u16> = b.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
@@ -0,0 +1,26 @@
+/// A local account as listed in an `/etc/passwd` file.
+#[derive(Debug, Clone)]
+pub struct UserAccount {
+    pub name: String,
+    pub home: String,
+}
+
+/// Parse a passwd file (name:pw:uid:gid:gecos:home:shell), skipping malformed lines.
+pub fn parse_passwd(content: &str) -> Vec<UserAccount> {
+    let mut out = vec![];
+    for line in content.lines() {
+        let l = line.trim();
+        if l.is_empty() || l.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = l.split(':').collect();
+        if fields.len() < 7 || fields[0].is_empty() || fields[5].is_empty() {
+            continue;
+        }
+        out.push(UserAccount {
+            name: fields[0].to_string(),
+            home: fields[5].to_string(),
+        });
+    }
+    out
+}
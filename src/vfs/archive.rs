@@ -0,0 +1,341 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::{normalize, DirEntry, Vfs};
+use crate::util::skipped;
+
+/// Files larger than this are listed but not read (hives, scripts and plists are far smaller).
+const MAX_FILE_SIZE: u64 = 512 * 1024 * 1024;
+
+/// A .tar.gz is decompressed to a temporary tar of at most this size.
+const MAX_SPOOL_SIZE: u64 = 32 * 1024 * 1024 * 1024;
+
+/// Triage bundle (.tar, .tar.gz/.tgz, .zip) exposed as a filesystem. Only entry metadata is
+/// kept in memory; contents are read from the archive when asked for.
+/// Only regular files are kept; links and device entries are ignored.
+pub struct ArchiveFs {
+    label: String,
+    source: Mutex<Source>,
+    /// Declared after `source` so the handle is closed before the file is removed
+    _spool: Option<Spool>,
+    index: Index,
+}
+
+enum Source {
+    Tar(File),
+    Zip(Box<zip::ZipArchive<File>>),
+}
+
+#[derive(Default)]
+struct Index {
+    files: BTreeMap<String, Entry>,
+    dirs: BTreeSet<String>,
+}
+
+struct Entry {
+    /// Size given in the archive header; reads never go past it
+    size: u64,
+    /// Data offset in a tar, entry index in a zip
+    location: u64,
+}
+
+/// Decompressed copy of a .tar.gz, removed when the archive is dropped.
+struct Spool {
+    path: PathBuf,
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl ArchiveFs {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Failed reading archive {}", path.display()))?;
+        let (source, spool, index) =
+            open_source(file).with_context(|| format!("Failed parsing archive {}", path.display()))?;
+        Ok(Self {
+            label: format!("archive:{}", path.display()),
+            source: Mutex::new(source),
+            _spool: spool,
+            index,
+        })
+    }
+
+    /// Read at most the entry's declared size; the buffer grows with what is actually read.
+    fn read_entry(&self, entry: &Entry) -> Result<Vec<u8>> {
+        let mut source = self.source.lock().map_err(|_| anyhow!("archive reader poisoned"))?;
+        let mut buf = vec![];
+        match &mut *source {
+            Source::Tar(file) => {
+                file.seek(SeekFrom::Start(entry.location))?;
+                file.take(entry.size).read_to_end(&mut buf)?;
+            }
+            Source::Zip(zip) => {
+                let f = zip.by_index(entry.location as usize)?;
+                f.take(entry.size).read_to_end(&mut buf)?;
+            }
+        }
+        Ok(buf)
+    }
+}
+
+fn open_source(mut file: File) -> Result<(Source, Option<Spool>, Index)> {
+    let mut magic = [0u8; 4];
+    let n = file.read(&mut magic)?;
+    let magic = &magic[..n];
+    file.rewind()?;
+
+    let mut index = Index::default();
+    index.dirs.insert("/".to_string());
+    if magic.starts_with(b"PK\x03\x04") || magic.starts_with(b"PK\x05\x06") {
+        let mut zip = zip::ZipArchive::new(file)?;
+        index.add_zip(&mut zip)?;
+        Ok((Source::Zip(Box::new(zip)), None, index))
+    } else if magic.starts_with(&[0x1f, 0x8b]) {
+        let (spool, tar) = spool_gzip(file)?;
+        index.add_tar(tar.try_clone()?)?;
+        Ok((Source::Tar(tar), Some(spool), index))
+    } else {
+        index.add_tar(file.try_clone()?)?;
+        Ok((Source::Tar(file), None, index))
+    }
+}
+
+impl Index {
+    fn add_tar(&mut self, file: File) -> Result<()> {
+        let mut ar = tar::Archive::new(file);
+        for entry in ar.entries()? {
+            let entry = entry?;
+            let name = entry.path()?.to_string_lossy().to_string();
+            match entry.header().entry_type() {
+                tar::EntryType::Directory => self.add_dir(&name),
+                t if t.is_file() => {
+                    let location = entry.raw_file_position();
+                    self.add_file(&name, Entry { size: entry.size(), location });
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn add_zip(&mut self, zip: &mut zip::ZipArchive<File>) -> Result<()> {
+        for i in 0..zip.len() {
+            let f = zip.by_index_raw(i)?;
+            let name = f.name().to_string();
+            if f.is_dir() {
+                self.add_dir(&name);
+            } else if f.is_file() {
+                self.add_file(&name, Entry { size: f.size(), location: i as u64 });
+            }
+        }
+        Ok(())
+    }
+
+    fn add_dir(&mut self, name: &str) {
+        let mut p = normalize(name);
+        while p != "/" {
+            if !self.dirs.insert(p.clone()) {
+                break;
+            }
+            p = parent(&p);
+        }
+    }
+
+    fn add_file(&mut self, name: &str, entry: Entry) {
+        let p = normalize(name);
+        if p == "/" {
+            return;
+        }
+        self.add_dir(&parent(&p));
+        self.files.insert(p, entry);
+    }
+}
+
+/// Decompress a gzip stream to a temporary tar, refusing to go past MAX_SPOOL_SIZE.
+fn spool_gzip(file: File) -> Result<(Spool, File)> {
+    let path = std::env::temp_dir().join(format!("persist-scan-{}-{}.tar", std::process::id(), spool_id()));
+    let spool = Spool { path };
+    let mut out = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&spool.path)
+        .with_context(|| format!("Failed creating {}", spool.path.display()))?;
+
+    let mut gz = flate2::read::GzDecoder::new(file).take(MAX_SPOOL_SIZE + 1);
+    let written = std::io::copy(&mut gz, &mut out)?;
+    if written > MAX_SPOOL_SIZE {
+        return Err(anyhow!("decompresses to more than {} GiB", MAX_SPOOL_SIZE >> 30));
+    }
+    out.rewind()?;
+    Ok((spool, out))
+}
+
+fn spool_id() -> usize {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+fn parent(p: &str) -> String {
+    match p.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(i) => p[..i].to_string(),
+    }
+}
+
+impl Vfs for ArchiveFs {
+    fn read(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        let path = normalize(path);
+        let Some(entry) = self.index.files.get(&path) else { return Ok(None) };
+        if entry.size > MAX_FILE_SIZE {
+            skipped::record(format!(
+                "{}: {} ({} MiB is over the {} MiB limit)",
+                self.label,
+                path,
+                entry.size >> 20,
+                MAX_FILE_SIZE >> 20
+            ));
+            return Ok(None);
+        }
+        let data = self.read_entry(entry).with_context(|| format!("Failed reading {} from {}", path, self.label))?;
+        Ok(Some(data))
+    }
+
+    fn size(&self, path: &str) -> Result<Option<u64>> {
+        Ok(self.index.files.get(&normalize(path)).map(|e| e.size))
+    }
+
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>> {
+        let dir = normalize(path);
+        if !self.index.dirs.contains(&dir) {
+            return Ok(vec![]);
+        }
+        let prefix = if dir == "/" { "/".to_string() } else { format!("{}/", dir) };
+
+        let mut out = vec![];
+        for d in self.index.dirs.range(prefix.clone()..) {
+            let Some(rest) = d.strip_prefix(&prefix) else { break };
+            if !rest.is_empty() && !rest.contains('/') {
+                out.push(DirEntry { name: rest.to_string(), is_dir: true });
+            }
+        }
+        for f in self.index.files.range(prefix.clone()..).map(|(k, _)| k) {
+            let Some(rest) = f.strip_prefix(&prefix) else { break };
+            if !rest.contains('/') {
+                out.push(DirEntry { name: rest.to_string(), is_dir: false });
+            }
+        }
+        out.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(out)
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        self.index.dirs.contains(&normalize(path))
+    }
+
+    fn describe(&self) -> String {
+        self.label.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn scratch(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("persist-scan-archive-{}-{}", std::process::id(), name))
+    }
+
+    fn tar_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut b = tar::Builder::new(vec![]);
+        for (name, data) in files {
+            let mut h = tar::Header::new_gnu();
+            h.set_size(data.len() as u64);
+            h.set_mode(0o644);
+            h.set_cksum();
+            b.append_data(&mut h, name, *data).unwrap();
+        }
+        b.into_inner().unwrap()
+    }
+
+    fn check_bundle(fs: &ArchiveFs) {
+        assert_eq!(fs.read("/etc/passwd").unwrap().as_deref(), Some(&b"root:x:0:0::/root:/bin/sh\n"[..]));
+        assert_eq!(fs.read("home/alice/.config/autostart/a.desktop").unwrap().as_deref(), Some(&b"Exec=/tmp/x\n"[..]));
+        assert_eq!(fs.size("/etc/passwd").unwrap(), Some(26));
+        assert_eq!(fs.read("/etc/shadow").unwrap(), None);
+        assert!(fs.is_dir("/home/alice/.config"));
+        let names: Vec<String> = fs.read_dir("/").unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["etc", "home"]);
+    }
+
+    const FILES: &[(&str, &[u8])] = &[
+        ("etc/passwd", b"root:x:0:0::/root:/bin/sh\n"),
+        ("home/alice/.config/autostart/a.desktop", b"Exec=/tmp/x\n"),
+    ];
+
+    #[test]
+    fn reads_tar_entries_on_demand() {
+        let path = scratch("plain.tar");
+        std::fs::write(&path, tar_bytes(FILES)).unwrap();
+        check_bundle(&ArchiveFs::open(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn spools_tar_gz_and_removes_the_spool() {
+        let path = scratch("bundle.tgz");
+        let mut gz = flate2::write::GzEncoder::new(vec![], flate2::Compression::fast());
+        gz.write_all(&tar_bytes(FILES)).unwrap();
+        std::fs::write(&path, gz.finish().unwrap()).unwrap();
+
+        let fs = ArchiveFs::open(&path).unwrap();
+        check_bundle(&fs);
+        let spool = fs._spool.as_ref().unwrap().path.clone();
+        assert!(spool.exists());
+        drop(fs);
+        assert!(!spool.exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reads_zip_entries_on_demand() {
+        let path = scratch("bundle.zip");
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+        for (name, data) in FILES {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        std::fs::write(&path, zip.finish().unwrap().into_inner()).unwrap();
+        check_bundle(&ArchiveFs::open(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn truncated_tar_with_a_huge_declared_size_is_an_error() {
+        let mut bytes = tar_bytes(&[("etc/passwd", b"root")]);
+        // Claim 400 MiB of data that is not there
+        let mut h = tar::Header::new_gnu();
+        h.set_path("etc/big").unwrap();
+        h.set_size(400 << 20);
+        h.set_cksum();
+        let end = bytes.len() - 1024;
+        bytes.truncate(end);
+        bytes.extend_from_slice(h.as_bytes());
+        bytes.extend_from_slice(&[0u8; 512]);
+
+        let path = scratch("truncated.tar");
+        std::fs::write(&path, &bytes).unwrap();
+        let err = ArchiveFs::open(&path).err().expect("truncated archive must not open");
+        assert!(format!("{:#}", err).contains("unexpected EOF"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
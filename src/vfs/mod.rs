@@ -0,0 +1,258 @@
+use anyhow::{Context, Result};
+use std::path::{Component, Path, PathBuf};
+
+pub mod archive;
+
+pub use archive::ArchiveFs;
+
+use crate::util::paths::rebase;
+
+/// Read-only view of a filesystem that collectors read from.
+/// Paths are absolute as seen inside the scanned system ("/etc/passwd"), using '/' separators.
+pub trait Vfs: Send + Sync {
+    /// File contents, or None if the file does not exist.
+    fn read(&self, path: &str) -> Result<Option<Vec<u8>>>;
+
+    /// File size in bytes, or None if the file does not exist; lets callers skip large files
+    /// without reading them.
+    fn size(&self, path: &str) -> Result<Option<u64>> {
+        Ok(self.read(path)?.map(|b| b.len() as u64))
+    }
+
+    /// Direct children of a directory; empty if it does not exist.
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>>;
+
+    fn is_dir(&self, path: &str) -> bool;
+
+    /// Human-readable description of where files come from (for reports).
+    fn describe(&self) -> String;
+
+    fn read_to_string(&self, path: &str) -> Result<Option<String>> {
+        Ok(self.read(path)?.map(|b| String::from_utf8_lossy(&b).to_string()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+impl DirEntry {
+    /// Full path of this entry given the directory it was listed from.
+    pub fn path_in(&self, dir: &str) -> String {
+        join(dir, &self.name)
+    }
+}
+
+/// Join an image path and a child name with '/'.
+pub fn join(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name.trim_start_matches('/'))
+}
+
+/// Resolve a path component by component, ignoring case (Windows evidence mounted or
+/// extracted on a case-sensitive filesystem). Returns the path as it exists, if it does.
+pub fn find_case_insensitive(fs: &dyn Vfs, path: &str) -> Result<Option<String>> {
+    let mut cur = "/".to_string();
+    for part in normalize(path).split('/').filter(|p| !p.is_empty()) {
+        let entries = fs.read_dir(&cur)?;
+        let hit = entries
+            .iter()
+            .find(|e| e.name == part)
+            .or_else(|| entries.iter().find(|e| e.name.eq_ignore_ascii_case(part)));
+        match hit {
+            Some(e) => cur = e.path_in(&cur),
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(cur))
+}
+
+/// Normalise an archive or user supplied path to "/a/b/c" form.
+pub fn normalize(path: &str) -> String {
+    let parts: Vec<&str> = path
+        .split(['/', '\\'])
+        .filter(|p| !p.is_empty() && *p != "." && *p != "..")
+        .collect();
+    format!("/{}", parts.join("/"))
+}
+
+/// A directory on the host acting as the filesystem root.
+/// For offline roots symlinks are resolved component by component inside the root (absolute
+/// targets are taken relative to it, `..` stops at it), so links inside an image cannot escape to the host.
+pub struct HostFs {
+    root: PathBuf,
+    follow_links: bool,
+}
+
+impl HostFs {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into(), follow_links: false }
+    }
+
+    /// The live host's own filesystem.
+    pub fn live() -> Self {
+        Self { root: PathBuf::from("/"), follow_links: true }
+    }
+
+    /// Host path of an image path; None if it cannot be resolved (symlink loop).
+    fn resolve(&self, path: &str) -> Option<PathBuf> {
+        if self.follow_links {
+            return Some(rebase(&self.root, Path::new(&normalize(path))));
+        }
+        self.resolve_in_root(path)
+    }
+
+    /// Host path of an image path with every symlink resolved as if the root were `/`.
+    /// None for link loops.
+    fn resolve_in_root(&self, path: &str) -> Option<PathBuf> {
+        let mut todo: Vec<String> = path.split(['/', '\\']).rev().map(str::to_string).collect();
+        let mut parts: Vec<String> = vec![];
+        let mut links = 0;
+        while let Some(part) = todo.pop() {
+            match part.as_str() {
+                "" | "." => continue,
+                ".." => {
+                    parts.pop();
+                    continue;
+                }
+                _ => {}
+            }
+            let candidate = parts.iter().fold(self.root.clone(), |p, c| p.join(c)).join(&part);
+            let is_link = std::fs::symlink_metadata(&candidate).is_ok_and(|m| m.file_type().is_symlink());
+            if !is_link {
+                parts.push(part);
+                continue;
+            }
+            links += 1;
+            if links > MAX_SYMLINKS {
+                return None;
+            }
+            let target = std::fs::read_link(&candidate).ok()?;
+            if target.has_root() {
+                parts.clear();
+            }
+            let target_parts: Vec<String> = target
+                .components()
+                .filter_map(|c| match c {
+                    Component::Normal(s) => Some(s.to_string_lossy().to_string()),
+                    Component::ParentDir => Some("..".to_string()),
+                    _ => None,
+                })
+                .collect();
+            todo.extend(target_parts.into_iter().rev());
+        }
+        Some(parts.iter().fold(self.root.clone(), |p, c| p.join(c)))
+    }
+
+    fn metadata(&self, p: &Path) -> Option<std::fs::Metadata> {
+        if self.follow_links {
+            std::fs::metadata(p).ok()
+        } else {
+            std::fs::symlink_metadata(p).ok()
+        }
+    }
+}
+
+/// Symlinks followed while resolving one offline path, as in Linux path lookup.
+const MAX_SYMLINKS: usize = 40;
+
+impl Vfs for HostFs {
+    fn read(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        let Some(p) = self.resolve(path) else { return Ok(None) };
+        match self.metadata(&p) {
+            Some(m) if m.is_file() => {}
+            _ => return Ok(None),
+        }
+        let bytes = std::fs::read(&p).with_context(|| format!("Failed reading {}", p.display()))?;
+        Ok(Some(bytes))
+    }
+
+    fn size(&self, path: &str) -> Result<Option<u64>> {
+        Ok(self.resolve(path).and_then(|p| self.metadata(&p)).filter(|m| m.is_file()).map(|m| m.len()))
+    }
+
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>> {
+        let Some(p) = self.resolve(path).filter(|_| self.is_dir(path)) else { return Ok(vec![]) };
+        let mut out = vec![];
+        for entry in std::fs::read_dir(&p).with_context(|| format!("Failed reading dir {}", p.display()))? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Links are listed by what they point to inside the root; dangling ones are skipped
+            let ft = match self.resolve(&join(path, &name)).and_then(|p| self.metadata(&p)) {
+                Some(m) => m.file_type(),
+                None => continue,
+            };
+            if ft.is_symlink() {
+                continue;
+            }
+            out.push(DirEntry { name, is_dir: ft.is_dir() });
+        }
+        out.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(out)
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        self.resolve(path).and_then(|p| self.metadata(&p)).is_some_and(|m| m.is_dir())
+    }
+
+    fn describe(&self) -> String {
+        self.root.display().to_string()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    /// Fresh scratch directory: `<tmp>/persist-scan-vfs-<name>-<pid>`.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("persist-scan-vfs-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn intermediate_symlinks_resolve_inside_the_root() {
+        let dir = scratch("links");
+        let root = dir.join("root");
+        let outside = dir.join("outside");
+        std::fs::create_dir_all(root.join("data/alice")).unwrap();
+        std::fs::create_dir_all(outside.join("alice")).unwrap();
+        std::fs::write(root.join("data/alice/.profile"), "image").unwrap();
+        std::fs::write(outside.join("alice/.profile"), "host").unwrap();
+
+        // Absolute link into the image, absolute link that only exists on the host,
+        // and a relative link climbing above the root
+        symlink("/data", root.join("home")).unwrap();
+        symlink(&outside, root.join("hostlink")).unwrap();
+        symlink("../../../../../outside", root.join("climb")).unwrap();
+
+        let fs = HostFs::new(&root);
+        assert_eq!(fs.read("/home/alice/.profile").unwrap().as_deref(), Some(&b"image"[..]));
+        assert!(fs.is_dir("/home/alice"));
+        assert_eq!(fs.read("/hostlink/alice/.profile").unwrap(), None);
+        assert_eq!(fs.read("/climb/alice/.profile").unwrap(), None);
+        assert!(!fs.is_dir("/hostlink"));
+
+        let names: Vec<String> = fs.read_dir("/").unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["data", "home"]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn symlink_loops_are_not_found() {
+        let dir = scratch("loop");
+        symlink("/b", dir.join("a")).unwrap();
+        symlink("/a", dir.join("b")).unwrap();
+
+        let fs = HostFs::new(&dir);
+        assert_eq!(fs.read("/a/x").unwrap(), None);
+        assert!(fs.read_dir("/").unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
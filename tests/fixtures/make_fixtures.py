@@ -0,0 +1,541 @@
+#!/usr/bin/env python3
+"""Regenerate the binary parser fixtures under tests/fixtures/.
+
+The fixtures are checked in; run this only when changing them:
+
+    python3 tests/fixtures/make_fixtures.py
+"""
+
+import os
+import plistlib
+import struct
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+
+
+def write(rel, data):
+    path = os.path.join(HERE, rel)
+    os.makedirs(os.path.dirname(path), exist_ok=True)
+    with open(path, "wb") as f:
+        f.write(data)
+
+
+def utf16(s):
+    return s.encode("utf-16-le")
+
+
+def utf16z(s):
+    return utf16(s) + b"\0\0"
+
+
+# --- Registry hives (src/parsers/regf.rs) -------------------------------------------------------
+
+REG_SZ, REG_EXPAND_SZ, REG_DWORD, REG_MULTI_SZ = 1, 2, 4, 7
+BIG_DATA_THRESHOLD = 16344
+
+
+class HiveBuilder:
+    """Cells are appended to a single hive bin; offsets are relative to the bin start."""
+
+    def __init__(self):
+        self.bin = bytearray(b"\0" * 0x20)
+
+    def cell(self, data):
+        offset = len(self.bin)
+        size = (len(data) + 4 + 7) & ~7
+        self.bin += struct.pack("<i", -size) + data + b"\0" * (size - 4 - len(data))
+        return offset
+
+    def key(self, name, subkeys=(), values=(), flags=0x20):
+        subkey_list = 0xFFFFFFFF
+        if subkeys:
+            entries = b"".join(struct.pack("<I", off) + name[:4].encode("latin-1").ljust(4, b"\0")
+                               for off, name in subkeys)
+            subkey_list = self.cell(b"lf" + struct.pack("<H", len(subkeys)) + entries)
+        value_list = 0xFFFFFFFF
+        if values:
+            value_list = self.cell(b"".join(struct.pack("<I", v) for v in values))
+        raw = name.encode("latin-1")
+        nk = bytearray(0x4C)
+        nk[0:2] = b"nk"
+        struct.pack_into("<H", nk, 0x02, flags)
+        struct.pack_into("<I", nk, 0x10, 0xFFFFFFFF)
+        struct.pack_into("<I", nk, 0x14, len(subkeys))
+        struct.pack_into("<I", nk, 0x1C, subkey_list)
+        struct.pack_into("<I", nk, 0x20, 0xFFFFFFFF)
+        struct.pack_into("<I", nk, 0x24, len(values))
+        struct.pack_into("<I", nk, 0x28, value_list)
+        struct.pack_into("<I", nk, 0x2C, 0xFFFFFFFF)
+        struct.pack_into("<I", nk, 0x30, 0xFFFFFFFF)
+        struct.pack_into("<H", nk, 0x48, len(raw))
+        return (self.cell(bytes(nk) + raw), name)
+
+    def value(self, name, kind, data, ascii_name=True):
+        raw = name.encode("latin-1") if ascii_name else utf16(name)
+        if len(data) <= 4:
+            size, offset = len(data) | 0x80000000, int.from_bytes(data.ljust(4, b"\0"), "little")
+        elif len(data) <= BIG_DATA_THRESHOLD:
+            size, offset = len(data), self.cell(data)
+        else:
+            chunks = [data[i:i + BIG_DATA_THRESHOLD] for i in range(0, len(data), BIG_DATA_THRESHOLD)]
+            segments = self.cell(b"".join(struct.pack("<I", self.cell(c)) for c in chunks))
+            size, offset = len(data), self.cell(b"db" + struct.pack("<HI", len(chunks), segments))
+        vk = b"vk" + struct.pack("<HIIIHH", len(raw), size, offset, kind, 1 if ascii_name else 0, 0)
+        return self.cell(vk + raw)
+
+    def tree(self, name, node, flags=0x20):
+        """A key from a dict: a "values" entry holds (name, kind, data) tuples, other entries are subkeys."""
+        values = [self.value(*v) for v in node.get("values", ())]
+        subkeys = [self.tree(k, v) for k, v in node.items() if k != "values"]
+        return self.key(name, subkeys=subkeys, values=values, flags=flags)
+
+    def hive(self, node):
+        return self.build(self.tree("ROOT", node, flags=0x2C)[0])
+
+    def build(self, root):
+        bin_size = (len(self.bin) + 0xFFF) & ~0xFFF
+        hbin = bytearray(self.bin + b"\0" * (bin_size - len(self.bin)))
+        hbin[0:4] = b"hbin"
+        struct.pack_into("<II", hbin, 4, 0, bin_size)
+        base = bytearray(0x1000)
+        base[0:4] = b"regf"
+        struct.pack_into("<IIIII", base, 0x14, 1, 5, 0, 0, 1)
+        struct.pack_into("<II", base, 0x24, root, bin_size)
+        checksum = 0
+        for (word,) in struct.iter_unpack("<I", bytes(base[:0x1FC])):
+            checksum ^= word
+        struct.pack_into("<I", base, 0x1FC, checksum)
+        return bytes(base + hbin)
+
+
+def ntuser_run(corrupt_cell=False):
+    h = HiveBuilder()
+    run = h.key("Run", values=[
+        h.value("Updater", REG_SZ, utf16z('"C:\\Users\\bob\\AppData\\Local\\Temp\\upd.exe" /silent')),
+        h.value("Expand", REG_EXPAND_SZ, utf16z("%APPDATA%\\Microsoft\\svc.exe")),
+        h.value("Multi", REG_MULTI_SZ, utf16z("C:\\a.exe") + utf16z("C:\\b.exe") + b"\0\0"),
+        h.value("Flag", REG_DWORD, struct.pack("<I", 1)),
+        h.value("Überwachung", REG_SZ, utf16z("C:\\Tools\\mon.exe"), ascii_name=False),
+    ])
+    big = "C:\\Windows\\System32\\cmd.exe /c " + "A" * 20000
+    cleanup = h.value("Cleanup", REG_SZ, utf16z("cmd.exe /c del C:\\Temp\\x.tmp"))
+    if corrupt_cell:
+        # Overwrite the value's "vk" signature: the hive and the Run key still parse, RunOnce does not
+        h.bin[cleanup + 4:cleanup + 6] = b"\xde\xad"
+    run_once = h.key("RunOnce", values=[
+        cleanup,
+        h.value("Big", REG_SZ, utf16z(big)),
+    ])
+    current = h.key("CurrentVersion", subkeys=[run, run_once])
+    windows = h.key("Windows", subkeys=[current])
+    microsoft = h.key("Microsoft", subkeys=[windows])
+    software = h.key("Software", subkeys=[microsoft])
+    root = h.key("ROOT", subkeys=[software], flags=0x2C)
+    return h.build(root[0])
+
+
+def sz(name, data, kind=REG_SZ):
+    return (name, kind, utf16z(data))
+
+
+def dword(name, data):
+    return (name, REG_DWORD, struct.pack("<I", data))
+
+
+def multi_sz(name, *items):
+    return (name, REG_MULTI_SZ, b"".join(utf16z(i) for i in items) + b"\0\0")
+
+
+def com_servers(**servers):
+    return {server: {"values": [sz("", path)]} for server, path in servers.items()}
+
+
+def software_com():
+    return HiveBuilder().hive({"Classes": {
+        "CLSID": {
+            # Shadowed by the user with a different DLL
+            "{00000001-0000-0000-0000-000000000001}": com_servers(InprocServer32="%SystemRoot%\\System32\\shell32.dll"),
+            # Stock Defender registration under ProgramData\Microsoft: not user-writable
+            "{00000002-0000-0000-0000-000000000002}": com_servers(
+                InprocServer32="C:\\ProgramData\\Microsoft\\Windows Defender\\Platform\\4.18.2\\MpOav.dll"),
+            # A vendor folder elsewhere in ProgramData is
+            "{00000003-0000-0000-0000-000000000003}": com_servers(LocalServer32="C:\\ProgramData\\Vendor\\agent.exe"),
+            # Only a LocalServer32; the user adds an InprocServer32
+            "{00000004-0000-0000-0000-000000000004}": com_servers(LocalServer32='"C:\\Program Files\\App\\app.exe" -Embedding'),
+            # The user registers the same server: not a hijack
+            "{00000005-0000-0000-0000-000000000005}": com_servers(InprocServer32="C:\\Program Files\\Same\\same.dll"),
+        },
+        "Wow6432Node": {"CLSID": {
+            "{00000006-0000-0000-0000-000000000006}": com_servers(InprocServer32="C:\\Windows\\SysWOW64\\wow.dll"),
+        }},
+    }})
+
+
+def usrclass_com():
+    return HiveBuilder().hive({
+        "CLSID": {
+            "{00000001-0000-0000-0000-000000000001}": com_servers(
+                InprocServer32="C:\\Users\\bob\\AppData\\Roaming\\evil.dll"),
+            "{00000004-0000-0000-0000-000000000004}": com_servers(InprocServer32="C:\\Tools\\inproc.dll"),
+            "{00000005-0000-0000-0000-000000000005}": com_servers(InprocServer32="c:\\program files\\same\\same.dll"),
+            # Registered in the 32-bit view only on the machine side: nothing to shadow here
+            "{00000006-0000-0000-0000-000000000006}": com_servers(InprocServer32="C:\\Tools\\x64.dll"),
+            # Not registered by the machine at all
+            "{00000007-0000-0000-0000-000000000007}": com_servers(InprocServer32="C:\\Program Files\\Own\\own.dll"),
+        },
+        "Wow6432Node": {"CLSID": {
+            "{00000006-0000-0000-0000-000000000006}": com_servers(InprocServer32="C:\\Tools\\wow.dll"),
+        }},
+    })
+
+
+def software_hooks(tampered):
+    """Winlogon, IFEO, SilentProcessExit and AppInit as on a stock install, or with an entry added to each."""
+    winlogon = {"values": [
+        sz("Userinit", "C:\\Windows\\system32\\userinit.exe,"
+           + ("C:\\Users\\Public\\u.exe" if tampered else "")),
+        sz("Shell", "explorer.exe, C:\\ProgramData\\sh.exe" if tampered else "Explorer.exe"),
+    ], "Notify": {"crypt32chain": {"values": [sz("DllName", "crypt32.dll")]}}}
+    ifeo = {"notepad.exe": {"values": [dword("GlobalFlag", 0x200)]}}
+    silent_exit = {"notepad.exe": {"values": [dword("ReportingMode", 1)]}}
+    appinit = {"values": [sz("AppInit_DLLs", ""), dword("LoadAppInit_DLLs", 0)]}
+    wow_ifeo = {}
+    if tampered:
+        winlogon["Notify"]["evilnt"] = {"values": [sz("DllName", "evilnt.dll")]}
+        ifeo["sethc.exe"] = {"values": [sz("Debugger", "C:\\Windows\\System32\\cmd.exe"), dword("GlobalFlag", 0x200)]}
+        silent_exit["notepad.exe"]["values"].append(sz("MonitorProcess", "C:\\Users\\Public\\m.exe"))
+        appinit = {"values": [sz("AppInit_DLLs", "C:\\Tools\\a.dll, b.dll"), dword("LoadAppInit_DLLs", 1),
+                              dword("RequireSignedAppInit_DLLs", 0)]}
+        wow_ifeo["utilman.exe"] = {"values": [sz("Debugger", '"C:\\Tools\\dbg.exe" -x')]}
+    return HiveBuilder().hive({
+        "Microsoft": {"Windows NT": {"CurrentVersion": {
+            "Winlogon": winlogon,
+            "Image File Execution Options": ifeo,
+            "SilentProcessExit": silent_exit,
+            "Windows": appinit,
+        }}},
+        "Wow6432Node": {"Microsoft": {"Windows NT": {"CurrentVersion": {"Image File Execution Options": wow_ifeo}}}},
+    })
+
+
+def ntuser_winlogon():
+    # Any per-user Shell replaces Explorer for that user, even a stock-looking one
+    winlogon = {"values": [sz("Shell", "explorer.exe")]}
+    return HiveBuilder().hive({"Software": {"Microsoft": {"Windows NT": {"CurrentVersion": {"Winlogon": winlogon}}}}})
+
+
+def system_hooks(tampered):
+    """ControlSet001\\Control with AppCertDlls, LSA packages and print monitors, stock or with an entry added to each."""
+    extra = (lambda *items: items) if tampered else (lambda *items: ())
+    appcert = {"values": [sz("cert", "C:\\Tools\\cert.dll")]} if tampered else {}
+    monitors = {
+        "Local Port": {"values": [sz("Driver", "localspl.dll")]},
+        "Standard TCP/IP Port": {"values": [sz("Driver", "TCPMON.DLL")]},
+        "USB Monitor": {"values": [sz("Driver", "C:\\Windows\\System32\\usbmon.dll")]},
+    }
+    if tampered:
+        monitors["Evil Port"] = {"values": [sz("Driver", "evilmon.dll")]}
+    return HiveBuilder().hive({"ControlSet001": {"Control": {
+        "Session Manager": {"AppCertDlls": appcert},
+        "Lsa": {
+            "values": [
+                multi_sz("Authentication Packages", "msv1_0", *extra("C:\\Tools\\auth.dll")),
+                multi_sz("Notification Packages", "scecli", "rassfm", *extra("pwfilter")),
+                multi_sz("Security Packages", "kerberos", "msv1_0", "schannel", "wdigest", "tspkg", "pku2u", '""'),
+            ],
+            "OSConfig": {"values": [multi_sz("Security Packages", "kerberos", "msv1_0", *extra("mimilib"))]},
+        },
+        "Print": {"Monitors": monitors},
+    }}})
+
+
+def system_services():
+    """Select\\Current points at ControlSet002; ControlSet001 is a stale set that must not be read."""
+    svchost = sz("ImagePath", "%SystemRoot%\\system32\\svchost.exe -k netsvcs -p", REG_EXPAND_SZ)
+    current = {
+        "EvilSvc": {
+            "values": [svchost, dword("Start", 2), dword("Type", 0x20), sz("ObjectName", "LocalSystem"),
+                       sz("FailureCommand", "C:\\Windows\\Temp\\fail.exe"),
+                       # Parameters\\ServiceDll wins over a ServiceDll on the service key
+                       sz("ServiceDll", "C:\\Windows\\System32\\old.dll")],
+            "Parameters": {"values": [sz("ServiceDll", "C:\\ProgramData\\evil.dll", REG_EXPAND_SZ)]},
+        },
+        "MyApp": {"values": [sz("ImagePath", "C:\\Program Files\\My App\\svc.exe"), sz("DisplayName", "My App"),
+                             dword("Start", 3), dword("Type", 0x10)]},
+        "mydrv": {"values": [sz("ImagePath", "\\SystemRoot\\System32\\drivers\\mydrv.sys", REG_EXPAND_SZ),
+                             dword("Start", 0), dword("Type", 0x1)]},
+        "DllOnly": {"values": [sz("ServiceDll", "C:\\Tools\\only.dll"), dword("Start", 7), dword("Type", 0x400)]},
+        # No ImagePath or ServiceDll: not a service that can run anything
+        "Empty": {"values": [dword("Start", 4)]},
+    }
+    stale = {"Stale": {"values": [sz("ImagePath", "C:\\Stale\\stale.exe")]}}
+    return HiveBuilder().hive({
+        "Select": {"values": [dword("Current", 2), dword("Default", 2), dword("LastKnownGood", 1)]},
+        "ControlSet001": {"Services": stale},
+        "ControlSet002": {"Services": current},
+    })
+
+
+def regf():
+    hive = ntuser_run()
+    write("regf/ntuser_run.dat", hive)
+    # Cut inside the hive bin: the base block still parses, the key tree does not
+    write("regf/ntuser_truncated.dat", hive[:0x1000 + 0x200])
+    write("regf/ntuser_corrupt_cell.dat", ntuser_run(corrupt_cell=True))
+    write("regf/software_com.dat", software_com())
+    write("regf/usrclass_com.dat", usrclass_com())
+    write("regf/software_hooks_stock.dat", software_hooks(tampered=False))
+    write("regf/software_hooks.dat", software_hooks(tampered=True))
+    write("regf/ntuser_winlogon.dat", ntuser_winlogon())
+    write("regf/system_hooks_stock.dat", system_hooks(tampered=False))
+    write("regf/system_hooks.dat", system_hooks(tampered=True))
+    write("regf/system_services.dat", system_services())
+
+
+# --- Scheduled task XML (src/parsers/task_xml.rs) ---------------------------------------------
+
+def tasks():
+    # Windows writes the task store as UTF-16LE with a BOM; the UTF-8 files are checked in as-is
+    with open(os.path.join(HERE, "tasks/logon_utf8.xml"), encoding="utf-8") as f:
+        xml = f.read().replace('encoding="UTF-8"', 'encoding="UTF-16"').replace("\n", "\r\n")
+    write("tasks/logon_utf16.xml", b"\xff\xfe" + utf16(xml))
+
+
+# --- Shell links (src/parsers/lnk.rs) ----------------------------------------------------------
+
+LINK_CLSID = bytes([0x01, 0x14, 0x02, 0, 0, 0, 0, 0, 0xC0, 0, 0, 0, 0, 0, 0, 0x46])
+HAS_LINK_TARGET_ID_LIST, HAS_LINK_INFO, HAS_NAME, HAS_RELATIVE_PATH = 0x01, 0x02, 0x04, 0x08
+HAS_WORKING_DIR, HAS_ARGUMENTS, HAS_ICON_LOCATION, IS_UNICODE = 0x10, 0x20, 0x40, 0x80
+HAS_EXP_STRING = 0x200
+
+
+def link_info(base, suffix="", unicode_base=None):
+    """LinkInfo with a VolumeID and LocalBasePath; unicode_base adds the Unicode offsets."""
+    header_size = 0x24 if unicode_base is not None else 0x1C
+    volume = struct.pack("<IIII", 0x14, 3, 0x1234ABCD, 0x10) + b"OS\0\0"
+    body = volume
+    base_offset = header_size + len(body)
+    body += base.encode("latin-1") + b"\0"
+    suffix_offset = header_size + len(body)
+    body += suffix.encode("latin-1") + b"\0"
+    extra = b""
+    if unicode_base is not None:
+        base_unicode = header_size + len(body)
+        body += utf16z(unicode_base)
+        suffix_unicode = header_size + len(body)
+        body += utf16z(suffix)
+        extra = struct.pack("<II", base_unicode, suffix_unicode)
+    header = struct.pack("<IIIIIII", header_size + len(body), header_size, 0x01, header_size, base_offset, 0,
+                         suffix_offset) + extra
+    return header + body
+
+
+def shell_link(info=None, name=None, relative_path=None, working_dir=None, arguments=None, icon=None,
+               environment=None, id_list=True, unicode=True):
+    flags = IS_UNICODE if unicode else 0
+    body = b""
+    if id_list:
+        # One opaque shell item plus the terminator; parsers only need to skip it
+        items = struct.pack("<H", 6) + b"\x1f\x50\x00\x00" + b"\0\0"
+        body += struct.pack("<H", len(items)) + items
+        flags |= HAS_LINK_TARGET_ID_LIST
+    if info is not None:
+        body += info
+        flags |= HAS_LINK_INFO
+    for flag, value in [(HAS_NAME, name), (HAS_RELATIVE_PATH, relative_path), (HAS_WORKING_DIR, working_dir),
+                        (HAS_ARGUMENTS, arguments), (HAS_ICON_LOCATION, icon)]:
+        if value is not None:
+            flags |= flag
+            raw = utf16(value) if unicode else value.encode("latin-1")
+            body += struct.pack("<H", len(value)) + raw
+    if environment is not None:
+        flags |= HAS_EXP_STRING
+        ansi = environment.encode("latin-1").ljust(260, b"\0")
+        wide = utf16(environment).ljust(520, b"\0")
+        body += struct.pack("<II", 8 + 260 + 520, 0xA0000001) + ansi + wide
+    body += struct.pack("<I", 0)
+    header = bytearray(0x4C)
+    struct.pack_into("<I", header, 0, 0x4C)
+    header[4:20] = LINK_CLSID
+    struct.pack_into("<II", header, 0x14, flags, 0x20)
+    struct.pack_into("<I", header, 0x3C, 1)
+    return bytes(header) + body
+
+
+def lnk():
+    write("lnk/local_path.lnk", shell_link(
+        info=link_info("C:\\Program Files\\Contoso\\start.bat"),
+        working_dir="C:\\Program Files\\Contoso",
+    ))
+    write("lnk/relative_path.lnk", shell_link(
+        relative_path="..\\..\\..\\..\\Tools\\sync tool.cmd",
+        working_dir="C:\\Tools",
+        id_list=False,
+    ))
+    write("lnk/arguments.lnk", shell_link(
+        info=link_info("C:\\Windows\\System32\\WindowsPowerShell\\", "v1.0\\powershell.exe"),
+        name="Updater",
+        arguments="-w hidden -ep bypass -f C:\\Users\\bob\\AppData\\Roaming\\u.ps1",
+        icon="%SystemRoot%\\System32\\shell32.dll",
+        unicode=False,
+    ))
+    write("lnk/unicode.lnk", shell_link(
+        info=link_info("C:\\Users\\J?rgen\\????????\\??????.vbs",
+                       unicode_base="C:\\Users\\Jürgen\\Документы\\запуск.vbs"),
+        arguments="/задача «ночь»",
+    ))
+    write("lnk/environment.lnk", shell_link(
+        environment="%windir%\\system32\\cmd.exe",
+        arguments="/c start /min C:\\ProgramData\\x.bat",
+    ))
+    good = shell_link(info=link_info("C:\\a.exe"))
+    write("lnk/bad_header.lnk", good[:4] + b"\0" * 16 + good[20:])
+    # LinkInfo claims more bytes than the file has
+    write("lnk/truncated.lnk", good[:0x4C + 10 + 0x30])
+
+
+# --- WMI repository pages (src/parsers/wmi.rs) --------------------------------------------------
+
+# Binary noise that carves to nothing, neither as ASCII nor as UTF-16LE
+NOISE = b"\x01\x80\x02\x91\x00\x00\x13\x88"
+
+
+def wmi_record(*strings, wide=False):
+    """An instance record: property strings separated by small binary headers."""
+    out = NOISE * 4
+    for text in strings:
+        raw = utf16z(text) if wide else text.encode("latin-1") + b"\0"
+        out += struct.pack("<I", len(raw)) + raw + NOISE
+    return out
+
+
+def wmi_page(*records):
+    """Records padded out like repository pages, so unrelated ones fall outside the carver's window."""
+    page = b""
+    for record in records:
+        page += record + NOISE * ((0x2000 - len(record)) // len(NOISE))
+    return page
+
+
+def wmi():
+    filter_query = "SELECT * FROM __InstanceModificationEvent WITHIN 60 WHERE TargetInstance ISA " \
+                   "'Win32_PerfFormattedData_PerfOS_System' AND TargetInstance.SystemUpTime >= 240"
+    # Consumer instance, then the binding that ties it to its filter, then the filter
+    write("wmi/commandline_consumer.bin", wmi_page(
+        wmi_record("Updater", "\\\\.\\root\\subscription", "HKEY_LOCAL_MACHINE\\SOFTWARE\\Contoso\\Updater",
+                   "C:\\Windows\\System32\\WindowsPowerShell\\v1.0\\powershell.exe -nop -w hidden -enc SQBFAFgA",
+                   "C:\\Windows\\System32"),
+        wmi_record('CommandLineEventConsumer.Name="Updater"', '__EventFilter.Name="UptimeTrigger"'),
+        wmi_record("UptimeTrigger", "root\\cimv2", "WQL", filter_query),
+    ))
+    write("wmi/active_script_consumer.bin", wmi_page(
+        wmi_record("Beacon", "VBScript",
+                   'Set s = CreateObject("WScript.Shell")\r\ns.Run "C:\\ProgramData\\beacon.exe", 0, False'),
+        wmi_record('ActiveScriptEventConsumer.Name="Beacon"', '__EventFilter.Name="LogonTrigger"'),
+        wmi_record("LogonTrigger", "root\\cimv2", "WQL",
+                   "SELECT * FROM __InstanceCreationEvent WITHIN 15 WHERE TargetInstance ISA 'Win32_LogonSession'"),
+    ))
+    # Filter and binding stored as UTF-16, next to a binding of a consumer that runs no code
+    write("wmi/filter_binding.bin", wmi_page(
+        wmi_record('NTEventLogEventConsumer.Name="SCM Event Log Consumer"', '__EventFilter.Name="SCM Event Log Filter"'),
+        wmi_record('CommandLineEventConsumer.Name=\\"Sync\\"', '__EventFilter.Name=\\"Every5Min\\"', wide=True),
+        wmi_record("Every5Min", "root\\cimv2", "WQL",
+                   "SELECT * FROM __TimerEvent WHERE TimerID = 'Every5Min'", wide=True),
+        wmi_record("Sync", "cmd.exe /c C:\\Tools\\sync.bat", "C:\\Tools", wide=True),
+    ))
+    # A consumer instance nothing is bound to (no reference strings anywhere)
+    write("wmi/unbound_consumer.bin", wmi_page(
+        wmi_record("Dormant", "C:\\Users\\Public\\dormant.exe --install", "C:\\Users\\Public"),
+    ))
+
+
+# --- macOS property lists and bookmarks (src/parsers/launchd.rs, bookmark.rs) -------------------
+
+BOOKMARK_STRING, BOOKMARK_ARRAY = 0x0101, 0x0601
+BOOKMARK_PATH, BOOKMARK_VOLUME_NAME = 0x1004, 0x2010
+
+
+def bookmark(path):
+    """Bookmark data with two chained tables of contents: the volume name, then the path components."""
+    area = bytearray(4)  # offset of the first TOC, set below
+
+    def item(kind, payload):
+        offset = len(area)
+        area.extend(struct.pack("<II", len(payload), kind) + payload)
+        area.extend(b"\0" * (-len(area) % 4))
+        return offset
+
+    def toc(entries, next_toc):
+        offset = len(area)
+        body = struct.pack("<IIII", 0xFFFFFFFE, 1, next_toc, len(entries))
+        body += b"".join(struct.pack("<III", key, item_offset, 0) for key, item_offset in entries)
+        area.extend(struct.pack("<I", len(body)) + body)
+        return offset
+
+    parts = [item(BOOKMARK_STRING, p.encode("utf-8")) for p in path.strip("/").split("/")]
+    components = item(BOOKMARK_ARRAY, b"".join(struct.pack("<I", o) for o in parts))
+    volume = item(BOOKMARK_STRING, b"Macintosh HD")
+    second = toc([(BOOKMARK_PATH, components)], 0)
+    first = toc([(BOOKMARK_VOLUME_NAME, volume)], second)
+    struct.pack_into("<I", area, 0, first)
+    header = b"book" + struct.pack("<III", 0x30 + len(area), 0x10040000, 0x30)
+    return header.ljust(0x30, b"\0") + bytes(area)
+
+
+def plist(rel, value, binary=True):
+    write(rel, plistlib.dumps(value, fmt=plistlib.FMT_BINARY if binary else plistlib.FMT_XML))
+
+
+def macos():
+    app = bookmark("/Applications/Evil Updater.app")
+    write("macos/bookmark/app.book", app)
+    # Cut inside the first table of contents, which is stored last
+    write("macos/bookmark/truncated.book", app[:len(app) - 24])
+
+    plist("macos/launchd/agent_xml.plist", {
+        "Label": "com.example.agent",
+        "ProgramArguments": ["/Users/bob/Library/.hidden/agent", "--daemon", "two words"],
+        "RunAtLoad": True,
+        "KeepAlive": {"SuccessfulExit": False, "NetworkState": True},
+        "StartInterval": 600,
+    }, binary=False)
+    plist("macos/launchd/daemon_binary.plist", {
+        "Label": "com.example.helper",
+        "Program": "/usr/local/libexec/helper",
+        "ProgramArguments": ["helper", "-v"],
+        "KeepAlive": True,
+        "UserName": "root",
+        "Disabled": True,
+        "WatchPaths": ["/etc/hosts"],
+    })
+    write("macos/launchd/invalid.plist", b"<?xml version=\"1.0\"?><plist><dict><key>Label")
+
+    plist("macos/loginitems.plist", {"SessionItems": {"CustomListItems": [
+        {"Name": "Evil Updater", "Alias": app},
+        {"Alias": bookmark("/Applications/Notes Helper.app")},
+        {"Name": "Broken", "Alias": b"not a bookmark"},
+    ]}})
+    plist("macos/backgrounditems.btm", {
+        "$archiver": "NSKeyedArchiver",
+        "$version": 100000,
+        "$objects": [
+            "$null",
+            app,
+            "file:///Users/bob/Library/Application%20Support/Sync/Sync%20Agent.app/",
+            # The same target again, as a URL: listed once
+            "file:///Applications/Evil%20Updater.app/",
+            "com.example.sync",
+        ],
+    })
+    plist("macos/loginwindow.plist", {
+        "LoginHook": "/Library/Scripts/login.sh",
+        "AutoLaunchedApplicationDictionary": [{"Path": "/Applications/Legacy.app", "Hide": False}],
+    }, binary=False)
+
+
+if __name__ == "__main__":
+    regf()
+    tasks()
+    lnk()
+    wmi()
+    macos()